@@ -0,0 +1,93 @@
+// triangle.rs
+// Compara las dos formas de sacar los fragmentos de un triángulo que tapa toda la
+// pantalla, como la nave vista muy de cerca: guardarlos en un Vec, como hacía la
+// `triangle` original, y pasarlos después por la prueba de profundidad, o pasarlos apenas se calculan con
+// `triangle_with`, como hace `render`
+//
+//     cargo bench --bench triangle
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use nalgebra_glm::Vec3;
+
+// El programa es un binario, sin biblioteca: se compilan aquí los módulos que
+// necesita el rasterizador
+#[allow(dead_code, unused_imports, reason = "el benchmark usa una parte del módulo y no corre sus pruebas")]
+#[path = "../src/color.rs"]
+mod color;
+#[allow(dead_code, unused_imports, reason = "el benchmark usa una parte del módulo y no corre sus pruebas")]
+#[path = "../src/fragment.rs"]
+mod fragment;
+#[allow(dead_code, unused_imports, reason = "el benchmark usa una parte del módulo y no corre sus pruebas")]
+#[path = "../src/light.rs"]
+mod light;
+#[allow(dead_code, unused_imports, reason = "el benchmark usa una parte del módulo y no corre sus pruebas")]
+#[path = "../src/shadow.rs"]
+mod shadow;
+#[allow(dead_code, unused_imports, reason = "el benchmark usa una parte del módulo y no corre sus pruebas")]
+#[path = "../src/triangle.rs"]
+mod triangle;
+#[allow(dead_code, unused_imports, reason = "el benchmark usa una parte del módulo y no corre sus pruebas")]
+#[path = "../src/utils.rs"]
+mod utils;
+#[allow(dead_code, unused_imports, reason = "el benchmark usa una parte del módulo y no corre sus pruebas")]
+#[path = "../src/vertex.rs"]
+mod vertex;
+
+use fragment::Fragment;
+use triangle::{triangle_with, Rasterizer};
+use vertex::Vertex;
+
+const WIDTH: usize = 1280;
+const HEIGHT: usize = 720;
+
+fn vertex(x: f32, y: f32, z: f32) -> Vertex {
+    Vertex { transformed_position: Vec3::new(x, y, z), ..Vertex::default() }
+}
+
+// Todos los fragmentos en un vector, para recorrerlos después
+fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
+    let mut fragments = Vec::new();
+    triangle_with(v1, v2, v3, Rasterizer::default(), i32::MIN..i32::MAX, i32::MIN..i32::MAX, |fragment| fragments.push(fragment));
+    fragments
+}
+
+fn full_screen(c: &mut Criterion) {
+    // Tapa toda la pantalla, como un triángulo de la nave vista de cerca. Sin lo
+    // que se sale: `triangle` no recorta y dibujaría píxeles que `render` no ve
+    let (w, h) = (WIDTH as f32, HEIGHT as f32);
+    let (a, b, c_) = (vertex(0.0, 0.0, 0.2), vertex(2.0 * w, 0.0, 0.5), vertex(0.0, 2.0 * h, 0.8));
+    let mut depths = vec![f32::INFINITY; WIDTH * HEIGHT];
+    let mut group = c.benchmark_group("triángulo de pantalla completa");
+    group.sample_size(20);
+
+    group.bench_function("Vec de fragmentos", |bench| {
+        bench.iter(|| {
+            depths.fill(f32::INFINITY);
+            for fragment in triangle(&a, &b, &c_) {
+                let (x, y) = (fragment.position.x as usize, fragment.position.y as usize);
+                if x < WIDTH && y < HEIGHT && fragment.depth < depths[y * WIDTH + x] {
+                    depths[y * WIDTH + x] = fragment.depth;
+                }
+            }
+            black_box(&depths);
+        })
+    });
+
+    group.bench_function("fragmentos al vuelo", |bench| {
+        bench.iter(|| {
+            depths.fill(f32::INFINITY);
+            triangle_with(&a, &b, &c_, Rasterizer::default(), 0..WIDTH as i32, 0..HEIGHT as i32, |fragment| {
+                let i = fragment.position.y as usize * WIDTH + fragment.position.x as usize;
+                if fragment.depth < depths[i] {
+                    depths[i] = fragment.depth;
+                }
+            });
+            black_box(&depths);
+        })
+    });
+
+    group.finish();
+}
+
+criterion_group!(benches, full_screen);
+criterion_main!(benches);
@@ -0,0 +1,446 @@
+// args.rs
+// Argumentos de la línea de comandos
+
+use std::collections::HashMap;
+use std::fmt;
+use crate::color::Color;
+use crate::triangle::{CullMode, Winding};
+use crate::light::Shading;
+use crate::model::GroupColors;
+use crate::texture::{FilterMode, WrapMode};
+use crate::Pipeline;
+
+pub const USAGE: &str = "uso: laboratorio3 <modelo> [--scale N] [--width N] [--height N] [--clear-color RRGGBB] [--spin N] [--smooth]
+                  [--near N] [--far N] [--reversed-z] [--cull MODO] [--winding ORDEN] [--two-sided]
+                  [--wire-color RRGGBB] [--wire-width N] [--wire-aa] [--point-size N]
+                  [--threads N] [--pipeline MODO] [--tile-size N]
+                  [--ssaa N] [--shading MODO] [--outline] [--outline-color RRGGBB] [--outline-width N]
+                  [--fog-color RRGGBB] [--shadows] [--shadow-size N] [--shadow-bias N]
+                  [--rim-color RRGGBB] [--rim-power N] [--texture ARCHIVO] [--texture-filter MODO]
+                  [--texture-wrap MODO] [--group-colors COLORES]
+
+  <modelo>              archivo .obj, .stl, .ply, .gltf o .glb
+  --scale N             zoom inicial (1.0 = el modelo ocupa el 80% de la ventana)
+  --width N             ancho de la ventana en píxeles (800 por defecto)
+  --height N            alto de la ventana en píxeles (600 por defecto)
+  --clear-color RRGGBB  color de fondo en hexadecimal (003300 por defecto)
+  --spin N              grados por segundo que gira la vista sin interacción (10 por defecto, 0 lo apaga)
+  --smooth              arranca con el movimiento suave de la cámara (tecla M)
+  --near N              distancia al plano cercano (0.1 por defecto)
+  --far N               distancia al plano lejano (100 por defecto)
+  --reversed-z          guarda la profundidad invertida en el z-buffer (tecla Z)
+  --cull MODO           caras que no se dibujan: back (por defecto), front o none (tecla C)
+  --winding ORDEN       orden de los vértices de las caras de frente: ccw (por defecto) o cw
+  --two-sided           ilumina las dos caras de cada triángulo y no descarta ninguna,
+                        sin importar --cull (tecla Shift+C)
+  --wire-color RRGGBB   color de las aristas en hexadecimal (ffffff por defecto, tecla W)
+  --wire-width N        grosor de las aristas en píxeles (1 por defecto)
+  --wire-aa             aristas suavizadas, de un píxel de grosor
+  --point-size N        lado en píxeles de los puntos, de 1 a 9 (3 por defecto, teclas Shift+1 a 9)
+  --threads N           hilos para dibujar (por defecto uno por núcleo; 1 no usa hilos)
+  --pipeline MODO       reparto entre hilos: bands (franjas, por defecto) o tiles (teselas, tecla N)
+  --tile-size N         lado de las teselas en píxeles (32 por defecto)
+  --ssaa N              supermuestreo: 1 (por defecto), 2 o 4 muestras por lado de cada píxel (tecla X)
+  --shading MODO        dónde se calcula la luz: flat (por cara, por defecto), gouraud (por vértice),
+                        blinn o phong (por píxel, con el brillo de Blinn-Phong o de Phong; tecla H)
+  --outline             arranca con el contorno de las siluetas (tecla 0)
+  --outline-color RRGGBB  color del contorno en hexadecimal (000000 por defecto)
+  --outline-width N     grosor del contorno en píxeles, de 1 a 3 (1 por defecto)
+  --fog-color RRGGBB    color de la niebla en hexadecimal (el del fondo por defecto, tecla Shift+F)
+  --shadows             arranca con la sombra del sol (tecla Shift+Y)
+  --shadow-size N       lado del mapa de sombras en píxeles, de 64 a 4096 (512 por defecto)
+  --shadow-bias N       margen de profundidad de la sombra (0.005 por defecto): más chico
+                        mancha las caras con franjas, más grande despega la sombra
+  --rim-color RRGGBB    arranca con luz de contorno de ese color (tecla Shift+R, 3080FF sin valor)
+  --rim-power N         qué tan pegado al borde queda el contorno (3 por defecto)
+  --texture ARCHIVO     PNG o JPEG del shader de textura (assets/uv.png por defecto, tecla Shift+U)
+  --texture-filter MODO  nearest o bilinear (bilinear por defecto, tecla Shift+B)
+  --texture-wrap MODO   UV fuera de [0, 1]: repeat (repite la imagen, por defecto) o clamp (estira el borde)
+  --group-colors COLORES  pinta cada grupo de un .obj de un color en vez del de su material:
+                        palette (de la paleta) o nombre=RRGGBB,... (los que falten, de la paleta)";
+
+#[derive(Debug, Clone)]
+pub struct Args {
+    pub model_path: String,
+    pub scale: f32,
+    pub width: usize,
+    pub height: usize,
+    pub clear_color: Color,
+    pub wire_color: Color,
+    pub wire_width: usize,
+    pub wire_antialias: bool,
+    pub point_size: usize,
+    pub threads: usize,
+    pub pipeline: Pipeline,
+    pub tile_size: usize,
+    pub ssaa: usize,
+    pub shading: Shading,
+    pub outline: bool,
+    pub outline_color: Color,
+    pub outline_width: usize,
+    pub fog_color: Option<Color>, // Sin valor, el del fondo
+    pub two_sided: bool, // Manda sobre `cull`
+    pub shadows: bool,
+    pub shadow_size: usize,
+    pub shadow_bias: f32,
+    pub rim_color: Option<Color>, // Sin valor, sin contorno al arrancar
+    pub rim_power: f32,
+    pub texture: Option<String>, // Sin valor, la de prueba de `assets`
+    pub texture_filter: Option<FilterMode>, // Sin valor, el de la textura
+    pub texture_wrap: WrapMode,
+    pub group_colors: GroupColors,
+    pub spin_speed: f32, // Grados por segundo del giro automático
+    pub smooth: bool,    // Cámara con amortiguación e inercia
+    pub near: f32,
+    pub far: f32,
+    pub reversed_z: bool,
+    pub cull_mode: CullMode,
+    pub winding: Winding,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgsError {
+    Help, // Se pidió `--help`
+    MissingModelPath,
+    MissingValue(String), // Opción sin su valor, p. ej. `--width` al final
+    InvalidValue { option: String, value: String },
+    UnknownOption(String),
+    ExtraArgument(String), // Un segundo archivo u otro argumento suelto
+}
+
+impl fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ArgsError::Help => write!(f, "{}", USAGE),
+            ArgsError::MissingModelPath => write!(f, "falta la ruta del modelo"),
+            ArgsError::MissingValue(option) => write!(f, "falta el valor de `{}`", option),
+            ArgsError::InvalidValue { option, value } => write!(f, "valor inválido `{}` para `{}`", value, option),
+            ArgsError::UnknownOption(option) => write!(f, "opción desconocida `{}`", option),
+            ArgsError::ExtraArgument(arg) => write!(f, "argumento de más `{}`", arg),
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+impl Args {
+    // `args` sin el nombre del programa
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Self, ArgsError> {
+        let mut model_path = None;
+        let mut scale = 1.0;
+        let mut width = 800;
+        let mut height = 600;
+        let mut clear_color = Color::from_hex(0x003300);
+        let mut wire_color = Color::from_hex(0xFFFFFF);
+        let mut wire_width = 1;
+        let mut wire_antialias = false;
+        let mut point_size = 3;
+        let mut threads = std::thread::available_parallelism().map_or(1, |threads| threads.get());
+        let mut pipeline = Pipeline::default();
+        let mut tile_size = 32;
+        let mut ssaa = 1;
+        let mut shading = Shading::default();
+        let mut outline = false;
+        let mut outline_color = Color::black();
+        let mut outline_width = 1;
+        let mut fog_color = None;
+        let mut two_sided = false;
+        let mut shadows = false;
+        let mut shadow_size = 512;
+        let mut shadow_bias = 0.005;
+        let mut rim_color = None;
+        let mut rim_power = 3.0;
+        let mut texture = None;
+        let mut texture_filter = None;
+        let mut texture_wrap = WrapMode::default();
+        let mut group_colors = GroupColors::default();
+        let mut spin_speed = 10.0;
+        let mut smooth = false;
+        let mut near = 0.1;
+        let mut far = 100.0;
+        let mut reversed_z = false;
+        let mut cull_mode = CullMode::default();
+        let mut winding = Winding::default();
+
+        let mut args = args.into_iter();
+        while let Some(arg) = args.next() {
+            // Acepta tanto `--width 1280` como `--width=1280`
+            let (option, inline_value) = match arg.split_once('=') {
+                Some((option, value)) if arg.starts_with("--") => (option.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let mut value = || inline_value.clone().or_else(|| args.next()).ok_or_else(|| ArgsError::MissingValue(option.clone()));
+            let invalid = |value: &str| ArgsError::InvalidValue { option: option.clone(), value: value.to_string() };
+
+            match option.as_str() {
+                "-h" | "--help" => return Err(ArgsError::Help),
+                "--scale" => {
+                    let value = value()?;
+                    scale = value.parse().ok().filter(|scale: &f32| *scale > 0.0 && scale.is_finite()).ok_or_else(|| invalid(&value))?;
+                }
+                "--width" => {
+                    let value = value()?;
+                    width = value.parse().ok().filter(|width| *width > 0).ok_or_else(|| invalid(&value))?;
+                }
+                "--height" => {
+                    let value = value()?;
+                    height = value.parse().ok().filter(|height| *height > 0).ok_or_else(|| invalid(&value))?;
+                }
+                "--clear-color" => {
+                    let value = value()?;
+                    clear_color = parse_hex_color(&value).ok_or_else(|| invalid(&value))?;
+                }
+                "--spin" => {
+                    let value = value()?;
+                    spin_speed = value.parse().ok().filter(|speed: &f32| speed.is_finite()).ok_or_else(|| invalid(&value))?;
+                }
+                "--smooth" => smooth = true,
+                "--near" => {
+                    let value = value()?;
+                    near = value.parse().ok().filter(|near: &f32| *near > 0.0 && near.is_finite()).ok_or_else(|| invalid(&value))?;
+                }
+                "--far" => {
+                    let value = value()?;
+                    far = value.parse().ok().filter(|far: &f32| *far > 0.0 && far.is_finite()).ok_or_else(|| invalid(&value))?;
+                }
+                "--reversed-z" => reversed_z = true,
+                "--cull" => {
+                    let value = value()?;
+                    cull_mode = match value.as_str() {
+                        "none" => CullMode::None,
+                        "back" => CullMode::Back,
+                        "front" => CullMode::Front,
+                        _ => return Err(invalid(&value)),
+                    };
+                }
+                "--winding" => {
+                    let value = value()?;
+                    winding = match value.as_str() {
+                        "ccw" => Winding::CounterClockwise,
+                        "cw" => Winding::Clockwise,
+                        _ => return Err(invalid(&value)),
+                    };
+                }
+                "--two-sided" => two_sided = true,
+                "--wire-color" => {
+                    let value = value()?;
+                    wire_color = parse_hex_color(&value).ok_or_else(|| invalid(&value))?;
+                }
+                "--wire-width" => {
+                    let value = value()?;
+                    wire_width = value.parse().ok().filter(|width| *width > 0).ok_or_else(|| invalid(&value))?;
+                }
+                "--wire-aa" => wire_antialias = true,
+                "--point-size" => {
+                    let value = value()?;
+                    point_size = value.parse().ok().filter(|size| (1..=9).contains(size)).ok_or_else(|| invalid(&value))?;
+                }
+                "--threads" => {
+                    let value = value()?;
+                    threads = value.parse().ok().filter(|threads| *threads > 0).ok_or_else(|| invalid(&value))?;
+                }
+                "--pipeline" => {
+                    let value = value()?;
+                    pipeline = match value.as_str() {
+                        "bands" => Pipeline::Bands,
+                        "tiles" => Pipeline::Tiles,
+                        _ => return Err(invalid(&value)),
+                    };
+                }
+                "--tile-size" => {
+                    let value = value()?;
+                    tile_size = value.parse().ok().filter(|size| *size > 0).ok_or_else(|| invalid(&value))?;
+                }
+                "--ssaa" => {
+                    let value = value()?;
+                    ssaa = value.parse().ok().filter(|ssaa| matches!(ssaa, 1 | 2 | 4)).ok_or_else(|| invalid(&value))?;
+                }
+                "--shading" => {
+                    let value = value()?;
+                    shading = match value.as_str() {
+                        "flat" => Shading::Flat,
+                        "gouraud" => Shading::Gouraud,
+                        "blinn" => Shading::BlinnPhong,
+                        "phong" => Shading::Phong,
+                        _ => return Err(invalid(&value)),
+                    };
+                }
+                "--outline" => outline = true,
+                "--outline-color" => {
+                    let value = value()?;
+                    outline_color = parse_hex_color(&value).ok_or_else(|| invalid(&value))?;
+                }
+                "--outline-width" => {
+                    let value = value()?;
+                    outline_width = value.parse().ok().filter(|width| (1..=3).contains(width)).ok_or_else(|| invalid(&value))?;
+                }
+                "--fog-color" => {
+                    let value = value()?;
+                    fog_color = Some(parse_hex_color(&value).ok_or_else(|| invalid(&value))?);
+                }
+                "--shadows" => shadows = true,
+                "--shadow-size" => {
+                    let value = value()?;
+                    shadow_size = value.parse().ok().filter(|size| (64..=4096).contains(size)).ok_or_else(|| invalid(&value))?;
+                }
+                "--shadow-bias" => {
+                    let value = value()?;
+                    shadow_bias = value.parse().ok().filter(|bias: &f32| bias.is_finite() && *bias >= 0.0).ok_or_else(|| invalid(&value))?;
+                }
+                "--rim-color" => {
+                    let value = value()?;
+                    rim_color = Some(parse_hex_color(&value).ok_or_else(|| invalid(&value))?);
+                }
+                "--rim-power" => {
+                    let value = value()?;
+                    rim_power = value.parse().ok().filter(|power: &f32| power.is_finite() && *power > 0.0).ok_or_else(|| invalid(&value))?;
+                }
+                "--texture" => texture = Some(value()?),
+                "--texture-filter" => {
+                    let value = value()?;
+                    texture_filter = Some(match value.as_str() {
+                        "nearest" => FilterMode::Nearest,
+                        "bilinear" => FilterMode::Bilinear,
+                        _ => return Err(invalid(&value)),
+                    });
+                }
+                "--texture-wrap" => {
+                    let value = value()?;
+                    texture_wrap = match value.as_str() {
+                        "repeat" => WrapMode::Repeat,
+                        "clamp" => WrapMode::Clamp,
+                        _ => return Err(invalid(&value)),
+                    };
+                }
+                "--group-colors" => {
+                    let value = value()?;
+                    group_colors = match value.as_str() {
+                        "palette" => GroupColors::Palette,
+                        _ => GroupColors::Map(parse_group_colors(&value).ok_or_else(|| invalid(&value))?),
+                    };
+                }
+                _ if option.starts_with('-') && option.len() > 1 => return Err(ArgsError::UnknownOption(option)),
+                _ if model_path.is_none() => model_path = Some(arg),
+                _ => return Err(ArgsError::ExtraArgument(arg)),
+            }
+        }
+
+        // El plano lejano tiene que quedar detrás del cercano
+        if far <= near {
+            return Err(ArgsError::InvalidValue { option: "--far".into(), value: far.to_string() });
+        }
+
+        Ok(Args {
+            model_path: model_path.ok_or(ArgsError::MissingModelPath)?,
+            scale,
+            width,
+            height,
+            clear_color,
+            wire_color,
+            wire_width,
+            wire_antialias,
+            point_size,
+            threads,
+            pipeline,
+            tile_size,
+            ssaa,
+            shading,
+            outline,
+            outline_color,
+            outline_width,
+            fog_color,
+            two_sided,
+            shadows,
+            shadow_size,
+            shadow_bias,
+            rim_color,
+            rim_power,
+            texture,
+            texture_filter,
+            texture_wrap,
+            group_colors,
+            spin_speed,
+            smooth,
+            near,
+            far,
+            reversed_z,
+            cull_mode,
+            winding,
+        })
+    }
+}
+
+// "RRGGBB", con o sin `#` adelante
+fn parse_hex_color(value: &str) -> Option<Color> {
+    let hex = value.trim_start_matches('#');
+    u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6).map(Color::from_hex)
+}
+
+// `casco=FF0000,alas=00FF00`: nombre del grupo y color de cada uno
+fn parse_group_colors(value: &str) -> Option<HashMap<String, Color>> {
+    value
+        .split(',')
+        .map(|pair| {
+            let (name, color) = pair.split_once('=')?;
+            Some((name.to_string(), parse_hex_color(color)?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(line: &str) -> Result<Args, ArgsError> {
+        Args::parse(line.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn values_after_an_equals_sign_or_a_space_are_the_same() {
+        for line in [
+            "modelo.obj --width=1280 --clear-color=#102030 --cull=front --texture-wrap=clamp --near=0.5",
+            "--width 1280 --clear-color #102030 modelo.obj --cull front --texture-wrap clamp --near 0.5",
+        ] {
+            let args = parse(line).unwrap();
+            assert_eq!(args.model_path, "modelo.obj", "{}", line);
+            assert_eq!(args.width, 1280, "{}", line);
+            assert_eq!(args.clear_color.to_hex(), 0x102030, "{}", line);
+            assert_eq!(args.cull_mode, CullMode::Front, "{}", line);
+            assert_eq!(args.texture_wrap, WrapMode::Clamp, "{}", line);
+            assert_eq!(args.near, 0.5, "{}", line);
+            // Lo que no se pasa queda con su valor por defecto
+            assert_eq!(args.height, 600, "{}", line);
+            assert!(!args.outline && args.fog_color.is_none(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn group_colors_take_the_palette_or_a_list_of_names() {
+        assert!(matches!(parse("a.obj --group-colors palette").unwrap().group_colors, GroupColors::Palette));
+        match parse("a.obj --group-colors=casco=FF0000,alas=#00FF00").unwrap().group_colors {
+            GroupColors::Map(colors) => {
+                assert_eq!(colors.len(), 2);
+                assert_eq!(colors["casco"].to_hex(), 0xFF0000);
+                assert_eq!(colors["alas"].to_hex(), 0x00FF00);
+            }
+            other => panic!("{:?}", other),
+        }
+        let invalid = ArgsError::InvalidValue { option: "--group-colors".into(), value: "casco=rojo".into() };
+        assert_eq!(parse("a.obj --group-colors casco=rojo").unwrap_err(), invalid);
+    }
+
+    #[test]
+    fn missing_paths_unknown_options_and_missing_values_are_errors() {
+        assert_eq!(parse("").unwrap_err(), ArgsError::MissingModelPath);
+        assert_eq!(parse("--width 640 --smooth").unwrap_err(), ArgsError::MissingModelPath);
+        assert_eq!(parse("a.obj --fast").unwrap_err(), ArgsError::UnknownOption("--fast".into()));
+        assert_eq!(parse("a.obj --fast=1").unwrap_err(), ArgsError::UnknownOption("--fast".into()));
+        assert_eq!(parse("a.obj --width").unwrap_err(), ArgsError::MissingValue("--width".into()));
+        assert_eq!(parse("--texture").unwrap_err(), ArgsError::MissingValue("--texture".into()));
+        // Con `=` el valor vacío es el valor, no se toma el argumento siguiente
+        let invalid = ArgsError::InvalidValue { option: "--width".into(), value: "".into() };
+        assert_eq!(parse("--width= 640 a.obj").unwrap_err(), invalid);
+        assert_eq!(parse("a.obj b.obj").unwrap_err(), ArgsError::ExtraArgument("b.obj".into()));
+        assert_eq!(parse("a.obj --help").unwrap_err(), ArgsError::Help);
+    }
+}
@@ -0,0 +1,432 @@
+// camera.rs
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use nalgebra_glm::{Mat3, Mat4, Quat, Vec2, Vec3};
+use crate::json::{JsonError, Value};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CameraMode {
+    Orbit, // El ojo gira alrededor del punto de mira
+    Fly,   // Primera persona: el punto de mira gira alrededor del ojo
+}
+
+// Margen para que la cámara nunca quede alineada con `up` (look_at degenera)
+const PITCH_LIMIT: f32 = std::f32::consts::FRAC_PI_2 - 0.01;
+
+// La orientación se guarda como cuaternión: girar siempre compone rotaciones y
+// nunca pasa por ángulos de Euler, así que no hay bloqueo de cardán ni saltos
+// al pasar de ±90° de inclinación
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Camera {
+    pub target: Vec3,
+    pub orientation: Quat, // Cámara -> mundo: la cámara mira hacia su -Z local
+    pub distance: f32,     // Del ojo al punto de mira
+}
+
+impl Camera {
+    pub fn new(eye: Vec3, target: Vec3, up: Vec3) -> Self {
+        let forward = (target - eye).try_normalize(f32::EPSILON).unwrap_or_else(|| -Vec3::z());
+        let right = forward.cross(&up).try_normalize(f32::EPSILON).unwrap_or_else(Vec3::x);
+        let up = right.cross(&forward);
+        let axes = Mat3::from_columns(&[right, up, -forward]);
+        Self {
+            target,
+            orientation: nalgebra_glm::quat_normalize(&nalgebra_glm::mat3_to_quat(&axes)),
+            distance: (eye - target).norm().max(f32::EPSILON),
+        }
+    }
+
+    pub fn eye(&self) -> Vec3 {
+        self.target - self.forward() * self.distance
+    }
+
+    // Espacio de mundo -> espacio de cámara (mirando hacia -Z, con Y hacia arriba).
+    // Es el único punto donde la orientación se convierte en matriz
+    pub fn view_matrix(&self) -> Mat4 {
+        let rotation = nalgebra_glm::quat_to_mat4(&nalgebra_glm::quat_conjugate(&self.orientation));
+        rotation * nalgebra_glm::translation(&-self.eye())
+    }
+
+    pub fn distance(&self) -> f32 {
+        self.distance
+    }
+
+    // Acerca o aleja el ojo sobre la misma línea de vista
+    pub fn set_distance(&mut self, distance: f32) {
+        self.distance = distance.max(f32::EPSILON);
+    }
+
+    // Mueve el punto de mira sobre la línea de vista dejando el ojo quieto
+    pub fn refocus(&mut self, distance: f32) {
+        let eye = self.eye();
+        self.set_distance(distance);
+        self.target = eye + self.forward() * self.distance;
+    }
+
+    // Dirección de la vista y ejes de la pantalla en espacio de mundo
+    pub fn forward(&self) -> Vec3 {
+        nalgebra_glm::quat_rotate_vec3(&self.orientation, &-Vec3::z())
+    }
+
+    pub fn right(&self) -> Vec3 {
+        nalgebra_glm::quat_rotate_vec3(&self.orientation, &Vec3::x())
+    }
+
+    pub fn screen_up(&self) -> Vec3 {
+        nalgebra_glm::quat_rotate_vec3(&self.orientation, &Vec3::y())
+    }
+
+    // Desplaza ojo y punto de mira juntos en el plano de la pantalla (unidades de mundo)
+    pub fn pan(&mut self, right: f32, up: f32) {
+        self.target += self.right() * right + self.screen_up() * up;
+    }
+
+    // Mueve el ojo sobre la esfera centrada en `target`: `yaw` gira alrededor del
+    // eje Y del mundo y `pitch` alrededor del eje horizontal de la pantalla
+    pub fn orbit(&mut self, yaw: f32, pitch: f32) {
+        self.rotate_world(nalgebra_glm::quat_angle_axis(yaw, &Vec3::y()));
+        self.rotate_local(nalgebra_glm::quat_angle_axis(-pitch, &Vec3::x()));
+    }
+
+    // Rotación de arcball entre dos posiciones del cursor, dadas en coordenadas
+    // relativas a la esfera virtual (centro del viewport = 0, borde de la esfera = 1)
+    pub fn arcball(&mut self, from: Vec2, to: Vec2) {
+        let (from, to) = (arcball_point(from), arcball_point(to));
+        let axis = from.cross(&to);
+        // Movimientos diminutos dan un eje casi nulo: normalizarlo daría NaN
+        let Some(axis) = axis.try_normalize(1e-6) else { return };
+        let angle = from.dot(&to).clamp(-1.0, 1.0).acos();
+
+        // El eje está en espacio de cámara. Arrastrar gira la escena; la cámara
+        // gira lo mismo en sentido contrario
+        self.rotate_local(nalgebra_glm::quat_angle_axis(-angle, &axis));
+    }
+
+    // Gira la vista sin mover el ojo (modo de vuelo), sin pasar de la vertical
+    pub fn look(&mut self, yaw: f32, pitch: f32) {
+        let eye = self.eye();
+        let current_pitch = self.forward().y.clamp(-1.0, 1.0).asin();
+        let pitch = (current_pitch + pitch).clamp(-PITCH_LIMIT, PITCH_LIMIT) - current_pitch;
+
+        self.rotate_world(nalgebra_glm::quat_angle_axis(yaw, &Vec3::y()));
+        self.rotate_local(nalgebra_glm::quat_angle_axis(pitch, &Vec3::x()));
+        self.target = eye + self.forward() * self.distance;
+    }
+
+    // Traslada ojo y punto de mira: `forward` y `right` siguen la vista y `up` es el eje Y
+    pub fn fly(&mut self, forward: f32, right: f32, up: f32) {
+        self.target += self.forward() * forward + self.right() * right + Vec3::y() * up;
+    }
+
+    // Acerca la cámara a `goal` una fracción `t` (0 = no se mueve, 1 = llega).
+    // Repetido en cada cuadro da un acercamiento exponencial suave
+    pub fn approach(&mut self, goal: &Camera, t: f32) {
+        let t = t.clamp(0.0, 1.0);
+        self.target += (goal.target - self.target) * t;
+        self.distance += (goal.distance - self.distance) * t;
+        // q y -q son la misma rotación: se interpola por el camino corto
+        let goal_orientation = if self.orientation.dot(&goal.orientation) < 0.0 { -goal.orientation } else { goal.orientation };
+        self.orientation = nalgebra_glm::quat_normalize(&nalgebra_glm::quat_lerp(&self.orientation, &goal_orientation, t));
+    }
+
+    // Los giros se componen sobre `orientation`. La órbita alrededor de `target`
+    // sale sola porque el ojo se deriva de la orientación y la distancia.
+    // Se renormaliza en cada paso para que el error de redondeo no se acumule
+    fn rotate_local(&mut self, rotation: Quat) {
+        self.orientation = nalgebra_glm::quat_normalize(&(self.orientation * rotation));
+    }
+
+    fn rotate_world(&mut self, rotation: Quat) {
+        self.orientation = nalgebra_glm::quat_normalize(&(rotation * self.orientation));
+    }
+}
+
+// Proyecta un punto del viewport sobre la esfera unitaria; fuera de ella se
+// ajusta a la silueta (el ecuador visto de frente)
+fn arcball_point(point: Vec2) -> Vec3 {
+    let length_squared = point.norm_squared();
+    if length_squared <= 1.0 {
+        Vec3::new(point.x, point.y, (1.0 - length_squared).sqrt())
+    } else {
+        let edge = point / length_squared.sqrt();
+        Vec3::new(edge.x, edge.y, 0.0)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ProjectionMode {
+    Perspective,
+    Orthographic, // Sin escorzo, como en los visores CAD
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Projection {
+    pub mode: ProjectionMode,
+    pub fov_y: f32,        // Campo de visión vertical en radianes (perspectiva)
+    pub ortho_height: f32, // Alto visible en unidades de mundo (ortográfica)
+    pub aspect: f32,       // Ancho / alto
+    pub near: f32,
+    pub far: f32,
+}
+
+impl Projection {
+    pub fn new(fov_y: f32, aspect: f32, near: f32, far: f32) -> Self {
+        Self { mode: ProjectionMode::Perspective, fov_y, ortho_height: 2.0, aspect, near, far }
+    }
+
+    // Espacio de cámara -> coordenadas de recorte (clip space)
+    pub fn matrix(&self) -> Mat4 {
+        match self.mode {
+            ProjectionMode::Perspective => nalgebra_glm::perspective(self.aspect, self.fov_y, self.near, self.far),
+            ProjectionMode::Orthographic => {
+                let (half_width, half_height) = (self.ortho_height * self.aspect * 0.5, self.ortho_height * 0.5);
+                nalgebra_glm::ortho(-half_width, half_width, -half_height, half_height, self.near, self.far)
+            }
+        }
+    }
+
+    pub fn toggle_mode(&mut self) {
+        self.mode = match self.mode {
+            ProjectionMode::Perspective => ProjectionMode::Orthographic,
+            ProjectionMode::Orthographic => ProjectionMode::Perspective,
+        };
+    }
+
+    // Alto que ve la perspectiva a `distance` del ojo; usarlo como `ortho_height`
+    // mantiene del mismo tamaño lo que está en el punto de mira al cambiar de modo
+    pub fn view_height_at(&self, distance: f32) -> f32 {
+        2.0 * distance * (self.fov_y * 0.5).tan()
+    }
+
+    // Alto visible en el punto de mira según el modo actual
+    pub fn visible_height(&self, distance: f32) -> f32 {
+        match self.mode {
+            ProjectionMode::Perspective => self.view_height_at(distance),
+            ProjectionMode::Orthographic => self.ortho_height,
+        }
+    }
+
+    // Distancia a la que un objeto de tamaño `size` ocupa `fraction` del alto de la ventana
+    pub fn fit_distance(&self, size: f32, fraction: f32) -> f32 {
+        // Se suma medio tamaño porque la cara frontal del objeto está más cerca que su centro
+        size * 0.5 / (fraction * (self.fov_y * 0.5).tan()) + size * 0.5
+    }
+}
+
+// Versión del formato de `ViewState`; subirla si cambian los campos
+pub const VIEW_STATE_VERSION: usize = 1;
+
+// Vista guardada en disco para recuperar un encuadre entre ejecuciones
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ViewState {
+    pub camera: Camera,
+    pub camera_mode: CameraMode,
+    pub projection_mode: ProjectionMode,
+    pub scale: f32, // Zoom
+    pub shader: usize, // Shader de fragmentos elegido, por su índice en `ShaderRegistry`
+}
+
+#[derive(Debug)]
+pub enum ViewStateError {
+    Io(io::Error),
+    Json(JsonError),
+    Version(Option<usize>), // Archivo de otra versión (None si no la indica)
+    Invalid(&'static str),  // Campo ausente o con un valor imposible
+}
+
+impl fmt::Display for ViewStateError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ViewStateError::Io(err) => write!(f, "error de lectura: {}", err),
+            ViewStateError::Json(err) => write!(f, "{}", err),
+            ViewStateError::Version(Some(version)) => write!(f, "versión {} no soportada (se esperaba {})", version, VIEW_STATE_VERSION),
+            ViewStateError::Version(None) => write!(f, "falta la versión"),
+            ViewStateError::Invalid(field) => write!(f, "campo `{}` inválido", field),
+        }
+    }
+}
+
+impl std::error::Error for ViewStateError {}
+
+impl From<io::Error> for ViewStateError {
+    fn from(err: io::Error) -> Self {
+        ViewStateError::Io(err)
+    }
+}
+
+impl From<JsonError> for ViewStateError {
+    fn from(err: JsonError) -> Self {
+        ViewStateError::Json(err)
+    }
+}
+
+impl ViewState {
+    pub fn to_json(self) -> String {
+        let numbers = |values: &[f32]| Value::Array(values.iter().map(|&v| Value::Number(v as f64)).collect());
+        let camera_mode = match self.camera_mode {
+            CameraMode::Orbit => "orbit",
+            CameraMode::Fly => "fly",
+        };
+        let projection_mode = match self.projection_mode {
+            ProjectionMode::Perspective => "perspective",
+            ProjectionMode::Orthographic => "orthographic",
+        };
+        let orientation = self.camera.orientation.coords; // x, y, z, w
+
+        Value::Object(vec![
+            ("version".into(), Value::Number(VIEW_STATE_VERSION as f64)),
+            ("target".into(), numbers(self.camera.target.as_slice())),
+            ("orientation".into(), numbers(orientation.as_slice())),
+            ("distance".into(), Value::Number(self.camera.distance as f64)),
+            ("scale".into(), Value::Number(self.scale as f64)),
+            ("camera_mode".into(), Value::String(camera_mode.into())),
+            ("projection_mode".into(), Value::String(projection_mode.into())),
+            ("shader".into(), Value::Number(self.shader as f64)),
+        ])
+        .to_string()
+    }
+
+    pub fn from_json(source: &str) -> Result<Self, ViewStateError> {
+        let root = Value::parse(source)?;
+        match root.get("version").and_then(Value::as_usize) {
+            Some(VIEW_STATE_VERSION) => {}
+            version => return Err(ViewStateError::Version(version)),
+        }
+
+        let number = |field: &'static str| {
+            root.get(field)
+                .and_then(Value::as_f64)
+                .map(|n| n as f32)
+                .filter(|n| n.is_finite() && *n > 0.0)
+                .ok_or(ViewStateError::Invalid(field))
+        };
+        let finite = |values: &[f32]| values.iter().all(|v| v.is_finite());
+
+        let target = root
+            .get("target")
+            .and_then(Value::as_f32_array::<3>)
+            .filter(|v| finite(v))
+            .ok_or(ViewStateError::Invalid("target"))?;
+        let [x, y, z, w] = root
+            .get("orientation")
+            .and_then(Value::as_f32_array::<4>)
+            .filter(|v| finite(v))
+            .ok_or(ViewStateError::Invalid("orientation"))?;
+        let mut orientation = nalgebra_glm::quat(x, y, z, w);
+        // Un cuaternión nulo no es una rotación; uno editado a mano se renormaliza,
+        // pero el que se guardó tal cual se conserva exacto
+        let length = nalgebra_glm::quat_length(&orientation);
+        if length <= f32::EPSILON {
+            return Err(ViewStateError::Invalid("orientation"));
+        }
+        if (length - 1.0).abs() > 1e-4 {
+            orientation = nalgebra_glm::quat_normalize(&orientation);
+        }
+
+        let camera_mode = match root.get("camera_mode").and_then(Value::as_str) {
+            Some("orbit") => CameraMode::Orbit,
+            Some("fly") => CameraMode::Fly,
+            _ => return Err(ViewStateError::Invalid("camera_mode")),
+        };
+        let projection_mode = match root.get("projection_mode").and_then(Value::as_str) {
+            Some("perspective") => ProjectionMode::Perspective,
+            Some("orthographic") => ProjectionMode::Orthographic,
+            _ => return Err(ViewStateError::Invalid("projection_mode")),
+        };
+
+        Ok(ViewState {
+            camera: Camera {
+                target: Vec3::from(target),
+                orientation,
+                distance: number("distance")?,
+            },
+            camera_mode,
+            projection_mode,
+            scale: number("scale")?,
+            // Las vistas guardadas antes de poder elegir el shader no lo traen
+            shader: root.get("shader").and_then(Value::as_usize).unwrap_or(0),
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        fs::write(path, self.to_json() + "\n")
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, ViewStateError> {
+        Self::from_json(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn view_state() -> ViewState {
+        let mut camera = Camera::new(Vec3::new(1.5, 2.0, 3.25), Vec3::new(0.1, -0.2, 0.3), Vec3::y());
+        camera.set_distance(4.75);
+        ViewState {
+            camera,
+            camera_mode: CameraMode::Fly,
+            projection_mode: ProjectionMode::Orthographic,
+            scale: 1.3,
+            shader: 2,
+        }
+    }
+
+    // JSON de `view_state` con `edit` aplicado a su objeto raíz
+    fn edited(edit: impl FnOnce(&mut Vec<(String, Value)>)) -> String {
+        let Value::Object(mut fields) = Value::parse(&view_state().to_json()).unwrap() else { unreachable!() };
+        edit(&mut fields);
+        Value::Object(fields).to_string()
+    }
+
+    #[test]
+    fn round_trip_is_exact() {
+        let state = view_state();
+        assert_eq!(ViewState::from_json(&state.to_json()).unwrap(), state);
+    }
+
+    #[test]
+    fn other_versions_are_rejected() {
+        let future = edited(|fields| fields[0].1 = Value::Number((VIEW_STATE_VERSION + 1) as f64));
+        assert!(matches!(ViewState::from_json(&future), Err(ViewStateError::Version(Some(v))) if v == VIEW_STATE_VERSION + 1));
+
+        let unversioned = edited(|fields| fields.retain(|(key, _)| key != "version"));
+        assert!(matches!(ViewState::from_json(&unversioned), Err(ViewStateError::Version(None))));
+    }
+
+    #[test]
+    fn missing_fields_are_rejected_except_the_shader() {
+        for field in ["target", "orientation", "distance", "scale", "camera_mode", "projection_mode"] {
+            let source = edited(|fields| fields.retain(|(key, _)| key != field));
+            assert!(matches!(ViewState::from_json(&source), Err(ViewStateError::Invalid(f)) if f == field), "{}", field);
+        }
+
+        let source = edited(|fields| fields.retain(|(key, _)| key != "shader"));
+        assert_eq!(ViewState::from_json(&source).unwrap(), ViewState { shader: 0, ..view_state() });
+    }
+
+    #[test]
+    fn extra_fields_are_ignored() {
+        let source = edited(|fields| fields.push((String::from("comentario"), Value::String(String::from("vista de la nave")))));
+        assert_eq!(ViewState::from_json(&source).unwrap(), view_state());
+    }
+
+    #[test]
+    fn invalid_values_are_rejected() {
+        let with = |field: &str, value: Value| {
+            edited(|fields| fields.iter_mut().find(|(key, _)| key == field).unwrap().1 = value)
+        };
+        let zero_distance = with("distance", Value::Number(0.0));
+        assert!(matches!(ViewState::from_json(&zero_distance), Err(ViewStateError::Invalid("distance"))));
+        let null_rotation = with("orientation", Value::Array(vec![Value::Number(0.0); 4]));
+        assert!(matches!(ViewState::from_json(&null_rotation), Err(ViewStateError::Invalid("orientation"))));
+        let unknown_mode = with("camera_mode", Value::String(String::from("tank")));
+        assert!(matches!(ViewState::from_json(&unknown_mode), Err(ViewStateError::Invalid("camera_mode"))));
+
+        assert!(matches!(ViewState::from_json("{"), Err(ViewStateError::Json(_))));
+    }
+}
@@ -0,0 +1,185 @@
+// clip.rs
+// Recorte de triángulos y segmentos en coordenadas de recorte, antes de dividir por w.
+// Ahí los atributos varían linealmente, así que interpolarlos es exacto
+
+use nalgebra_glm::Vec4;
+use crate::vertex::Vertex;
+
+#[derive(Debug, Clone)]
+pub struct ClipVertex {
+    pub clip: Vec4,     // Posición en coordenadas de recorte
+    pub vertex: Vertex, // Atributos (normal ya transformada, color, UV)
+}
+
+impl ClipVertex {
+    // Punto a una fracción `t` del camino hacia `other`
+    fn lerp(&self, other: &ClipVertex, t: f32) -> ClipVertex {
+        let (a, b) = (&self.vertex, &other.vertex);
+        ClipVertex {
+            clip: self.clip + (other.clip - self.clip) * t,
+            vertex: Vertex {
+                position: a.position + (b.position - a.position) * t,
+                model_position: a.model_position + (b.model_position - a.model_position) * t,
+                normal: a.normal + (b.normal - a.normal) * t,
+                tex_coords: a.tex_coords + (b.tex_coords - a.tex_coords) * t,
+                color: a.color.lerp(b.color, t),
+                ambient: a.ambient.lerp(b.ambient, t),
+                emissive: a.emissive.lerp(b.emissive, t),
+                specular: a.specular.lerp(b.specular, t),
+                shininess: a.shininess + (b.shininess - a.shininess) * t,
+                transformed_position: a.transformed_position + (b.transformed_position - a.transformed_position) * t,
+                transformed_normal: a.transformed_normal + (b.transformed_normal - a.transformed_normal) * t,
+                transformed_w: a.transformed_w + (b.transformed_w - a.transformed_w) * t,
+            },
+        }
+    }
+}
+
+// Distancia con signo a cada plano del frustum: negativa del lado de afuera.
+// Orden: izquierda, derecha, abajo, arriba, cerca, lejos
+const PLANES: [fn(&Vec4) -> f32; 6] = [
+    |clip| clip.w + clip.x,
+    |clip| clip.w - clip.x,
+    |clip| clip.w + clip.y,
+    |clip| clip.w - clip.y,
+    |clip| clip.w + clip.z,
+    |clip| clip.w - clip.z,
+];
+
+// Un bit por cada plano del frustum que deja afuera al punto. Si los vértices de
+// un triángulo comparten un bit, el triángulo entero está afuera de ese plano
+pub fn frustum_outcode(clip: &Vec4) -> u8 {
+    PLANES.iter().enumerate().fold(0, |code, (plane, distance)| code | ((distance(clip) < 0.0) as u8) << plane)
+}
+
+// Recorta un triángulo contra los planos marcados en `planes` (bits como los de
+// `frustum_outcode`). Devuelve el polígono convexo que queda adentro, vacío si no
+// queda nada. Con el plano cercano incluido, todos los vértices tienen w > 0
+pub fn clip_triangle(triangle: [ClipVertex; 3], planes: u8) -> Vec<ClipVertex> {
+    let mut polygon = triangle.to_vec();
+    for (plane, distance) in PLANES.iter().enumerate() {
+        if planes & (1 << plane) != 0 {
+            polygon = clip_polygon(&polygon, distance);
+            if polygon.len() < 3 {
+                return Vec::new();
+            }
+        }
+    }
+    polygon
+}
+
+// Recorta el segmento `a` -> `b` contra los planos de `planes`: se va acortando el
+// intervalo [t0, t1] del segmento que queda adentro de cada plano. `None` si no queda nada
+pub fn clip_line(a: &ClipVertex, b: &ClipVertex, planes: u8) -> Option<(ClipVertex, ClipVertex)> {
+    let (mut t0, mut t1) = (0.0_f32, 1.0_f32);
+    for (plane, distance) in PLANES.iter().enumerate() {
+        if planes & (1 << plane) == 0 {
+            continue;
+        }
+        let (d_a, d_b) = (distance(&a.clip), distance(&b.clip));
+        if d_a < 0.0 && d_b < 0.0 {
+            return None;
+        }
+        let t = d_a / (d_a - d_b);
+        if d_a < 0.0 {
+            t0 = t0.max(t);
+        } else if d_b < 0.0 {
+            t1 = t1.min(t);
+        }
+        if t0 > t1 {
+            return None;
+        }
+    }
+    Some((a.lerp(b, t0), a.lerp(b, t1)))
+}
+
+// Sutherland-Hodgman contra un plano: recorre las aristas conservando los
+// vértices de adentro y agregando el cruce de cada arista que atraviesa el plano
+fn clip_polygon(polygon: &[ClipVertex], distance: impl Fn(&Vec4) -> f32) -> Vec<ClipVertex> {
+    let mut clipped = Vec::with_capacity(polygon.len() + 1);
+    for (index, current) in polygon.iter().enumerate() {
+        let next = &polygon[(index + 1) % polygon.len()];
+        let (d_current, d_next) = (distance(&current.clip), distance(&next.clip));
+
+        if d_current >= 0.0 {
+            clipped.push(current.clone());
+        }
+        if (d_current >= 0.0) != (d_next >= 0.0) {
+            clipped.push(current.lerp(next, d_current / (d_current - d_next)));
+        }
+    }
+    clipped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra_glm::{Mat4, Vec2, Vec3};
+
+    const NEAR: u8 = 1 << 4;
+    const ALL: u8 = 0b11_1111;
+
+    fn projection() -> Mat4 {
+        nalgebra_glm::perspective(1.0, std::f32::consts::FRAC_PI_2, 1.0, 100.0)
+    }
+
+    // Vértice en el espacio de la cámara, con la UV guardando su posición (x, z)
+    // para poder comprobar la interpolación
+    fn clip_vertex(x: f32, y: f32, z: f32) -> ClipVertex {
+        let clip = projection() * Vec4::new(x, y, z, 1.0);
+        ClipVertex { clip, vertex: Vertex { tex_coords: Vec2::new(x, z), ..Vertex::default() } }
+    }
+
+    // Los puntos que deja el recorte están sobre las aristas originales: (x, z) de
+    // su UV debe coincidir con la posición que se desproyecta de `clip`
+    fn assert_attributes_follow_position(polygon: &[ClipVertex]) {
+        let inverse = projection().try_inverse().unwrap();
+        for vertex in polygon {
+            let eye = inverse * vertex.clip;
+            let eye = Vec3::new(eye.x, eye.y, eye.z) / eye.w;
+            assert!((vertex.vertex.tex_coords - Vec2::new(eye.x, eye.z)).norm() < 1e-3, "{:?}", vertex.clip);
+        }
+    }
+
+    #[test]
+    fn triangle_behind_the_near_plane_disappears() {
+        let triangle = [clip_vertex(0.0, 0.0, -0.5), clip_vertex(1.0, 0.0, 2.0), clip_vertex(0.0, 1.0, 0.5)];
+        assert!(clip_triangle(triangle, NEAR).is_empty());
+    }
+
+    #[test]
+    fn one_vertex_inside_leaves_a_triangle() {
+        let triangle = [clip_vertex(0.0, 0.0, -5.0), clip_vertex(1.0, 0.0, 0.5), clip_vertex(-1.0, 0.0, 0.5)];
+        let polygon = clip_triangle(triangle, NEAR);
+        assert_eq!(polygon.len(), 3);
+        assert!(polygon.iter().all(|vertex| vertex.clip.w > 0.0 && vertex.clip.z >= -vertex.clip.w - 1e-5));
+        // Los dos nuevos están sobre el plano cercano (z = -1 en la cámara), a 4 / 5.5 del
+        // camino desde el vértice de adentro
+        let mut crossings: Vec<Vec2> = polygon[1..].iter().map(|vertex| vertex.vertex.tex_coords).collect();
+        crossings.sort_by(|a, b| a.x.total_cmp(&b.x));
+        assert!((crossings[0] - Vec2::new(-8.0 / 11.0, -1.0)).norm() < 1e-4);
+        assert!((crossings[1] - Vec2::new(8.0 / 11.0, -1.0)).norm() < 1e-4);
+        assert_attributes_follow_position(&polygon);
+    }
+
+    #[test]
+    fn two_vertices_inside_leave_a_quad() {
+        let triangle = [clip_vertex(0.0, 0.0, 0.5), clip_vertex(1.0, 0.0, -5.0), clip_vertex(-1.0, 0.0, -5.0)];
+        let polygon = clip_triangle(triangle, NEAR);
+        assert_eq!(polygon.len(), 4);
+        assert!(polygon.iter().all(|vertex| vertex.clip.w > 0.0));
+        assert_attributes_follow_position(&polygon);
+    }
+
+    #[test]
+    fn clipped_vertices_never_have_w_at_or_below_zero() {
+        // Triángulos que atraviesan el plano del ojo en distintas posiciones
+        for step in 0..50 {
+            let t = step as f32 / 10.0 - 2.5;
+            let triangle = [clip_vertex(t, -1.0, -3.0), clip_vertex(-t, 1.0, 1.0 + t), clip_vertex(1.0, t, 2.0)];
+            for polygon in [clip_triangle(triangle.clone(), NEAR), clip_triangle(triangle, ALL)] {
+                assert!(polygon.iter().all(|vertex| vertex.clip.w > 0.0));
+            }
+        }
+    }
+}
@@ -20,11 +20,51 @@ impl Color {
         Color { r: 1.0, g: 0.0, b: 0.0 }
     }
 
-    // Convierte el color a formato hexadecimal
-    pub fn to_hex(&self) -> u32 {
-        let r = (self.r * 255.0) as u32;
-        let g = (self.g * 255.0) as u32;
-        let b = (self.b * 255.0) as u32;
+    // Constructor a partir de un arreglo [r, g, b] (como los de los archivos .mtl)
+    pub fn from_rgb(rgb: [f32; 3]) -> Self {
+        Color { r: rgb[0], g: rgb[1], b: rgb[2] }
+    }
+
+    // Constructor a partir de un valor 0xRRGGBB
+    pub fn from_hex(hex: u32) -> Self {
+        Color {
+            r: ((hex >> 16) & 0xFF) as f32 / 255.0,
+            g: ((hex >> 8) & 0xFF) as f32 / 255.0,
+            b: (hex & 0xFF) as f32 / 255.0,
+        }
+    }
+
+    // Constructor a partir de tono, saturación y valor, los tres en [0, 1]. El tono da
+    // la vuelta: 0 y 1 son el mismo rojo
+    pub fn from_hsv(hue: f32, saturation: f32, value: f32) -> Self {
+        let channel = |offset: f32| {
+            let k = (offset + hue.rem_euclid(1.0) * 6.0) % 6.0;
+            value - value * saturation * k.min(4.0 - k).clamp(0.0, 1.0)
+        };
+        Color { r: channel(5.0), g: channel(3.0), b: channel(1.0) }
+    }
+
+    // Mezcla lineal: `t` = 0 da este color y `t` = 1 da `other`
+    pub fn lerp(self, other: Color, t: f32) -> Self {
+        Color {
+            r: self.r + (other.r - self.r) * t,
+            g: self.g + (other.g - self.g) * t,
+            b: self.b + (other.b - self.b) * t,
+        }
+    }
+
+    // Limita cada canal a [0, 1]; la iluminación puede pasarse de ahí
+    pub fn clamped(self) -> Self {
+        Color { r: self.r.clamp(0.0, 1.0), g: self.g.clamp(0.0, 1.0), b: self.b.clamp(0.0, 1.0) }
+    }
+
+    // Convierte el color a formato hexadecimal. Se limita antes: un canal de más
+    // de 1.0 se saldría de su byte y mancharía el canal vecino
+    pub fn to_hex(self) -> u32 {
+        let color = self.clamped();
+        let r = (color.r * 255.0) as u32;
+        let g = (color.g * 255.0) as u32;
+        let b = (color.b * 255.0) as u32;
         (r << 16) | (g << 8) | b
     }
 }
@@ -39,4 +79,41 @@ impl std::ops::Mul<f32> for Color {
             b: self.b * intensity,
         }
     }
-}
\ No newline at end of file
+}
+// Canal por canal: el color de una superficie bajo una luz de color
+impl std::ops::Mul for Color {
+    type Output = Color;
+
+    fn mul(self, other: Color) -> Self::Output {
+        Color {
+            r: self.r * other.r,
+            g: self.g * other.g,
+            b: self.b * other.b,
+        }
+    }
+}
+
+impl std::ops::Add for Color {
+    type Output = Color;
+
+    fn add(self, other: Color) -> Self::Output {
+        Color {
+            r: self.r + other.r,
+            g: self.g + other.g,
+            b: self.b + other.b,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_hex_clamps_each_channel() {
+        assert_eq!(Color { r: 1.5, g: 0.0, b: 0.0 }.to_hex(), 0xFF0000);
+        assert_eq!(Color { r: 0.0, g: 3.0, b: -1.0 }.to_hex(), 0x00FF00);
+        assert_eq!(Color { r: -0.5, g: -0.5, b: 2.0 }.to_hex(), 0x0000FF);
+        assert_eq!(Color::from_hex(0x123456).to_hex(), 0x123456);
+    }
+}
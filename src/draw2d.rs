@@ -0,0 +1,369 @@
+// draw2d.rs
+// Figuras en 2D sobre la pantalla (círculos, elipses y polígonos) para dibujar guías encima
+// de la escena. No saben nada del framebuffer: entregan píxeles o tramos de fila y
+// quien los recibe los recorta
+
+// Elipse de punto medio, solo con enteros: recorre el primer cuadrante desde (0, `ry`)
+// hasta (`rx`, 0) sin dejar huecos entre un píxel y el siguiente
+fn ellipse_quadrant(rx: i32, ry: i32, mut point: impl FnMut(i32, i32)) {
+    // Sin alto el recorrido de abajo no avanzaría en X
+    if ry == 0 {
+        (0..=rx).for_each(|x| point(x, 0));
+        return;
+    }
+    let (rx2, ry2) = (rx as i64 * rx as i64, ry as i64 * ry as i64);
+    let (mut x, mut y) = (0_i64, ry as i64);
+    let (mut px, mut py) = (0, 2 * rx2 * y);
+
+    // Región 1: la pendiente es menor que 1 y se avanza en X
+    let mut p = ry2 - rx2 * ry as i64 + rx2 / 4;
+    while px < py {
+        point(x as i32, y as i32);
+        x += 1;
+        px += 2 * ry2;
+        if p < 0 {
+            p += ry2 + px;
+        } else {
+            y -= 1;
+            py -= 2 * rx2;
+            p += ry2 + px - py;
+        }
+    }
+
+    // Región 2: la pendiente pasa de 1 y se avanza en Y
+    let mut p = ry2 * (x * x + x) + ry2 / 4 + rx2 * (y - 1) * (y - 1) - rx2 * ry2;
+    while y >= 0 {
+        point(x as i32, y as i32);
+        y -= 1;
+        py -= 2 * rx2;
+        if p > 0 {
+            p += rx2 - py;
+        } else {
+            x += 1;
+            px += 2 * ry2;
+            p += rx2 - py + px;
+        }
+    }
+}
+
+// Medio ancho de la elipse en cada fila, de la del centro (0) a la de arriba (`ry`)
+fn half_widths(rx: i32, ry: i32) -> Vec<i32> {
+    let mut widths = vec![0; ry as usize + 1];
+    ellipse_quadrant(rx, ry, |x, y| widths[y as usize] = widths[y as usize].max(x));
+    widths
+}
+
+// Contorno de la elipse centrada en (`cx`, `cy`). Con `thickness` mayor que 1 el
+// anillo se rellena por filas entre la elipse de afuera y una más chica: dibujar
+// varios contornos uno dentro de otro dejaría huecos entre ellos
+pub fn ellipse(cx: i32, cy: i32, rx: i32, ry: i32, thickness: usize, mut plot: impl FnMut(i32, i32)) {
+    let (rx, ry) = (rx.max(0), ry.max(0));
+    if thickness <= 1 {
+        ellipse_quadrant(rx, ry, |x, y| {
+            plot(cx + x, cy + y);
+            plot(cx - x, cy + y);
+            plot(cx + x, cy - y);
+            plot(cx - x, cy - y);
+        });
+        return;
+    }
+
+    let thickness = thickness as i32;
+    let outer = half_widths(rx, ry);
+    // Si el grosor llega al centro el anillo es la elipse llena
+    let inner = if thickness <= rx.min(ry) { half_widths(rx - thickness, ry - thickness) } else { Vec::new() };
+    let mut span = |y: i32, from: i32, to: i32| (from..=to).for_each(|x| plot(x, y));
+    for (dy, &width) in outer.iter().enumerate() {
+        let dy = dy as i32;
+        let rows = [cy - dy, cy + dy];
+        for &y in &rows[..if dy == 0 { 1 } else { 2 }] {
+            match inner.get(dy as usize) {
+                Some(&hole) => {
+                    span(y, cx - width, cx - hole - 1);
+                    span(y, cx + hole + 1, cx + width);
+                }
+                None => span(y, cx - width, cx + width),
+            }
+        }
+    }
+}
+
+pub fn circle(cx: i32, cy: i32, radius: i32, thickness: usize, plot: impl FnMut(i32, i32)) {
+    ellipse(cx, cy, radius, radius, thickness, plot)
+}
+
+// Elipse llena: entrega un tramo (fila, desde X, hasta X) por fila, que se pinta
+// de una vez
+pub fn fill_ellipse(cx: i32, cy: i32, rx: i32, ry: i32, mut span: impl FnMut(i32, i32, i32)) {
+    for (dy, &width) in half_widths(rx.max(0), ry.max(0)).iter().enumerate() {
+        let dy = dy as i32;
+        span(cy - dy, cx - width, cx + width);
+        if dy > 0 {
+            span(cy + dy, cx - width, cx + width);
+        }
+    }
+}
+
+pub fn fill_circle(cx: i32, cy: i32, radius: i32, span: impl FnMut(i32, i32, i32)) {
+    fill_ellipse(cx, cy, radius, radius, span)
+}
+
+// Polígono lleno, cóncavo o cruzado: se recorren las filas de `rows` y en cada una
+// se cortan las aristas activas a la altura del centro de los píxeles. Por la regla
+// par-impar, entre el primer corte y el segundo hay adentro, entre el segundo y el
+// tercero afuera, y así (en una estrella el pentágono del centro queda vacío). Se
+// muestrea igual que `triangle`: el centro del píxel
+pub fn fill_polygon(points: &[(f32, f32)], rows: std::ops::Range<i32>, mut span: impl FnMut(i32, i32, i32)) {
+    if points.len() < 3 || points.iter().any(|&(x, y)| !x.is_finite() || !y.is_finite()) {
+        return;
+    }
+
+    // Aristas como (y de arriba, y de abajo, x en la de arriba, cuánto avanza x por
+    // fila), ordenadas por donde empiezan. Las horizontales no cortan ninguna fila
+    let mut edges: Vec<(f32, f32, f32, f32)> = points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .filter(|((_, y0), (_, y1))| y0 != y1)
+        .map(|(&(x0, y0), &(x1, y1))| {
+            let ((x0, y0), (x1, y1)) = if y0 < y1 { ((x0, y0), (x1, y1)) } else { ((x1, y1), (x0, y0)) };
+            (y0, y1, x0, (x1 - x0) / (y1 - y0))
+        })
+        .collect();
+    edges.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+    let top = edges.first().map_or(0.0, |edge| edge.0);
+    let bottom = edges.iter().fold(f32::MIN, |bottom, edge| bottom.max(edge.1));
+    let first_row = rows.start.max((top - 0.5).ceil() as i32);
+    let last_row = rows.end.min((bottom - 0.5).ceil() as i32);
+
+    // Tabla de aristas activas: las que cruzan la fila actual, con su x en ella
+    let mut next_edge = 0;
+    let mut active: Vec<(f32, f32, f32)> = Vec::new(); // (y de abajo, x, avance)
+    let mut crossings: Vec<f32> = Vec::new();
+    for y in first_row..last_row {
+        let center = y as f32 + 0.5;
+        // Cada arista cuenta en [arriba, abajo): un vértice entre dos aristas se corta una sola vez
+        while next_edge < edges.len() && edges[next_edge].0 <= center {
+            let (top, bottom, x, slope) = edges[next_edge];
+            active.push((bottom, x + (center - top) * slope, slope));
+            next_edge += 1;
+        }
+        active.retain(|edge| edge.0 > center);
+
+        crossings.clear();
+        crossings.extend(active.iter().map(|edge| edge.1));
+        crossings.sort_by(f32::total_cmp);
+        // Píxeles con el centro en [x de entrada, x de salida)
+        for pair in crossings.chunks_exact(2) {
+            let (from, to) = ((pair[0] - 0.5).ceil() as i32, (pair[1] - 0.5).ceil() as i32 - 1);
+            if from <= to {
+                span(y, from, to);
+            }
+        }
+        for edge in &mut active {
+            edge.1 += edge.2;
+        }
+    }
+}
+
+// Envolvente convexa de `points` en orden (cadena monótona de Andrew), sin los
+// puntos que caen sobre un lado. Es la silueta en pantalla de una figura convexa,
+// como una caja, a partir de sus esquinas proyectadas
+pub fn convex_hull(points: &[(f32, f32)]) -> Vec<(f32, f32)> {
+    let mut sorted = points.to_vec();
+    sorted.sort_by(|a, b| a.0.total_cmp(&b.0).then(a.1.total_cmp(&b.1)));
+    sorted.dedup();
+    if sorted.len() < 3 {
+        return sorted;
+    }
+    let turns_left = |o: (f32, f32), a: (f32, f32), b: (f32, f32)| (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0) > 0.0;
+    // La mitad de abajo de izquierda a derecha y la de arriba de vuelta
+    let mut hull: Vec<(f32, f32)> = Vec::with_capacity(sorted.len() + 1);
+    for pass in [sorted.clone(), sorted.into_iter().rev().collect()] {
+        let start = hull.len();
+        for point in pass {
+            while hull.len() >= start + 2 && !turns_left(hull[hull.len() - 2], hull[hull.len() - 1], point) {
+                hull.pop();
+            }
+            hull.push(point);
+        }
+        // El último de cada mitad es el primero de la otra
+        hull.pop();
+    }
+    hull
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    // Píxeles del contorno, relativos al centro
+    fn outline(rx: i32, ry: i32, thickness: usize) -> HashSet<(i32, i32)> {
+        let (cx, cy) = (100, 50);
+        let mut pixels = HashSet::new();
+        ellipse(cx, cy, rx, ry, thickness, |x, y| {
+            pixels.insert((x - cx, y - cy));
+        });
+        pixels
+    }
+
+    #[test]
+    fn outlines_are_symmetric_in_the_four_quadrants() {
+        for (rx, ry) in [(0, 0), (1, 1), (5, 5), (12, 12), (30, 30), (9, 3), (3, 9), (25, 0), (0, 7), (40, 17)] {
+            for thickness in [1, 3] {
+                let pixels = outline(rx, ry, thickness);
+                assert!(!pixels.is_empty());
+                for &(x, y) in &pixels {
+                    for mirrored in [(-x, y), (x, -y), (-x, -y)] {
+                        assert!(pixels.contains(&mirrored), "{}x{} de {}: está {:?} y falta {:?}", rx, ry, thickness, (x, y), mirrored);
+                    }
+                }
+                // Llega justo a los cuatro extremos de los ejes
+                assert!(pixels.contains(&(rx, 0)) && pixels.contains(&(0, ry)));
+                assert!(pixels.iter().all(|&(x, y)| x.abs() <= rx && y.abs() <= ry));
+            }
+        }
+    }
+
+    #[test]
+    fn circle_outline_is_round_and_connected() {
+        for radius in 1..25 {
+            let mut pixels = HashSet::new();
+            circle(0, 0, radius, 1, |x, y| {
+                pixels.insert((x, y));
+            });
+            for &(x, y) in &pixels {
+                // Un círculo es simétrico también respecto de las diagonales
+                assert!(pixels.contains(&(y, x)), "radio {}: falta {:?}", radius, (y, x));
+                let distance = ((x * x + y * y) as f32).sqrt();
+                assert!((distance - radius as f32).abs() < 1.0, "radio {}: {:?} a {}", radius, (x, y), distance);
+                // Sin huecos: cada píxel toca al menos a otros dos del contorno
+                let neighbours = (-1..=1).flat_map(|dy| (-1..=1).map(move |dx| (x + dx, y + dy))).filter(|&p| p != (x, y) && pixels.contains(&p)).count();
+                assert!(neighbours >= 2, "radio {}: {:?} suelto", radius, (x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn filled_ellipse_is_one_span_per_row_and_covers_its_outline() {
+        for (rx, ry) in [(6, 6), (20, 20), (15, 4), (3, 11)] {
+            let mut rows = HashSet::new();
+            let mut filled = HashSet::new();
+            fill_ellipse(0, 0, rx, ry, |y, from, to| {
+                assert!(rows.insert(y), "fila {} dos veces", y);
+                assert_eq!(from, -to);
+                filled.extend((from..=to).map(|x| (x, y)));
+            });
+            assert_eq!(rows.len(), 2 * ry as usize + 1);
+            assert!(outline(rx, ry, 1).is_subset(&filled));
+            let area = std::f32::consts::PI * rx as f32 * ry as f32;
+            assert!((filled.len() as f32 - area).abs() < area * 0.25 + 8.0, "{}x{}: {} contra {}", rx, ry, filled.len(), area);
+        }
+    }
+
+    #[test]
+    fn thick_ring_has_no_holes() {
+        let ring = outline(20, 20, 4);
+        // Todo lo que queda entre el contorno de afuera y uno 4 píxeles más chico
+        let (mut outer, mut inner) = (HashSet::new(), HashSet::new());
+        fill_circle(0, 0, 20, |y, from, to| outer.extend((from..=to).map(|x| (x, y))));
+        fill_circle(0, 0, 16, |y, from, to| inner.extend((from..=to).map(|x| (x, y))));
+        let expected: HashSet<(i32, i32)> = outer.difference(&inner).copied().collect();
+        assert_eq!(ring, expected);
+    }
+
+    fn filled_polygon(points: &[(f32, f32)]) -> HashSet<(i32, i32)> {
+        let mut pixels = HashSet::new();
+        fill_polygon(points, -100..100, |y, from, to| {
+            for x in from..=to {
+                assert!(pixels.insert((x, y)), "{:?} dos veces", (x, y));
+            }
+        });
+        pixels
+    }
+
+    // Estrella de cinco puntas trazada saltando una punta cada vez, así los lados se
+    // cruzan y dejan un pentágono en el medio
+    fn star() -> Vec<(f32, f32)> {
+        (0..5)
+            .map(|i| {
+                let angle = (i * 2 % 5) as f32 * std::f32::consts::TAU / 5.0 - std::f32::consts::FRAC_PI_2;
+                (50.0 + 40.0 * angle.cos(), 50.0 + 40.0 * angle.sin())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn l_shape_fills_its_arms_and_not_the_corner() {
+        let l = [(2.0, 2.0), (12.0, 2.0), (12.0, 6.0), (6.0, 6.0), (6.0, 14.0), (2.0, 14.0)];
+        let pixels = filled_polygon(&l);
+        // Con los vértices en bordes de píxel cubre exactamente su área
+        assert_eq!(pixels.len(), 10 * 4 + 4 * 8);
+        for inside in [(2, 2), (11, 5), (3, 12), (5, 13), (5, 6)] {
+            assert!(pixels.contains(&inside), "falta {:?}", inside);
+        }
+        for outside in [(6, 6), (9, 10), (11, 13), (12, 3), (1, 5), (3, 14)] {
+            assert!(!pixels.contains(&outside), "sobra {:?}", outside);
+        }
+    }
+
+    #[test]
+    fn star_fills_its_tips_and_not_the_gaps_between_them() {
+        let points = star();
+        let pixels = filled_polygon(&points);
+        for &(x, y) in &points {
+            // Un poco adentro de cada punta
+            let tip = ((x + (50.0 - x) * 0.1) as i32, (y + (50.0 - y) * 0.1) as i32);
+            assert!(pixels.contains(&tip), "falta la punta {:?}", tip);
+            // Entre dos puntas, afuera de la estrella
+            let gap = ((50.0 - (x - 50.0) * 0.8) as i32, (50.0 - (y - 50.0) * 0.8) as i32);
+            assert!(!pixels.contains(&gap), "sobra {:?}", gap);
+        }
+    }
+
+    #[test]
+    fn odd_polygons_do_not_panic() {
+        let bow_tie = [(0.0, 0.0), (20.0, 20.0), (20.0, 0.0), (0.0, 20.0)];
+        let pixels = filled_polygon(&bow_tie);
+        // Los dos triángulos del moño, de 100 píxeles de área cada uno
+        assert_eq!(pixels.len(), 200);
+        assert!(pixels.contains(&(2, 10)) && pixels.contains(&(17, 10)) && !pixels.contains(&(10, 2)));
+
+        // Sin área o con un punto inválido no se pinta nada
+        for points in [
+            vec![(5.0, 5.0); 4],
+            vec![(0.0, 0.0), (10.0, 10.0), (20.0, 20.0)],
+            vec![(0.0, 0.0), (f32::NAN, 3.0), (5.0, 9.0)],
+            vec![(1.0, 1.0), (2.0, 2.0)],
+        ] {
+            fill_polygon(&points, 0..50, |y, from, to| panic!("{:?}: fila {} de {} a {}", points, y, from, to));
+        }
+
+        // Enorme: solo se recorren las filas pedidas
+        let mut rows = Vec::new();
+        fill_polygon(&[(-1e9, -1e9), (1e9, -1e9), (0.0, 1e9)], 0..50, |y, _, _| rows.push(y));
+        assert_eq!(rows, (0..50).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn star_center_is_a_hole() {
+        let pixels = filled_polygon(&star());
+        // El pentágono del medio, de radio 40 cos 72° / cos 36°, queda vacío salvo
+        // por los píxeles que tocan sus lados
+        let inner = 40.0 * 72f32.to_radians().cos() / 36f32.to_radians().cos();
+        let near_center = |&&(x, y): &&(i32, i32)| ((x as f32 + 0.5 - 50.0).powi(2) + (y as f32 + 0.5 - 50.0).powi(2)).sqrt() < inner * 0.75;
+        assert_eq!(pixels.iter().filter(near_center).count(), 0);
+    }
+
+    #[test]
+    fn hull_drops_inner_points_and_keeps_the_corners_in_order() {
+        let points = [(0.0, 0.0), (4.0, 0.0), (2.0, 1.0), (4.0, 3.0), (2.0, 0.0), (0.0, 3.0), (1.0, 2.0), (4.0, 3.0)];
+        let hull = convex_hull(&points);
+        assert_eq!(hull, [(0.0, 0.0), (4.0, 0.0), (4.0, 3.0), (0.0, 3.0)]);
+        // La silueta de un cubo visto en diagonal es un hexágono
+        let cube: Vec<(f32, f32)> = (0..8).map(|i| ((i & 1) as f32 + 0.5 * (i >> 2) as f32, ((i >> 1) & 1) as f32 + 0.3 * (i >> 2) as f32)).collect();
+        assert_eq!(convex_hull(&cube).len(), 6);
+        assert_eq!(convex_hull(&[(1.0, 1.0), (1.0, 1.0)]), [(1.0, 1.0)]);
+    }
+}
@@ -0,0 +1,62 @@
+// fog.rs
+// Niebla: lleva el color de cada fragmento hacia el de la niebla según su
+// profundidad en espacio de cámara. Con el mismo color que el fondo, lo lejano se
+// funde con él en vez de cortarse en el plano lejano
+
+use crate::color::Color;
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum FogMode {
+    #[default]
+    None,
+    Linear { start: f32, end: f32 }, // Sin niebla hasta `start` y solo niebla desde `end`
+    Exponential { density: f32 },    // Lo que se ve cae como e^(-densidad * profundidad)
+    Exponential2 { density: f32 },   // Como e^(-(densidad * profundidad)²): despeja más lo cercano
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Fog {
+    pub mode: FogMode,
+    pub color: Color,
+}
+
+impl Fog {
+    // Qué parte del color del fragmento se ve a `depth` unidades delante del ojo:
+    // 1 sin niebla, 0 solo niebla
+    pub fn visibility(&self, depth: f32) -> f32 {
+        let visibility = match self.mode {
+            FogMode::None => return 1.0,
+            FogMode::Linear { start, end } if end <= start => if depth < start { 1.0 } else { 0.0 },
+            FogMode::Linear { start, end } => (end - depth) / (end - start),
+            FogMode::Exponential { density } => (-density * depth).exp(),
+            FogMode::Exponential2 { density } => (-(density * depth).powi(2)).exp(),
+        };
+        visibility.clamp(0.0, 1.0)
+    }
+
+    // Se aplica al color ya iluminado. No hay corrección gamma en el programa: se
+    // mezcla en los mismos valores que se guardan en el framebuffer
+    pub fn apply(&self, color: Color, depth: f32) -> Color {
+        if self.mode == FogMode::None {
+            return color;
+        }
+        self.color.lerp(color, self.visibility(depth))
+    }
+
+    // Cambia al modo siguiente, con valores pensados para la escena de tamaño 1 vista
+    // desde unas 3 unidades
+    pub fn next_mode(&mut self) {
+        self.mode = match self.mode {
+            FogMode::None => FogMode::Linear { start: 2.0, end: 6.0 },
+            FogMode::Linear { .. } => FogMode::Exponential { density: 0.3 },
+            FogMode::Exponential { .. } => FogMode::Exponential2 { density: 0.3 },
+            FogMode::Exponential2 { .. } => FogMode::None,
+        };
+    }
+}
+
+impl Default for Fog {
+    fn default() -> Self {
+        Fog { mode: FogMode::None, color: Color::from_hex(0x003300) }
+    }
+}
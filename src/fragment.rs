@@ -1,11 +1,21 @@
 // fragment.rs
-use nalgebra_glm::Vec2;
+use nalgebra_glm::{Vec2, Vec3};
 use crate::color::Color;
+use crate::light::Surface;
 
+// Se crea uno por píxel cubierto, así que conviene que siga siendo chico:
+// hoy son 34 f32 (136 bytes)
 pub struct Fragment {
     pub position: Vec2,
     pub color: Color,
     pub depth: f32, // Profundidad en el eje Z
+    pub tex_coords: Vec2, // Coordenadas de textura interpoladas
+    pub normal: Vec3, // Normal interpolada en espacio de cámara, de largo 1 (cero si el modelo no trae)
+    pub world_position: Vec3, // Posición interpolada en espacio de mundo
+    pub model_position: Vec3, // La misma en el modelo, antes de moverlo: no cambia al animarlo
+    pub coverage: f32, // Qué parte del píxel cubre, de 0 a 1; menos de 1 se mezcla con el fondo
+    pub material: Surface, // Material del triángulo; el difuso interpolado es `color`
+    pub face_light: Color, // Luz difusa de toda la cara, calculada en su centro (flat shading)
 }
 
 impl Fragment {
@@ -14,6 +24,18 @@ impl Fragment {
             position: Vec2::new(x, y),
             color,
             depth,
+            tex_coords: Vec2::new(0.0, 0.0),
+            normal: Vec3::new(0.0, 0.0, 1.0),
+            world_position: Vec3::new(0.0, 0.0, 0.0),
+            model_position: Vec3::new(0.0, 0.0, 0.0),
+            coverage: 1.0,
+            material: Surface::default(),
+            face_light: Color::black(),
         }
     }
+
+    // El material con el color difuso de este píxel
+    pub fn surface(&self) -> Surface {
+        Surface { diffuse: self.color, ..self.material }
+    }
 }
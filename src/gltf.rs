@@ -0,0 +1,485 @@
+// gltf.rs
+// Carga básica de glTF 2.0 (.gltf con buffers externos o embebidos, y .glb):
+// posiciones, normales y TEXCOORD_0 de las primitivas en modo triángulos
+
+use std::fmt;
+use std::fs;
+use std::io;
+use std::path::Path;
+use nalgebra_glm::{Mat3, Mat4, Vec2, Vec3};
+use crate::color::Color;
+use crate::json::{JsonError, Value};
+
+#[derive(Debug)]
+pub enum GltfError {
+    Io(io::Error),
+    Json(JsonError),
+    Invalid(String), // Estructura que no cumple la especificación o no soportada
+}
+
+impl fmt::Display for GltfError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GltfError::Io(err) => write!(f, "error de lectura: {}", err),
+            GltfError::Json(err) => write!(f, "{}", err),
+            GltfError::Invalid(reason) => write!(f, "glTF inválido: {}", reason),
+        }
+    }
+}
+
+impl std::error::Error for GltfError {}
+
+impl From<io::Error> for GltfError {
+    fn from(err: io::Error) -> Self {
+        GltfError::Io(err)
+    }
+}
+
+impl From<JsonError> for GltfError {
+    fn from(err: JsonError) -> Self {
+        GltfError::Json(err)
+    }
+}
+
+fn invalid(reason: impl Into<String>) -> GltfError {
+    GltfError::Invalid(reason.into())
+}
+
+// Primitiva ya transformada al espacio de la escena
+pub struct GltfPrimitive {
+    pub name: String,
+    pub positions: Vec<Vec3>,
+    pub normals: Option<Vec<Vec3>>,
+    pub tex_coords: Option<Vec<Vec2>>,
+    pub indices: Vec<u32>,
+    pub color: Option<Color>, // `baseColorFactor` del material, si tiene
+}
+
+pub struct Gltf {
+    pub primitives: Vec<GltfPrimitive>,
+}
+
+// Modo de dibujo TRIANGLES; puntos, líneas y tiras no se soportan
+const MODE_TRIANGLES: usize = 4;
+const GLB_MAGIC: u32 = 0x4654_6C67;
+const CHUNK_JSON: u32 = 0x4E4F_534A;
+const CHUNK_BIN: u32 = 0x004E_4942;
+// Elementos de un accessor sin bufferView, que son puros ceros: no ocupan nada en el
+// archivo, así que el `count` no está acotado por ningún buffer
+const MAX_ZERO_ELEMENTS: usize = 1 << 20;
+
+struct Document {
+    json: Value,
+    buffers: Vec<Vec<u8>>,
+}
+
+impl Gltf {
+    pub fn load(filename: &str) -> Result<Self, GltfError> {
+        let bytes = fs::read(filename)?;
+        // Las URIs de los buffers externos son relativas al archivo
+        let directory = Path::new(filename).parent().unwrap_or_else(|| Path::new(""));
+        Self::parse(&bytes, Some(directory))
+    }
+
+    pub fn parse(bytes: &[u8], directory: Option<&Path>) -> Result<Self, GltfError> {
+        let (json, binary_chunk) = if read_u32(bytes, 0) == Some(GLB_MAGIC) {
+            split_glb(bytes)?
+        } else {
+            let text = std::str::from_utf8(bytes).map_err(|_| invalid("el .gltf no es UTF-8"))?;
+            (Value::parse(text)?, None)
+        };
+
+        let buffers = array(&json, "buffers")
+            .iter()
+            .map(|buffer| load_buffer(buffer, directory, binary_chunk))
+            .collect::<Result<Vec<_>, _>>()?;
+        let document = Document { json, buffers };
+
+        let mut gltf = Gltf { primitives: Vec::new() };
+        for node in document.root_nodes() {
+            document.visit_node(node, &Mat4::identity(), 0, &mut gltf.primitives)?;
+        }
+        Ok(gltf)
+    }
+}
+
+impl Document {
+    // Nodos de la escena por defecto; sin escenas, los nodos que no son hijos de nadie
+    fn root_nodes(&self) -> Vec<usize> {
+        let scene = self.json.get("scene").and_then(Value::as_usize).unwrap_or(0);
+        if let Some(scene) = array(&self.json, "scenes").get(scene) {
+            return array(scene, "nodes").iter().filter_map(Value::as_usize).collect();
+        }
+
+        let nodes = array(&self.json, "nodes");
+        let children: Vec<usize> = nodes
+            .iter()
+            .flat_map(|node| array(node, "children").iter().filter_map(Value::as_usize))
+            .collect();
+        (0..nodes.len()).filter(|node| !children.contains(node)).collect()
+    }
+
+    fn visit_node(
+        &self,
+        index: usize,
+        parent: &Mat4,
+        depth: usize,
+        primitives: &mut Vec<GltfPrimitive>,
+    ) -> Result<(), GltfError> {
+        let nodes = array(&self.json, "nodes");
+        // Un archivo con ciclos en la jerarquía no debe recursar para siempre
+        if depth > nodes.len() {
+            return Err(invalid("la jerarquía de nodos tiene un ciclo"));
+        }
+        let node = nodes.get(index).ok_or_else(|| invalid(format!("nodo {} inexistente", index)))?;
+        let transform = parent * local_transform(node);
+
+        if let Some(mesh) = node.get("mesh").and_then(Value::as_usize) {
+            self.add_mesh(mesh, &transform, primitives)?;
+        }
+        for child in array(node, "children").iter().filter_map(Value::as_usize) {
+            self.visit_node(child, &transform, depth + 1, primitives)?;
+        }
+        Ok(())
+    }
+
+    fn add_mesh(&self, index: usize, transform: &Mat4, primitives: &mut Vec<GltfPrimitive>) -> Result<(), GltfError> {
+        let mesh = array(&self.json, "meshes")
+            .get(index)
+            .ok_or_else(|| invalid(format!("malla {} inexistente", index)))?;
+        let name = mesh.get("name").and_then(Value::as_str).map_or_else(|| format!("mesh{}", index), String::from);
+        // Las normales se transforman con la inversa transpuesta para soportar escalas no uniformes
+        let normal_matrix = nalgebra_glm::mat4_to_mat3(transform)
+            .try_inverse()
+            .map(|inverse| inverse.transpose())
+            .unwrap_or_else(Mat3::identity);
+
+        for primitive in array(mesh, "primitives") {
+            if primitive.get("mode").and_then(Value::as_usize).unwrap_or(MODE_TRIANGLES) != MODE_TRIANGLES {
+                continue;
+            }
+            let attributes = primitive.get("attributes").ok_or_else(|| invalid("primitiva sin `attributes`"))?;
+            let attribute = |name: &str| attributes.get(name).and_then(Value::as_usize);
+
+            let position = attribute("POSITION").ok_or_else(|| invalid("primitiva sin POSITION"))?;
+            let positions: Vec<Vec3> = self
+                .read_accessor(position, "VEC3", None)?
+                .chunks_exact(3)
+                .map(|p| {
+                    let world = transform * nalgebra_glm::vec4(p[0] as f32, p[1] as f32, p[2] as f32, 1.0);
+                    Vec3::new(world.x, world.y, world.z)
+                })
+                .collect();
+
+            // Todos los atributos de una primitiva tienen tantos elementos como POSITION
+            let count = Some(positions.len());
+            let normals = attribute("NORMAL")
+                .map(|accessor| self.read_accessor(accessor, "VEC3", count))
+                .transpose()?
+                .map(|values| {
+                    values
+                        .chunks_exact(3)
+                        .map(|n| {
+                            let normal = normal_matrix * Vec3::new(n[0] as f32, n[1] as f32, n[2] as f32);
+                            normal.try_normalize(f32::EPSILON).unwrap_or_else(Vec3::zeros)
+                        })
+                        .collect()
+                });
+
+            let tex_coords = attribute("TEXCOORD_0")
+                .map(|accessor| self.read_accessor(accessor, "VEC2", count))
+                .transpose()?
+                .map(|values| values.chunks_exact(2).map(|t| Vec2::new(t[0] as f32, t[1] as f32)).collect());
+
+            // Sin índices, cada tres vértices consecutivos forman un triángulo
+            let indices: Vec<u32> = match primitive.get("indices").and_then(Value::as_usize) {
+                Some(accessor) => self.read_accessor(accessor, "SCALAR", None)?.iter().map(|&i| i as u32).collect(),
+                None => (0..positions.len() as u32).collect(),
+            };
+            if let Some(&index) = indices.iter().find(|&&index| index as usize >= positions.len()) {
+                return Err(invalid(format!("índice {} fuera de rango en la malla {}", index, name)));
+            }
+
+            let color = primitive
+                .get("material")
+                .and_then(Value::as_usize)
+                .and_then(|material| array(&self.json, "materials").get(material))
+                .and_then(|material| material.get("pbrMetallicRoughness"))
+                .and_then(|pbr| pbr.get("baseColorFactor"))
+                .and_then(Value::as_f32_array::<4>)
+                .map(|[r, g, b, _]| Color { r, g, b });
+
+            primitives.push(GltfPrimitive { name: name.clone(), positions, normals, tex_coords, indices, color });
+        }
+        Ok(())
+    }
+
+    // Lee un accessor completo como números (los enteros normalizados se llevan a [0, 1] o [-1, 1]).
+    // Debe ser de tipo `kind` y, si se da `expected_count`, tener esa cantidad de elementos
+    fn read_accessor(&self, index: usize, kind: &str, expected_count: Option<usize>) -> Result<Vec<f64>, GltfError> {
+        let accessor = array(&self.json, "accessors")
+            .get(index)
+            .ok_or_else(|| invalid(format!("accessor {} inexistente", index)))?;
+        if accessor.get("sparse").is_some() {
+            return Err(invalid("los accessors dispersos no están soportados"));
+        }
+
+        let count = accessor.get("count").and_then(Value::as_usize).ok_or_else(|| invalid("accessor sin `count`"))?;
+        let kind_found = accessor.get("type").and_then(Value::as_str);
+        if kind_found.is_some_and(|found| found != kind) {
+            return Err(invalid(format!("accessor {} de tipo {} donde se esperaba {}", index, kind_found.unwrap_or(""), kind)));
+        }
+        if let Some(expected) = expected_count.filter(|&expected| expected != count) {
+            return Err(invalid(format!("accessor {} con {} elementos en vez de {}", index, count, expected)));
+        }
+        let components = match kind_found {
+            Some("SCALAR") => 1,
+            Some("VEC2") => 2,
+            Some("VEC3") => 3,
+            Some("VEC4") => 4,
+            Some("MAT4") => 16,
+            other => return Err(invalid(format!("tipo de accessor no soportado {:?}", other))),
+        };
+        let component_type = accessor.get("componentType").and_then(Value::as_usize).unwrap_or(0);
+        let (size, read): (usize, fn(&[u8]) -> f64) = match component_type {
+            5120 => (1, |b| b[0] as i8 as f64),
+            5121 => (1, |b| b[0] as f64),
+            5122 => (2, |b| i16::from_le_bytes([b[0], b[1]]) as f64),
+            5123 => (2, |b| u16::from_le_bytes([b[0], b[1]]) as f64),
+            5125 => (4, |b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64),
+            5126 => (4, |b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64),
+            other => return Err(invalid(format!("componentType desconocido {}", other))),
+        };
+        let normalized = accessor.get("normalized").and_then(Value::as_bool).unwrap_or(false);
+        let scale = match component_type {
+            5120 => 127.0,
+            5121 => 255.0,
+            5122 => 32767.0,
+            5123 => 65535.0,
+            _ => 1.0,
+        };
+
+        // Los tamaños salen del archivo: un `count` absurdo no debe desbordar las cuentas
+        let overflow = || invalid(format!("accessor {} demasiado grande", index));
+        let len = count.checked_mul(components).ok_or_else(overflow)?;
+
+        // Un accessor sin bufferView equivale a puros ceros
+        let Some(view_index) = accessor.get("bufferView").and_then(Value::as_usize) else {
+            if count > MAX_ZERO_ELEMENTS {
+                return Err(overflow());
+            }
+            return Ok(vec![0.0; len]);
+        };
+        let view = array(&self.json, "bufferViews")
+            .get(view_index)
+            .ok_or_else(|| invalid(format!("bufferView {} inexistente", view_index)))?;
+        let buffer = view
+            .get("buffer")
+            .and_then(Value::as_usize)
+            .and_then(|buffer| self.buffers.get(buffer))
+            .ok_or_else(|| invalid("bufferView con buffer inexistente"))?;
+
+        let element_size = size * components;
+        let stride = view.get("byteStride").and_then(Value::as_usize).unwrap_or(element_size);
+        let start = view
+            .get("byteOffset")
+            .and_then(Value::as_usize)
+            .unwrap_or(0)
+            .checked_add(accessor.get("byteOffset").and_then(Value::as_usize).unwrap_or(0))
+            .ok_or_else(overflow)?;
+        // Fin del último elemento; se comprueba antes de reservar memoria para `values`
+        let end = stride
+            .checked_mul(count.saturating_sub(1))
+            .and_then(|last| last.checked_add(start))
+            .and_then(|last| last.checked_add(element_size))
+            .ok_or_else(overflow)?;
+        if count > 0 && end > buffer.len() {
+            return Err(invalid(format!("accessor {} se sale de su buffer", index)));
+        }
+
+        let mut values = Vec::with_capacity(len);
+        for element in 0..count {
+            let offset = start + element * stride;
+            for component in 0..components {
+                let value = read(&buffer[offset + component * size..]);
+                values.push(if normalized { (value / scale).max(-1.0) } else { value });
+            }
+        }
+        Ok(values)
+    }
+}
+
+// Matriz local del nodo: `matrix` explícita o traslación * rotación * escala
+fn local_transform(node: &Value) -> Mat4 {
+    if let Some(matrix) = node.get("matrix").and_then(Value::as_f32_array::<16>) {
+        return Mat4::from_column_slice(&matrix);
+    }
+    let [tx, ty, tz] = node.get("translation").and_then(Value::as_f32_array::<3>).unwrap_or([0.0; 3]);
+    let [x, y, z, w] = node.get("rotation").and_then(Value::as_f32_array::<4>).unwrap_or([0.0, 0.0, 0.0, 1.0]);
+    let [sx, sy, sz] = node.get("scale").and_then(Value::as_f32_array::<3>).unwrap_or([1.0; 3]);
+
+    Mat4::new_translation(&Vec3::new(tx, ty, tz))
+        * nalgebra_glm::quat_to_mat4(&nalgebra_glm::quat(x, y, z, w))
+        * Mat4::new_nonuniform_scaling(&Vec3::new(sx, sy, sz))
+}
+
+fn array<'a>(value: &'a Value, key: &str) -> &'a [Value] {
+    value.get(key).and_then(Value::as_array).unwrap_or(&[])
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    let b = bytes.get(offset..offset + 4)?;
+    Some(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+// Separa un .glb en su JSON y su bloque binario opcional
+fn split_glb(bytes: &[u8]) -> Result<(Value, Option<&[u8]>), GltfError> {
+    let length = (read_u32(bytes, 8).ok_or_else(|| invalid("cabecera GLB incompleta"))? as usize).min(bytes.len());
+    let mut offset = 12;
+    let mut json = None;
+    let mut binary = None;
+
+    while offset + 8 <= length {
+        let chunk_length = read_u32(bytes, offset).unwrap_or(0) as usize;
+        let chunk_type = read_u32(bytes, offset + 4).unwrap_or(0);
+        let data = bytes
+            .get(offset + 8..offset + 8 + chunk_length)
+            .ok_or_else(|| invalid("bloque GLB truncado"))?;
+        match chunk_type {
+            CHUNK_JSON => {
+                let text = std::str::from_utf8(data).map_err(|_| invalid("el JSON del GLB no es UTF-8"))?;
+                json = Some(Value::parse(text)?);
+            }
+            CHUNK_BIN => binary = Some(data),
+            _ => {}
+        }
+        offset += 8 + chunk_length;
+    }
+
+    Ok((json.ok_or_else(|| invalid("GLB sin bloque JSON"))?, binary))
+}
+
+fn load_buffer(buffer: &Value, directory: Option<&Path>, binary_chunk: Option<&[u8]>) -> Result<Vec<u8>, GltfError> {
+    match buffer.get("uri").and_then(Value::as_str) {
+        // En un .glb el primer buffer sin URI es el bloque binario
+        None => binary_chunk.map(<[u8]>::to_vec).ok_or_else(|| invalid("buffer sin `uri` ni bloque binario")),
+        Some(uri) if uri.starts_with("data:") => {
+            let data = uri.split_once(";base64,").map(|(_, data)| data).ok_or_else(|| invalid("URI de datos sin base64"))?;
+            decode_base64(data).ok_or_else(|| invalid("base64 inválido en un buffer"))
+        }
+        Some(uri) => {
+            let directory = directory.ok_or_else(|| invalid("buffer externo sin directorio de origen"))?;
+            Ok(fs::read(directory.join(uri))?)
+        }
+    }
+}
+
+fn decode_base64(text: &str) -> Option<Vec<u8>> {
+    let value = |c: u8| match c {
+        b'A'..=b'Z' => Some(c - b'A'),
+        b'a'..=b'z' => Some(c - b'a' + 26),
+        b'0'..=b'9' => Some(c - b'0' + 52),
+        b'+' | b'-' => Some(62),
+        b'/' | b'_' => Some(63),
+        _ => None,
+    };
+
+    let mut bytes = Vec::with_capacity(text.len() * 3 / 4);
+    let mut accumulator = 0u32;
+    let mut bits = 0;
+    for c in text.bytes().filter(|&c| c != b'=' && !c.is_ascii_whitespace()) {
+        accumulator = (accumulator << 6) | value(c)? as u32;
+        bits += 6;
+        if bits >= 8 {
+            bits -= 8;
+            bytes.push((accumulator >> bits) as u8);
+        }
+    }
+    Some(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Documento con un buffer de 24 bytes y un accessor VEC3 de floats descrito por `accessor`
+    fn document(accessor: &str) -> Document {
+        let json = format!(r#"{{"bufferViews": [{{"buffer": 0, "byteLength": 24}}], "accessors": [{}]}}"#, accessor);
+        let buffer = [1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0].iter().flat_map(|v| v.to_le_bytes()).collect();
+        Document { json: Value::parse(&json).unwrap(), buffers: vec![buffer] }
+    }
+
+    fn read_error(accessor: &str) -> String {
+        match document(accessor).read_accessor(0, "VEC3", None) {
+            Err(GltfError::Invalid(reason)) => reason,
+            other => panic!("se esperaba un error y salió {:?}", other.map(|values| values.len())),
+        }
+    }
+
+    #[test]
+    fn reads_an_accessor_inside_its_buffer() {
+        let values = document(r#"{"bufferView": 0, "count": 2, "type": "VEC3", "componentType": 5126}"#).read_accessor(0, "VEC3", None).unwrap();
+        assert_eq!(values, vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn accessor_past_the_end_of_its_buffer_is_an_error() {
+        let reason = read_error(r#"{"bufferView": 0, "count": 3, "type": "VEC3", "componentType": 5126}"#);
+        assert_eq!(reason, "accessor 0 se sale de su buffer");
+        let reason = read_error(r#"{"bufferView": 0, "byteOffset": 4, "count": 2, "type": "VEC3", "componentType": 5126}"#);
+        assert_eq!(reason, "accessor 0 se sale de su buffer");
+    }
+
+    #[test]
+    fn accessor_of_the_wrong_type_or_count_is_an_error() {
+        let reason = read_error(r#"{"bufferView": 0, "count": 3, "type": "VEC2", "componentType": 5126}"#);
+        assert_eq!(reason, "accessor 0 de tipo VEC2 donde se esperaba VEC3");
+        let accessor = r#"{"bufferView": 0, "count": 2, "type": "VEC3", "componentType": 5126}"#;
+        match document(accessor).read_accessor(0, "VEC3", Some(3)) {
+            Err(GltfError::Invalid(reason)) => assert_eq!(reason, "accessor 0 con 2 elementos en vez de 3"),
+            other => panic!("{:?}", other),
+        }
+        assert_eq!(document(accessor).read_accessor(0, "VEC3", Some(2)).unwrap().len(), 6);
+    }
+
+    #[test]
+    fn accessor_without_buffer_view_is_zeros_up_to_a_limit() {
+        let zeros = |count: usize| format!(r#"{{"count": {}, "type": "VEC3", "componentType": 5126}}"#, count);
+        assert_eq!(document(&zeros(4)).read_accessor(0, "VEC3", None).unwrap(), vec![0.0; 12]);
+        // Mil millones de ceros desde un archivo de pocos bytes
+        assert_eq!(read_error(&zeros(1_000_000_000)), "accessor 0 demasiado grande");
+    }
+
+    #[test]
+    fn normals_must_be_vec3_like_the_spec_says() {
+        let source = r#"{
+            "buffers": [{"uri": "data:application/octet-stream;base64,AAAAAAAAAAAAAAAAAACAPwAAAAAAAAAAAAAAAAAAgD8AAAAA"}],
+            "bufferViews": [{"buffer": 0, "byteLength": 36}],
+            "accessors": [
+                {"bufferView": 0, "count": 3, "type": "VEC3", "componentType": 5126},
+                {"bufferView": 0, "count": 4, "type": "VEC2", "componentType": 5126}
+            ],
+            "meshes": [{"primitives": [{"attributes": {"POSITION": 0, "NORMAL": 1}}]}],
+            "nodes": [{"mesh": 0}]
+        }"#;
+        match Gltf::parse(source.as_bytes(), None) {
+            Err(GltfError::Invalid(reason)) => assert_eq!(reason, "accessor 1 de tipo VEC2 donde se esperaba VEC3"),
+            other => panic!("{:?}", other.map(|gltf| gltf.primitives.len())),
+        }
+        let valid = source.replace(r#""NORMAL": 1"#, r#""TEXCOORD_0": 1"#).replace(r#""count": 4"#, r#""count": 3"#);
+        let gltf = Gltf::parse(valid.as_bytes(), None).unwrap();
+        assert_eq!(gltf.primitives[0].positions.len(), 3);
+        assert_eq!(gltf.primitives[0].tex_coords.as_ref().map(Vec::len), Some(3));
+    }
+
+    #[test]
+    fn huge_sizes_are_an_error_instead_of_overflowing() {
+        let huge = "1e30"; // `as_usize` lo satura a usize::MAX
+        let reason = read_error(&format!(r#"{{"bufferView": 0, "count": {}, "type": "VEC3", "componentType": 5126}}"#, huge));
+        assert_eq!(reason, "accessor 0 demasiado grande");
+        let reason = read_error(&format!(r#"{{"count": {}, "type": "VEC3", "componentType": 5126}}"#, huge));
+        assert_eq!(reason, "accessor 0 demasiado grande");
+        let reason = read_error(&format!(r#"{{"bufferView": 0, "byteOffset": {}, "count": 1, "type": "VEC3", "componentType": 5126}}"#, huge));
+        assert_eq!(reason, "accessor 0 demasiado grande");
+    }
+}
@@ -0,0 +1,392 @@
+// json.rs
+// Lector y escritor JSON mínimo: lo justo para glTF y archivos de configuración pequeños
+
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>), // Conserva el orden de las claves
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct JsonError {
+    pub offset: usize, // Posición en bytes donde se detectó el error
+    pub reason: String,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "JSON inválido en el byte {}: {}", self.offset, self.reason)
+    }
+}
+
+impl std::error::Error for JsonError {}
+
+impl Value {
+    pub fn parse(source: &str) -> Result<Value, JsonError> {
+        let mut parser = Parser { bytes: source.as_bytes(), offset: 0, depth: 0 };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        if parser.offset < parser.bytes.len() {
+            return Err(parser.error("contenido después del valor"));
+        }
+        Ok(value)
+    }
+
+    // Campo de un objeto (None si no es objeto o no tiene la clave)
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            _ => None,
+        }
+    }
+
+    pub fn as_usize(&self) -> Option<usize> {
+        self.as_f64().filter(|n| *n >= 0.0 && n.fract() == 0.0).map(|n| n as usize)
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Value::String(text) => Some(text),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            Value::Bool(value) => Some(*value),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[Value]> {
+        match self {
+            Value::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+
+    // Arreglo de números de largo fijo, como `[x, y, z]`
+    pub fn as_f32_array<const N: usize>(&self) -> Option<[f32; N]> {
+        let items = self.as_array().filter(|items| items.len() == N)?;
+        let mut values = [0.0; N];
+        for (value, item) in values.iter_mut().zip(items) {
+            *value = item.as_f64()? as f32;
+        }
+        Some(values)
+    }
+}
+
+// Escribe el valor como JSON compacto
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Value::Null => write!(f, "null"),
+            Value::Bool(value) => write!(f, "{}", value),
+            // JSON no tiene NaN ni infinito
+            Value::Number(number) if !number.is_finite() => write!(f, "null"),
+            Value::Number(number) => write!(f, "{}", number),
+            Value::String(text) => write_string(f, text),
+            Value::Array(items) => {
+                write!(f, "[")?;
+                for (index, item) in items.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Value::Object(fields) => {
+                write!(f, "{{")?;
+                for (index, (name, value)) in fields.iter().enumerate() {
+                    if index > 0 {
+                        write!(f, ",")?;
+                    }
+                    write_string(f, name)?;
+                    write!(f, ":{}", value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+fn write_string(f: &mut fmt::Formatter, text: &str) -> fmt::Result {
+    write!(f, "\"")?;
+    for c in text.chars() {
+        match c {
+            '"' => write!(f, "\\\"")?,
+            '\\' => write!(f, "\\\\")?,
+            '\n' => write!(f, "\\n")?,
+            '\r' => write!(f, "\\r")?,
+            '\t' => write!(f, "\\t")?,
+            c if (c as u32) < 0x20 => write!(f, "\\u{:04x}", c as u32)?,
+            c => write!(f, "{}", c)?,
+        }
+    }
+    write!(f, "\"")
+}
+
+// Arreglos y objetos anidados más allá de esto son un error: cada nivel es una
+// llamada recursiva y un archivo como `[[[[...` desbordaría la pila
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    bytes: &'a [u8],
+    offset: usize,
+    depth: usize, // Arreglos y objetos abiertos en este punto
+}
+
+impl Parser<'_> {
+    fn error(&self, reason: &str) -> JsonError {
+        JsonError { offset: self.offset, reason: reason.to_string() }
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.offset < self.bytes.len() && self.bytes[self.offset].is_ascii_whitespace() {
+            self.offset += 1;
+        }
+    }
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.offset).copied()
+    }
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        if self.peek() == Some(byte) {
+            self.offset += 1;
+            Ok(())
+        } else {
+            Err(self.error(&format!("se esperaba `{}`", byte as char)))
+        }
+    }
+
+    fn literal(&mut self, text: &str, value: Value) -> Result<Value, JsonError> {
+        if self.bytes[self.offset..].starts_with(text.as_bytes()) {
+            self.offset += text.len();
+            Ok(value)
+        } else {
+            Err(self.error("valor desconocido"))
+        }
+    }
+
+    fn value(&mut self) -> Result<Value, JsonError> {
+        match self.peek() {
+            Some(b'{') => self.nested(Self::object),
+            Some(b'[') => self.nested(Self::array),
+            Some(b'"') => Ok(Value::String(self.string()?)),
+            Some(b't') => self.literal("true", Value::Bool(true)),
+            Some(b'f') => self.literal("false", Value::Bool(false)),
+            Some(b'n') => self.literal("null", Value::Null),
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("carácter inesperado")),
+            None => Err(self.error("fin inesperado")),
+        }
+    }
+
+    // Lee un arreglo u objeto con `parse`, contando un nivel más de anidamiento
+    fn nested(&mut self, parse: fn(&mut Self) -> Result<Value, JsonError>) -> Result<Value, JsonError> {
+        if self.depth == MAX_DEPTH {
+            return Err(self.error("demasiados niveles de anidamiento"));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    fn object(&mut self) -> Result<Value, JsonError> {
+        self.expect(b'{')?;
+        let mut fields = Vec::new();
+        if self.peek() == Some(b'}') {
+            self.offset += 1;
+            return Ok(Value::Object(fields));
+        }
+        loop {
+            if self.peek() != Some(b'"') {
+                return Err(self.error("se esperaba una clave"));
+            }
+            let key = self.string()?;
+            self.expect(b':')?;
+            fields.push((key, self.value()?));
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(b'}') => {
+                    self.offset += 1;
+                    return Ok(Value::Object(fields));
+                }
+                _ => return Err(self.error("se esperaba `,` o `}`")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> Result<Value, JsonError> {
+        self.expect(b'[')?;
+        let mut items = Vec::new();
+        if self.peek() == Some(b']') {
+            self.offset += 1;
+            return Ok(Value::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            match self.peek() {
+                Some(b',') => self.offset += 1,
+                Some(b']') => {
+                    self.offset += 1;
+                    return Ok(Value::Array(items));
+                }
+                _ => return Err(self.error("se esperaba `,` o `]`")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, JsonError> {
+        let start = self.offset;
+        while self.offset < self.bytes.len()
+            && matches!(self.bytes[self.offset], b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')
+        {
+            self.offset += 1;
+        }
+        std::str::from_utf8(&self.bytes[start..self.offset])
+            .ok()
+            .and_then(|text| text.parse::<f64>().ok())
+            .map(Value::Number)
+            .ok_or_else(|| JsonError { offset: start, reason: String::from("número inválido") })
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut text = String::new();
+        loop {
+            let rest = &self.bytes[self.offset..];
+            // Copia de una vez el tramo sin comillas ni escapes
+            let run = rest.iter().position(|&b| b == b'"' || b == b'\\').ok_or_else(|| self.error("texto sin cerrar"))?;
+            text.push_str(std::str::from_utf8(&rest[..run]).map_err(|_| self.error("texto que no es UTF-8"))?);
+            self.offset += run;
+
+            if self.bytes[self.offset] == b'"' {
+                self.offset += 1;
+                return Ok(text);
+            }
+
+            self.offset += 1;
+            let escape = *self.bytes.get(self.offset).ok_or_else(|| self.error("escape incompleto"))?;
+            self.offset += 1;
+            match escape {
+                b'"' => text.push('"'),
+                b'\\' => text.push('\\'),
+                b'/' => text.push('/'),
+                b'b' => text.push('\u{8}'),
+                b'f' => text.push('\u{c}'),
+                b'n' => text.push('\n'),
+                b'r' => text.push('\r'),
+                b't' => text.push('\t'),
+                b'u' => {
+                    let mut code = self.hex4()?;
+                    // Pares sustitutos para caracteres fuera del plano básico
+                    if (0xD800..0xDC00).contains(&code) && self.bytes[self.offset..].starts_with(b"\\u") {
+                        self.offset += 2;
+                        let low = self.hex4()?;
+                        code = 0x10000 + ((code - 0xD800) << 10) + (low.wrapping_sub(0xDC00) & 0x3FF);
+                    }
+                    text.push(char::from_u32(code).unwrap_or('\u{FFFD}'));
+                }
+                _ => return Err(self.error("escape desconocido")),
+            }
+        }
+    }
+
+    fn hex4(&mut self) -> Result<u32, JsonError> {
+        let digits = self.bytes.get(self.offset..self.offset + 4).ok_or_else(|| self.error("escape incompleto"))?;
+        let code = std::str::from_utf8(digits)
+            .ok()
+            .and_then(|digits| u32::from_str_radix(digits, 16).ok())
+            .ok_or_else(|| self.error("escape \\u inválido"))?;
+        self.offset += 4;
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(source: &str) -> (usize, String) {
+        let err = Value::parse(source).unwrap_err();
+        (err.offset, err.reason)
+    }
+
+    #[test]
+    fn parses_scalars() {
+        assert_eq!(Value::parse(" null "), Ok(Value::Null));
+        assert_eq!(Value::parse("true"), Ok(Value::Bool(true)));
+        assert_eq!(Value::parse("false"), Ok(Value::Bool(false)));
+        assert_eq!(Value::parse("-12.5e1"), Ok(Value::Number(-125.0)));
+        assert_eq!(Value::parse("0"), Ok(Value::Number(0.0)));
+    }
+
+    #[test]
+    fn parses_strings_with_escapes() {
+        let text = Value::parse(r#""a\"b\\c\/\n\t\u00e9\ud83d\ude00""#).unwrap();
+        assert_eq!(text.as_str(), Some("a\"b\\c/\n\té😀"));
+        assert_eq!(Value::parse(r#""ñandú""#).unwrap().as_str(), Some("ñandú"));
+    }
+
+    #[test]
+    fn parses_nested_values_in_order() {
+        let value = Value::parse(r#"{"b": [1, 2, {"c": null}], "a": {}, "vacío": []}"#).unwrap();
+        let Value::Object(fields) = &value else { panic!("se esperaba un objeto") };
+        assert_eq!(fields.iter().map(|(key, _)| key.as_str()).collect::<Vec<_>>(), ["b", "a", "vacío"]);
+        let items = value.get("b").and_then(Value::as_array).unwrap();
+        assert_eq!(items[1].as_usize(), Some(2));
+        assert_eq!(items[2].get("c"), Some(&Value::Null));
+        assert_eq!(value.get("a"), Some(&Value::Object(Vec::new())));
+        assert!(value.get("falta").is_none());
+    }
+
+    #[test]
+    fn display_writes_json_that_parses_back() {
+        let source = r#"{"nombre":"línea\n\"uno\"","lista":[1,-2.5,true,null],"objeto":{"x":[]}}"#;
+        let value = Value::parse(source).unwrap();
+        assert_eq!(value.to_string(), source);
+        assert_eq!(Value::parse(&value.to_string()), Ok(value));
+        assert_eq!(Value::Number(f64::NAN).to_string(), "null");
+    }
+
+    #[test]
+    fn errors_report_offset_and_reason() {
+        assert_eq!(error(""), (0, String::from("fin inesperado")));
+        assert_eq!(error("[1, 2"), (5, String::from("se esperaba `,` o `]`")));
+        assert_eq!(error(r#"{"a" 1}"#), (5, String::from("se esperaba `:`")));
+        assert_eq!(error("{1: 2}"), (1, String::from("se esperaba una clave")));
+        assert_eq!(error("tru"), (0, String::from("valor desconocido")));
+        assert_eq!(error("1.2.3"), (0, String::from("número inválido")));
+        assert_eq!(error(r#""abc"#), (1, String::from("texto sin cerrar")));
+        assert_eq!(error(r#""\x""#), (3, String::from("escape desconocido")));
+        assert_eq!(error("[] []"), (3, String::from("contenido después del valor")));
+    }
+
+    #[test]
+    fn deep_nesting_is_an_error_instead_of_a_stack_overflow() {
+        let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+        assert!(Value::parse(&nested(MAX_DEPTH)).is_ok());
+        assert_eq!(error(&nested(MAX_DEPTH + 1)), (MAX_DEPTH, String::from("demasiados niveles de anidamiento")));
+
+        // Un archivo malicioso de un millón de niveles
+        assert_eq!(error(&"[{\"a\":".repeat(1_000_000)).1, "demasiados niveles de anidamiento");
+    }
+}
@@ -0,0 +1,397 @@
+// light.rs
+// Luces de la escena: direccionales, que llegan desde la misma dirección a todo el
+// modelo como el sol, puntuales, que salen de un punto y se debilitan con la
+// distancia, y focos, que además solo alumbran dentro de un cono como una linterna.
+// Están fijas en el mundo, así que al orbitar se ve girar la parte iluminada
+
+use std::sync::Arc;
+use nalgebra_glm::{Mat4, Vec3};
+use crate::color::Color;
+use crate::shadow::ShadowMap;
+use crate::vertex::Vertex;
+
+#[derive(Debug, Clone, Copy)]
+pub enum LightKind {
+    Directional { direction: Vec3 },            // Hacia dónde viaja la luz
+    Point { position: Vec3, attenuation: f32 }, // Llega con 1 / (1 + attenuation * distancia²)
+    // Como la puntual, pero solo hacia `direction`: entera hasta `inner` radianes del
+    // eje del cono y nada desde `outer`, con una transición suave entre los dos
+    Spot { position: Vec3, direction: Vec3, inner: f32, outer: f32, attenuation: f32 },
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Light {
+    pub kind: LightKind,
+    pub color: Color,
+    pub intensity: f32,
+}
+
+impl Light {
+    pub fn directional(direction: Vec3, color: Color, intensity: f32) -> Self {
+        Light { kind: LightKind::Directional { direction }, color, intensity }
+    }
+
+    pub fn point(position: Vec3, attenuation: f32, color: Color, intensity: f32) -> Self {
+        Light { kind: LightKind::Point { position, attenuation }, color, intensity }
+    }
+
+    pub fn spot(position: Vec3, direction: Vec3, inner: f32, outer: f32, attenuation: f32, color: Color, intensity: f32) -> Self {
+        Light { kind: LightKind::Spot { position, direction, inner, outer, attenuation }, color, intensity }
+    }
+
+    // La misma luz con la dirección y la posición en espacio de cámara
+    fn in_camera(&self, view_matrix: &Mat4) -> Light {
+        let transform = |v: Vec3, w: f32| (view_matrix * nalgebra_glm::vec4(v.x, v.y, v.z, w)).xyz();
+        let kind = match self.kind {
+            LightKind::Directional { direction } => LightKind::Directional { direction: transform(direction, 0.0) },
+            LightKind::Point { position, attenuation } => LightKind::Point { position: transform(position, 1.0), attenuation },
+            LightKind::Spot { position, direction, inner, outer, attenuation } => LightKind::Spot {
+                position: transform(position, 1.0),
+                direction: transform(direction, 0.0),
+                inner,
+                outer,
+                attenuation,
+            },
+        };
+        Light { kind, ..*self }
+    }
+
+    // Dirección hacia la luz, de largo 1, y la luz que llega a `position`
+    fn arriving(&self, position: &Vec3) -> (Vec3, Color) {
+        let radiance = self.color * self.intensity;
+        match self.kind {
+            LightKind::Directional { direction } => ((-direction).try_normalize(f32::EPSILON).unwrap_or_else(Vec3::z), radiance),
+            LightKind::Point { position: light, attenuation } => {
+                let offset = light - position;
+                let to_light = offset.try_normalize(f32::EPSILON).unwrap_or_else(Vec3::z);
+                (to_light, radiance * (1.0 / (1.0 + attenuation * offset.norm_squared())))
+            }
+            LightKind::Spot { position: light, direction, inner, outer, attenuation } => {
+                let offset = light - position;
+                let to_light = offset.try_normalize(f32::EPSILON).unwrap_or_else(Vec3::z);
+                let axis = direction.try_normalize(f32::EPSILON).unwrap_or_else(|| -Vec3::z());
+                let cone = cone_falloff(-to_light.dot(&axis), inner, outer);
+                (to_light, radiance * (cone / (1.0 + attenuation * offset.norm_squared())))
+            }
+        }
+    }
+}
+
+// Qué parte de la luz de un foco llega a un punto que está a un ángulo de coseno
+// `cosine` del eje: 1 adentro de `inner`, 0 afuera de `outer` y una curva suave
+// (smoothstep) entre los dos, así el borde del cono no corta de golpe. Si `inner`
+// no es menor que `outer` el borde queda duro en `outer`, sin dividir por cero
+fn cone_falloff(cosine: f32, inner: f32, outer: f32) -> f32 {
+    let (cos_inner, cos_outer) = (inner.min(outer).cos(), outer.cos());
+    if cos_inner - cos_outer <= f32::EPSILON {
+        return if cosine >= cos_outer { 1.0 } else { 0.0 };
+    }
+    let t = ((cosine - cos_outer) / (cos_inner - cos_outer)).clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+// Cómo refleja y emite la luz un punto de la superficie, con los valores del
+// material que traen sus vértices
+#[derive(Debug, Clone, Copy)]
+pub struct Surface {
+    pub diffuse: Color,
+    pub ambient: Color,
+    pub specular: Color,
+    pub shininess: f32,
+    pub emissive: Color,
+}
+
+impl Surface {
+    pub fn of(vertex: &Vertex) -> Self {
+        Surface {
+            diffuse: vertex.color,
+            ambient: vertex.ambient,
+            specular: vertex.specular,
+            shininess: vertex.shininess,
+            emissive: vertex.emissive,
+        }
+    }
+}
+
+impl Default for Surface {
+    fn default() -> Self {
+        Surface::of(&Vertex::default())
+    }
+}
+
+// La luz `light` de `Lights` solo llega donde su mapa de sombras no ve nada delante
+#[derive(Debug, Clone)]
+pub struct Shadow {
+    pub map: Arc<ShadowMap>,
+    pub light: usize,
+    to_map: Mat4, // Del espacio en que están las luces al mapa
+}
+
+impl Shadow {
+    pub fn new(map: Arc<ShadowMap>, light: usize) -> Self {
+        let to_map = map.light_matrix;
+        Shadow { map, light, to_map }
+    }
+}
+
+// Todas las luces, más la ambiente que reciben todas las caras, aun las de
+// espaldas a las luces. Sin luces queda solo la ambiente
+#[derive(Debug, Clone)]
+pub struct Lights {
+    pub lights: Vec<Light>,
+    pub ambient: Color,
+    pub shadow: Option<Shadow>,
+}
+
+impl Lights {
+    // Las mismas luces en espacio de cámara, donde están las normales transformadas.
+    // Los métodos de abajo esperan luces, normales y posiciones en ese espacio
+    pub fn in_camera(&self, view_matrix: &Mat4) -> Lights {
+        let to_world = view_matrix.try_inverse().unwrap_or_else(Mat4::identity);
+        Lights {
+            lights: self.lights.iter().map(|light| light.in_camera(view_matrix)).collect(),
+            ambient: self.ambient,
+            shadow: self.shadow.as_ref().map(|shadow| Shadow { to_map: shadow.to_map * to_world, ..shadow.clone() }),
+        }
+    }
+
+    // Dirección hacia la luz `index` y la luz que llega a `position`, sin lo que tapa
+    // su sombra
+    fn arriving(&self, index: usize, light: &Light, position: &Vec3) -> (Vec3, Color) {
+        let (to_light, radiance) = light.arriving(position);
+        match &self.shadow {
+            Some(shadow) if shadow.light == index => (to_light, radiance * shadow.map.visibility(&shadow.to_map, position)),
+            _ => (to_light, radiance),
+        }
+    }
+
+    // Gira las luces puntuales alrededor del eje Y del mundo
+    pub fn orbit_points(&mut self, angle: f32) {
+        let rotation = nalgebra_glm::rotate_y(&Mat4::identity(), angle);
+        for light in &mut self.lights {
+            if let LightKind::Point { position, .. } = &mut light.kind {
+                *position = (rotation * nalgebra_glm::vec4(position.x, position.y, position.z, 1.0)).xyz();
+            }
+        }
+    }
+
+    // Luz difusa (Lambert) de todas las luces en un punto con normal `normal`, de
+    // largo 1. Se multiplica por el color difuso de la superficie
+    pub fn diffuse(&self, normal: &Vec3, position: &Vec3) -> Color {
+        self.lights.iter().enumerate().fold(Color::black(), |total, (index, light)| {
+            let (to_light, radiance) = self.arriving(index, light, position);
+            total + radiance * normal.dot(&to_light).max(0.0)
+        })
+    }
+
+    // Lo que la superficie aporta sin depender de hacia dónde mira: la ambiente que
+    // refleja más su luz propia, que se suma después de iluminar
+    pub fn glow(&self, surface: &Surface) -> Color {
+        surface.ambient * self.ambient + surface.emissive
+    }
+
+    // Ambiente, difusa y luz propia, sin brillo. Sin recortar: quien la usa decide
+    // cuándo llevarla a [0, 1]
+    pub fn lambert(&self, surface: &Surface, normal: &Vec3, position: &Vec3) -> Color {
+        surface.diffuse * self.diffuse(normal, position) + self.glow(surface)
+    }
+
+    // Modelo de Phong: ambiente y difusa, más un brillo del color especular de la
+    // superficie donde la luz reflejada apunta hacia el ojo, que está en el origen
+    pub fn phong(&self, surface: &Surface, normal: &Vec3, position: &Vec3) -> Color {
+        self.highlighted(surface, normal, position, |to_light, to_eye| {
+            let reflected = normal * (2.0 * normal.dot(to_light)) - to_light;
+            reflected.dot(to_eye).max(0.0).powf(surface.shininess)
+        })
+    }
+
+    // Blinn-Phong: compara la normal con el vector que queda a medio camino entre la
+    // luz y el ojo en vez de reflejar la luz. Es más barato y no corta el brillo de
+    // golpe cuando la luz llega rasante
+    pub fn blinn_phong(&self, surface: &Surface, normal: &Vec3, position: &Vec3) -> Color {
+        self.highlighted(surface, normal, position, |to_light, to_eye| {
+            let halfway = (to_light + to_eye).try_normalize(f32::EPSILON).unwrap_or(*normal);
+            normal.dot(&halfway).max(0.0).powf(surface.shininess)
+        })
+    }
+
+    fn highlighted(&self, surface: &Surface, normal: &Vec3, position: &Vec3, highlight: impl Fn(&Vec3, &Vec3) -> f32) -> Color {
+        let to_eye = (-position).try_normalize(f32::EPSILON).unwrap_or_else(Vec3::z);
+        let mut diffuse = Color::black();
+        let mut shine = Color::black();
+        for (index, light) in self.lights.iter().enumerate() {
+            let (to_light, radiance) = self.arriving(index, light, position);
+            let facing = normal.dot(&to_light);
+            // Del lado de atrás de la luz no hay reflejo
+            if facing > 0.0 {
+                diffuse = diffuse + radiance * facing;
+                shine = shine + radiance * highlight(&to_light, &to_eye);
+            }
+        }
+        surface.diffuse * diffuse + surface.specular * shine + self.glow(surface)
+    }
+}
+
+// Un sol blanco desde arriba, a la derecha y por delante del modelo en la vista
+// inicial, y una luz puntual cálida del otro lado para que la sombra no quede negra
+impl Default for Lights {
+    fn default() -> Self {
+        Lights {
+            lights: vec![
+                Light::directional(Vec3::new(-0.5, -1.0, -0.8), Color::from_rgb([1.0; 3]), 0.8),
+                Light::point(Vec3::new(-1.5, 0.5, -1.5), 0.5, Color::from_rgb([1.0, 0.8, 0.6]), 0.6),
+            ],
+            ambient: Color::from_rgb([0.25; 3]),
+            shadow: None,
+        }
+    }
+}
+
+// Luz de contorno: se suma donde la superficie queda de canto a la vista, así las
+// siluetas oscuras se separan del fondo. Con `power` más alto queda más pegada al borde
+#[derive(Debug, Clone, Copy)]
+pub struct Rim {
+    pub color: Color,
+    pub power: f32,
+}
+
+impl Rim {
+    // `normal` y `to_eye` de largo 1
+    pub fn term(&self, normal: &Vec3, to_eye: &Vec3) -> Color {
+        self.color * (1.0 - normal.dot(to_eye).max(0.0)).powf(self.power)
+    }
+}
+
+// Dónde se calcula la luz
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Shading {
+    #[default]
+    Flat,       // Una vez por cara, con la normal de la cara: se notan las facetas
+    Gouraud,    // Una vez por vértice, y el color se interpola entre ellos
+    BlinnPhong, // En cada píxel que pasa la prueba de profundidad, con brillo
+    Phong,      // Igual, con el brillo de Phong para comparar
+}
+
+impl Shading {
+    pub fn name(self) -> &'static str {
+        match self {
+            Shading::Flat => "Flat",
+            Shading::Gouraud => "Gouraud",
+            Shading::BlinnPhong => "Blinn-Phong",
+            Shading::Phong => "Phong",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Solo brillo blanco: sin difusa, ambiente ni luz propia
+    fn shiny(shininess: f32) -> Surface {
+        Surface { diffuse: Color::black(), ambient: Color::black(), specular: Color::from_rgb([1.0; 3]), shininess, emissive: Color::black() }
+    }
+
+    fn only(light: Light) -> Lights {
+        Lights { lights: vec![light], ambient: Color::black(), shadow: None }
+    }
+
+    // Recorre la recta y = 0 del plano z = -5, que mira al ojo (en el origen), y
+    // devuelve la x donde más brilla y ese brillo
+    fn brightest(lights: &Lights, shininess: f32, model: fn(&Lights, &Surface, &Vec3, &Vec3) -> Color) -> (f32, f32) {
+        (-400..=400)
+            .map(|i| {
+                let x = i as f32 * 0.01;
+                (x, model(lights, &shiny(shininess), &Vec3::z(), &Vec3::new(x, 0.0, -5.0)).r)
+            })
+            .fold((0.0, f32::MIN), |best, sample| if sample.1 > best.1 { sample } else { best })
+    }
+
+    #[test]
+    fn blinn_highlight_sits_halfway_between_light_and_eye() {
+        // La luz baja hacia -Z inclinada hacia -X: el reflejo espejo llega al ojo desde
+        // donde la dirección al ojo es la de la luz reflejada, en x = 5 * lx / lz
+        let to_light = Vec3::new(0.6, 0.0, 0.8);
+        let lights = only(Light::directional(-to_light, Color::from_rgb([1.0; 3]), 1.0));
+        let expected = 5.0 * to_light.x / to_light.z;
+        for shininess in [8.0, 32.0, 128.0] {
+            let (x, peak) = brightest(&lights, shininess, Lights::blinn_phong);
+            assert!((x - expected).abs() <= 0.01, "brillo {}: en {} y no en {}", shininess, x, expected);
+            // Ahí el vector intermedio coincide con la normal
+            assert!((peak - 1.0).abs() < 1e-3, "{}", peak);
+            // Y cae hacia los dos lados
+            let at = |x: f32| Lights::blinn_phong(&lights, &shiny(shininess), &Vec3::z(), &Vec3::new(x, 0.0, -5.0)).r;
+            assert!(at(expected - 1.0) < peak && at(expected + 1.0) < peak);
+        }
+        // Phong pone el brillo en el mismo lugar, con otra caída
+        let (x, _) = brightest(&lights, 32.0, Lights::phong);
+        assert!((x - expected).abs() <= 0.01);
+    }
+
+    #[test]
+    fn higher_shininess_narrows_the_highlight() {
+        let lights = only(Light::directional(Vec3::new(-0.6, 0.0, -0.8), Color::from_rgb([1.0; 3]), 1.0));
+        let width = |shininess: f32| {
+            (-400..=400)
+                .filter(|&i| Lights::blinn_phong(&lights, &shiny(shininess), &Vec3::z(), &Vec3::new(i as f32 * 0.01, 0.0, -5.0)).r > 0.5)
+                .count()
+        };
+        assert!(width(8.0) > width(32.0) && width(32.0) > width(128.0) && width(128.0) > 0);
+    }
+
+    #[test]
+    fn no_highlight_from_behind_the_surface() {
+        let lights = only(Light::directional(Vec3::new(0.0, 0.0, 1.0), Color::from_rgb([1.0; 3]), 1.0));
+        for x in [-2.0, 0.0, 2.0] {
+            let color = lights.blinn_phong(&shiny(16.0), &Vec3::z(), &Vec3::new(x, 0.0, -5.0));
+            assert_eq!([color.r, color.g, color.b], [0.0; 3]);
+        }
+    }
+
+    #[test]
+    fn cone_is_full_inside_empty_outside_and_smooth_between() {
+        let (inner, outer) = (20f32.to_radians(), 30f32.to_radians());
+        let at = |degrees: f32| cone_falloff(degrees.to_radians().cos(), inner, outer);
+        assert_eq!((at(0.0), at(10.0), at(19.9)), (1.0, 1.0, 1.0));
+        assert_eq!((at(30.1), at(60.0), at(180.0)), (0.0, 0.0, 0.0));
+        // A mitad de camino en coseno, smoothstep da justo la mitad
+        let middle = ((inner.cos() + outer.cos()) / 2.0).acos().to_degrees();
+        assert!((at(middle) - 0.5).abs() < 1e-4);
+        // Baja sin saltos: de décima en décima de grado nunca cambia mucho
+        let samples: Vec<f32> = (190..=310).map(|tenths| at(tenths as f32 / 10.0)).collect();
+        for pair in samples.windows(2) {
+            assert!(pair[1] <= pair[0] && pair[0] - pair[1] < 0.03, "{:?}", pair);
+        }
+    }
+
+    #[test]
+    fn inverted_or_equal_cones_clamp_to_a_hard_edge() {
+        let outer = 25f32.to_radians();
+        for inner in [outer, 40f32.to_radians(), f32::MAX] {
+            for degrees in [0.0, 24.9, 25.1, 90.0] {
+                let value = cone_falloff((degrees as f32).to_radians().cos(), inner, outer);
+                assert!(!value.is_nan());
+                assert_eq!(value, if degrees < 25.0 { 1.0 } else { 0.0 }, "{} grados", degrees);
+            }
+        }
+    }
+
+    #[test]
+    fn spot_lights_only_the_points_inside_its_cone() {
+        // Desde (0, 5, 0) hacia abajo, sin atenuación, sobre el piso y = 0
+        let spot = Light::spot(Vec3::new(0.0, 5.0, 0.0), -Vec3::y(), 20f32.to_radians(), 30f32.to_radians(), 0.0, Color::from_rgb([1.0; 3]), 2.0);
+        let lights = only(spot);
+        let lit = |x: f32| lights.diffuse(&Vec3::y(), &Vec3::new(x, 0.0, 0.0)).r;
+        // Justo debajo llega entera, con el coseno del ángulo de llegada
+        assert!((lit(0.0) - 2.0).abs() < 1e-5);
+        let edge = |degrees: f32| 5.0 * degrees.to_radians().tan();
+        assert!((lit(edge(15.0)) - 2.0 * 15f32.to_radians().cos()).abs() < 1e-4);
+        assert!(lit(edge(25.0)) > 0.0 && lit(edge(25.0)) < lit(edge(15.0)));
+        assert_eq!((lit(edge(31.0)), lit(-edge(45.0))), (0.0, 0.0));
+
+        // Con la dirección en cero apunta hacia -Z, sin NaN
+        let pointless = only(Light::spot(Vec3::zeros(), Vec3::zeros(), 0.3, 0.5, 0.0, Color::from_rgb([1.0; 3]), 1.0));
+        assert!((pointless.diffuse(&Vec3::z(), &Vec3::new(0.0, 0.0, -3.0)).r - 1.0).abs() < 1e-5);
+        assert!(!pointless.diffuse(&Vec3::z(), &Vec3::zeros()).r.is_nan());
+    }
+}
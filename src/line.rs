@@ -2,16 +2,114 @@
 
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
+use nalgebra_glm::Vec3;
 
-pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
+// Patrón de trazos al estilo de OpenGL: cada bit de `pattern`, desde el menos
+// significativo, dice si se dibujan `factor` píxeles seguidos
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Stipple {
+    pub pattern: u16,
+    pub factor: usize,
+}
+
+impl Stipple {
+    pub const SOLID: Stipple = Stipple { pattern: 0xFFFF, factor: 1 };
+    pub const DASHED: Stipple = Stipple { pattern: 0x00FF, factor: 1 };
+
+    // Si se dibuja el píxel número `counter` contado desde el inicio del trazo
+    pub fn draws(self, counter: usize) -> bool {
+        (self.pattern >> ((counter / self.factor.max(1)) % 16)) & 1 == 1
+    }
+}
+
+// Rectángulo de recorte en píxeles: cubre de (`x`, `y`) a (`x + width`, `y + height`)
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ClipRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+// Cohen-Sutherland: recorta el segmento en pantalla al rectángulo antes de recorrerlo,
+// así una línea que sale lejos de la ventana no avanza píxel por píxel por afuera.
+// El color y la profundidad (lo que usan las líneas) se recortan junto con la
+// posición. `None` si el segmento no toca el rectángulo
+pub fn clip_to_rect(a: &Vertex, b: &Vertex, rect: ClipRect) -> Option<(Vertex, Vertex)> {
+    // El borde derecho e inferior no son parte del rectángulo: se recorta un poco antes
+    // para que `floor` caiga en el último píxel de adentro
+    let inset = 1e-3;
+    let (min_x, min_y) = (rect.x, rect.y);
+    let (max_x, max_y) = (rect.x + rect.width - inset, rect.y + rect.height - inset);
+    let outcode = |p: &Vec3| {
+        ((p.x < min_x) as u8) | ((p.x > max_x) as u8) << 1 | ((p.y < min_y) as u8) << 2 | ((p.y > max_y) as u8) << 3
+    };
 
-    let x0 = a.position.x as i32;
-    let y0 = a.position.y as i32;
-    let x1 = b.position.x as i32;
-    let y1 = b.position.y as i32;
-    let color = a.color; // Usa el color del primer vértice o interpolación si lo prefieres
-    let depth = 0.0; // Profundidad predeterminada, ajustar según necesidades
+    let (start, end) = (a.transformed_position, b.transformed_position);
+    let at = |t: f32, point: Vec3| Vertex { transformed_position: point, color: a.color.lerp(b.color, t), ..a.clone() };
+    // Fracción del segmento original donde está cada extremo recortado
+    let (mut t0, mut t1) = (0.0_f32, 1.0_f32);
+    let (mut p0, mut p1) = (start, end);
+    let (mut code0, mut code1) = (outcode(&p0), outcode(&p1));
+    loop {
+        if code0 | code1 == 0 {
+            return Some((at(t0, p0), at(t1, p1)));
+        }
+        // Los dos extremos del mismo lado de un borde: el segmento queda afuera
+        if code0 & code1 != 0 {
+            return None;
+        }
+        // Se mueve un extremo de afuera hasta el borde que cruza
+        let code = if code0 != 0 { code0 } else { code1 };
+        let (border, along_x) = if code & 1 != 0 {
+            (min_x, true)
+        } else if code & 2 != 0 {
+            (max_x, true)
+        } else if code & 4 != 0 {
+            (min_y, false)
+        } else {
+            (max_y, false)
+        };
+        let t = if along_x { (border - start.x) / (end.x - start.x) } else { (border - start.y) / (end.y - start.y) };
+        let point = start + (end - start) * t;
+        // El cruce queda justo sobre el borde, sin el error del cálculo
+        let point = if along_x { Vec3::new(border, point.y, point.z) } else { Vec3::new(point.x, border, point.z) };
+        if code == code0 {
+            (t0, p0) = (t, point);
+            code0 = outcode(&p0);
+        } else {
+            (t1, p1) = (t, point);
+            code1 = outcode(&p1);
+        }
+    }
+}
+
+// Bresenham entre las posiciones en pantalla de `a` y `b`, con los dos extremos.
+// Llama a `emit` con cada píxel; el color y la profundidad se interpolan a lo largo
+// del segmento (en pantalla la profundidad varía linealmente, como en `triangle`).
+// Con `thickness` mayor que 1 cada píxel se repite a lo ancho del eje corto, así
+// que los extremos quedan cortados en cuadrado; con 1 es la línea simple
+pub fn line_with(a: &Vertex, b: &Vertex, thickness: usize, emit: impl FnMut(Fragment)) {
+    line_stippled(a, b, thickness, Stipple::SOLID, &mut 0, emit);
+}
+
+// Como `line_with`, pero salta los píxeles que el patrón deja vacíos. `counter` es
+// el largo acumulado del trazo: al pasar el mismo a los segmentos de una polilínea, el
+// patrón sigue donde quedó en vez de empezar de nuevo. El píxel final de un segmento
+// y el inicial del siguiente son el mismo y llevan el mismo número
+pub fn line_stippled(
+    a: &Vertex,
+    b: &Vertex,
+    thickness: usize,
+    stipple: Stipple,
+    counter: &mut usize,
+    mut emit: impl FnMut(Fragment),
+) {
+    let (start, end) = (a.transformed_position, b.transformed_position);
+    let x0 = start.x.floor() as i32;
+    let y0 = start.y.floor() as i32;
+    let x1 = end.x.floor() as i32;
+    let y1 = end.y.floor() as i32;
 
     let dx = (x1 - x0).abs();
     let dy = -(y1 - y0).abs();
@@ -19,11 +117,30 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
     let sy = if y0 < y1 { 1 } else { -1 };
     let mut err = dx + dy;
 
+    // Cada vuelta avanza un píxel en el eje más largo
+    let steps = dx.max(-dy).max(1) as f32;
+    // El grosor se reparte a los dos lados del píxel central, sobre el eje corto
+    let (across_x, across_y) = if dx >= -dy { (0, 1) } else { (1, 0) };
+    let thickness = thickness.max(1) as i32;
+    let offsets = -(thickness - 1) / 2..=thickness / 2;
+    let mut step = 0_usize;
+
     let mut x = x0;
     let mut y = y0;
 
-    while x != x1 || y != y1 {
-        fragments.push(Fragment::new(x as f32, y as f32, color, depth));
+    loop {
+        let t = step as f32 / steps;
+        let depth = start.z + (end.z - start.z) * t;
+        let color = a.color.lerp(b.color, t);
+        if stipple.draws(*counter + step) {
+            for offset in offsets.clone() {
+                emit(Fragment::new((x + offset * across_x) as f32, (y + offset * across_y) as f32, color, depth));
+            }
+        }
+        if x == x1 && y == y1 {
+            *counter += step;
+            break;
+        }
 
         let e2 = 2 * err;
         if e2 >= dy {
@@ -34,7 +151,202 @@ pub fn line(a: &Vertex, b: &Vertex) -> Vec<Fragment> {
             err += dx;
             y += sy;
         }
+        step += 1;
+    }
+}
+
+// Línea suavizada de Wu: en cada columna (o fila, si la línea es empinada) reparte
+// el píxel entre los dos más cercanos a la recta según la distancia a cada centro.
+// Las dos coberturas suman 1; `render` las mezcla con lo que ya hay en el framebuffer
+pub fn line_aa(a: &Vertex, b: &Vertex, mut emit: impl FnMut(Fragment)) {
+    // Se trabaja con los centros de píxel en coordenadas enteras
+    let center = Vec3::new(0.5, 0.5, 0.0);
+    let (start, end) = (a.transformed_position - center, b.transformed_position - center);
+    let steep = (end.y - start.y).abs() > (end.x - start.x).abs();
+    // (eje largo, eje corto, fracción del camino de `a` a `b`)
+    let axes = |point: Vec3, t: f32| if steep { (point.y, point.x, t) } else { (point.x, point.y, t) };
+    let (mut from, mut to) = (axes(start, 0.0), axes(end, 1.0));
+    if from.0 > to.0 {
+        std::mem::swap(&mut from, &mut to);
+    }
+    let length = to.0 - from.0;
+    let gradient = if length > f32::EPSILON { (to.1 - from.1) / length } else { 0.0 };
+
+    for major in from.0.round() as i32..=to.0.round() as i32 {
+        let along = if length > f32::EPSILON { ((major as f32 - from.0) / length).clamp(0.0, 1.0) } else { 0.0 };
+        let t = from.2 + (to.2 - from.2) * along;
+        let depth = start.z + (end.z - start.z) * t;
+        let color = a.color.lerp(b.color, t);
+
+        let minor = from.1 + gradient * (major as f32 - from.0);
+        let (base, fraction) = (minor.floor(), minor - minor.floor());
+        for (minor, coverage) in [(base, 1.0 - fraction), (base + 1.0, fraction)] {
+            if coverage <= 0.0 {
+                continue;
+            }
+            let (x, y) = if steep { (minor, major as f32) } else { (major as f32, minor) };
+            emit(Fragment { coverage, ..Fragment::new(x, y, color, depth) });
+        }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
 
-    fragments
+    fn point(x: f32, y: f32) -> Vertex {
+        Vertex { transformed_position: Vec3::new(x, y, 0.5), ..Vertex::default() }
+    }
+
+    // Los píxeles de la línea, ordenados; ninguno sale dos veces
+    fn pixels(a: (f32, f32), b: (f32, f32), thickness: usize) -> Vec<(i32, i32)> {
+        let mut pixels = Vec::new();
+        line_with(&point(a.0, a.1), &point(b.0, b.1), thickness, |f| pixels.push((f.position.x as i32, f.position.y as i32)));
+        let count = pixels.len();
+        pixels.sort_unstable();
+        pixels.dedup();
+        assert_eq!(pixels.len(), count, "píxeles repetidos");
+        pixels
+    }
+
+    #[test]
+    fn thick_lines_cover_width_times_length() {
+        // 20 píxeles de largo en cada dirección, con los dos extremos
+        let lines = [((2.5, 10.5), (21.5, 10.5)), ((10.5, 2.5), (10.5, 21.5)), ((2.5, 2.5), (21.5, 21.5))];
+        for (a, b) in lines {
+            for thickness in [1, 3, 5] {
+                assert_eq!(pixels(a, b, thickness).len(), 20 * thickness, "{:?} a {:?} de {}", a, b, thickness);
+            }
+        }
+        // El grosor se reparte parejo a los dos lados, sobre el eje corto
+        let rows = |thickness| {
+            let mut rows: Vec<i32> = pixels((2.5, 10.5), (21.5, 10.5), thickness).iter().map(|&(_, y)| y).collect();
+            rows.sort_unstable();
+            rows.dedup();
+            rows
+        };
+        assert_eq!(rows(3), vec![9, 10, 11]);
+        assert_eq!(rows(5), vec![8, 9, 10, 11, 12]);
+        let columns: Vec<i32> = pixels((10.5, 2.5), (10.5, 21.5), 3).iter().map(|&(x, _)| x).collect();
+        assert!(columns.iter().all(|x| (9..=11).contains(x)));
+    }
+
+    #[test]
+    fn width_one_is_the_plain_bresenham_line() {
+        assert_eq!(pixels((0.5, 0.5), (5.5, 2.5), 1), vec![(0, 0), (1, 0), (2, 1), (3, 1), (4, 2), (5, 2)]);
+        assert_eq!(pixels((0.5, 0.5), (2.5, 5.5), 1), vec![(0, 0), (0, 1), (1, 2), (1, 3), (2, 4), (2, 5)]);
+        // Con más grosor la línea del medio es la misma
+        let wide = pixels((0.5, 0.5), (5.5, 2.5), 3);
+        assert!(pixels((0.5, 0.5), (5.5, 2.5), 1).iter().all(|pixel| wide.contains(pixel)));
+    }
+
+    #[test]
+    fn wu_line_splits_each_column_between_two_pixels() {
+        // 30 grados sobre la horizontal, de centro de píxel a centro de píxel en X
+        let slope = 30f32.to_radians().tan();
+        let (a, b) = (point(2.5, 5.5), point(42.5, 5.5 + 40.0 * slope));
+        let mut columns = vec![Vec::new(); 43];
+        line_aa(&a, &b, |f| columns[f.position.x as usize].push((f.position.y, f.coverage)));
+
+        assert!(columns[..2].iter().all(Vec::is_empty));
+        // El primer centro cae justo sobre la recta: un píxel entero
+        assert_eq!(columns[2], vec![(5.0, 1.0)]);
+        for (x, column) in columns.iter().enumerate().skip(3) {
+            assert_eq!(column.len(), 2, "columna {}: {:?}", x, column);
+            let ((top, top_coverage), (bottom, bottom_coverage)) = (column[0], column[1]);
+            assert_eq!(bottom, top + 1.0);
+            assert!(top_coverage > 0.0 && top_coverage < 1.0 && bottom_coverage > 0.0 && bottom_coverage < 1.0);
+            assert!((top_coverage + bottom_coverage - 1.0).abs() < 1e-5, "columna {}: {:?}", x, column);
+            // El centro de la recta queda donde lo ponen las coberturas
+            let center = top * top_coverage + bottom * bottom_coverage;
+            assert!((center - (5.0 + (x as f32 - 2.0) * slope)).abs() < 1e-3, "columna {}: {}", x, center);
+        }
+    }
+
+    // Un tramo de polilínea, de un punto al otro
+    type Segment = ((f32, f32), (f32, f32));
+
+    fn stippled(segments: &[Segment], stipple: Stipple, counter: &mut usize) -> Vec<(i32, i32)> {
+        let mut pixels = Vec::new();
+        for &(a, b) in segments {
+            line_stippled(&point(a.0, a.1), &point(b.0, b.1), 1, stipple, counter, |f| pixels.push((f.position.x as i32, f.position.y as i32)));
+        }
+        pixels.sort_unstable();
+        pixels.dedup();
+        pixels
+    }
+
+    #[test]
+    fn dash_phase_continues_across_segments() {
+        // Una polilínea en L: 10 píxeles a la derecha y 20 hacia abajo
+        let corner = [((0.5, 0.5), (10.5, 0.5)), ((10.5, 0.5), (10.5, 20.5))];
+        let mut counter = 0;
+        let pixels = stippled(&corner, Stipple::DASHED, &mut counter);
+        assert_eq!(counter, 30);
+        // El píxel número `i` del trazo completo, contando la esquina una sola vez
+        let expected: Vec<(i32, i32)> = (0..=30)
+            .filter(|i| i % 16 < 8)
+            .map(|i| if i <= 10 { (i, 0) } else { (10, i - 10) })
+            .collect();
+        assert_eq!(pixels, expected);
+
+        // Lo mismo que una sola línea recta del mismo largo, partida en dos
+        let straight = stippled(&[((0.5, 4.5), (30.5, 4.5))], Stipple::DASHED, &mut 0);
+        let split = stippled(&[((0.5, 4.5), (12.5, 4.5)), ((12.5, 4.5), (30.5, 4.5))], Stipple::DASHED, &mut 0);
+        assert_eq!(split, straight);
+        // Empezando cada segmento de cero el segundo trazo saldría corrido
+        let restarted: Vec<(i32, i32)> = [((0.5, 4.5), (12.5, 4.5)), ((12.5, 4.5), (30.5, 4.5))]
+            .iter()
+            .flat_map(|&segment| stippled(&[segment], Stipple::DASHED, &mut 0))
+            .collect();
+        assert_ne!(restarted, straight);
+    }
+
+    #[test]
+    fn stipple_factor_stretches_each_bit() {
+        let stipple = Stipple { pattern: 0b0101, factor: 3 };
+        let drawn: Vec<bool> = (0..12).map(|i| stipple.draws(i)).collect();
+        assert_eq!(drawn, [true, true, true, false, false, false, true, true, true, false, false, false]);
+        assert!((0..100).all(|i| Stipple::SOLID.draws(i)));
+    }
+
+    const SCREEN: ClipRect = ClipRect { x: 0.0, y: 0.0, width: 800.0, height: 600.0 };
+
+    #[test]
+    fn long_line_is_clipped_to_the_screen_width() {
+        let a = Vertex { transformed_position: Vec3::new(-10000.0, 300.0, 0.0), color: Color::from_hex(0x000000), ..Vertex::default() };
+        let b = Vertex { transformed_position: Vec3::new(10800.0, 300.0, 1.0), color: Color::from_hex(0xFFFFFF), ..Vertex::default() };
+        let (start, end) = clip_to_rect(&a, &b, SCREEN).unwrap();
+
+        // La profundidad y el color se recortan en la misma fracción que la posición
+        let (t0, t1) = (10000.0 / 20800.0, 10799.999 / 20800.0);
+        assert_eq!(start.transformed_position.x, 0.0);
+        assert!((end.transformed_position.x - 799.999).abs() < 1e-3);
+        assert!((start.transformed_position.z - t0).abs() < 1e-5 && (end.transformed_position.z - t1).abs() < 1e-5);
+        assert_eq!(start.color.to_hex(), Color::from_hex(0x000000).lerp(Color::from_hex(0xFFFFFF), t0).to_hex());
+
+        let mut columns = Vec::new();
+        line_with(&start, &end, 1, |f| {
+            assert_eq!(f.position.y, 300.0);
+            columns.push(f.position.x as i32);
+        });
+        assert_eq!(columns, (0..800).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn clipping_keeps_diagonals_inside_and_drops_lines_outside() {
+        // Cruza la esquina de arriba a la izquierda: entra por un borde y sale por otro
+        let (start, end) = clip_to_rect(&point(-100.0, 150.0), &point(150.0, -100.0), SCREEN).unwrap();
+        for p in [start.transformed_position, end.transformed_position] {
+            assert!(p.x >= 0.0 && p.y >= 0.0 && (p.x + p.y - 50.0).abs() < 1e-3, "{:?}", p);
+        }
+        assert_eq!((start.transformed_position.x, end.transformed_position.y), (0.0, 0.0));
+        // Del mismo lado de un borde, o pasando por fuera de una esquina
+        assert!(clip_to_rect(&point(-5.0, -5.0), &point(900.0, -1.0), SCREEN).is_none());
+        assert!(clip_to_rect(&point(-100.0, 10.0), &point(10.0, -100.0), SCREEN).is_none());
+        // Adentro no cambia nada
+        let (start, end) = clip_to_rect(&point(10.0, 20.0), &point(700.0, 500.0), SCREEN).unwrap();
+        assert_eq!((start.transformed_position, end.transformed_position), (Vec3::new(10.0, 20.0, 0.5), Vec3::new(700.0, 500.0, 0.5)));
+    }
 }
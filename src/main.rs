@@ -1,23 +1,64 @@
-use crate::obj::Obj;
 use crate::vertex::Vertex;
+use crate::fragment::Fragment;
 use crate::color::Color;
-use crate::model::Model3D;
-use crate::triangle::triangle;
+use crate::model::{Aabb, ImportOptions, LoadError, Model3D};
+use crate::material::Material;
+use crate::scene::{ModelId, Scene};
+use crate::camera::{Camera, CameraMode, Projection, ProjectionMode, ViewState, ViewStateError};
+use crate::watch::FileWatcher;
+use crate::args::{Args, ArgsError, USAGE};
+use crate::triangle::{depth_slope, face_normal, is_degenerate, signed_area, triangle_with, CullMode, Rasterizer, Winding};
+use crate::clip::{clip_line, clip_triangle, frustum_outcode, ClipVertex};
+use crate::line::{clip_to_rect, line_aa, line_stippled, line_with, ClipRect, Stipple};
+use crate::light::{Light, LightKind, Lights, Rim, Shading, Shadow, Surface};
+use crate::shadow::ShadowMap;
+use crate::fog::Fog;
+use crate::shader::{builtin, DefaultVertexShader, DepthOnlyShader, FragmentShader, ShaderRegistry, TextureShader, ToonShader, VertexShader, WaveVertexShader};
+use crate::texture::Texture;
+use crate::utils::{normal_matrix, thousands, viewport_matrix};
 use pixels::{Pixels, SurfaceTexture};
 use winit::dpi::LogicalSize;
-use winit::event::{Event, MouseScrollDelta, WindowEvent, ElementState, MouseButton};
+use winit::event::{Event, MouseScrollDelta, WindowEvent, ElementState, MouseButton, KeyboardInput, VirtualKeyCode};
 use winit::event_loop::{ControlFlow, EventLoop};
-use winit::window::WindowBuilder;
-use nalgebra_glm::{Vec3, Mat4, rotate_x, rotate_y};
+use winit::window::{Window, WindowBuilder};
+use nalgebra_glm::{Vec2, Vec3, Mat3, Mat4};
+use std::fmt;
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+use std::collections::HashSet;
+use std::time::{Duration, Instant};
 
 mod obj;
+mod stl;
+mod ply;
+mod json;
+mod gltf;
 mod vertex;
 mod color;
 mod fragment;
 mod line;
 mod triangle;
 mod model;
+mod scene;
+mod camera;
+mod material;
+mod light;
+mod fog;
+mod shadow;
+mod noise;
+mod texture;
+mod shader;
 mod utils;
+mod clip;
+mod draw2d;
+mod args;
+mod watch;
+
+// Mezcla `color` con un píxel 0xRRGGBB: `coverage` = 1 lo reemplaza
+fn blend(pixel: u32, color: Color, coverage: f32) -> u32 {
+    Color::from_hex(pixel).lerp(color, coverage).to_hex()
+}
 
 #[derive(Debug)]
 struct Framebuffer {
@@ -41,6 +82,26 @@ impl Framebuffer {
         }
     }
 
+    // Promedia cada bloque de `factor` x `factor` píxeles de `samples` en un píxel de
+    // este framebuffer (filtro de caja). No hay corrección gamma en el programa: los
+    // canales se promedian tal como se guardan
+    fn downsample(&mut self, samples: &Framebuffer, factor: usize) {
+        let count = (factor * factor) as u32;
+        for y in 0..self.height.min(samples.height / factor) {
+            for x in 0..self.width.min(samples.width / factor) {
+                let (mut r, mut g, mut b) = (0, 0, 0);
+                for sample_y in y * factor..(y + 1) * factor {
+                    for &pixel in &samples.buffer[sample_y * samples.width + x * factor..][..factor] {
+                        r += (pixel >> 16) & 0xFF;
+                        g += (pixel >> 8) & 0xFF;
+                        b += pixel & 0xFF;
+                    }
+                }
+                self.buffer[y * self.width + x] = ((r / count) << 16) | ((g / count) << 8) | (b / count);
+            }
+        }
+    }
+
     fn clear(&mut self, color: u32) {
         for pixel in &mut self.buffer {
             *pixel = color;
@@ -48,78 +109,1369 @@ impl Framebuffer {
     }
 }
 
+// Dibujo 2D encima de la escena, para indicadores y paneles: sin profundidad y
+// recortado a la ventana
+impl Framebuffer {
+    // Mezcla `color` con lo que ya tiene el píxel
+    fn blend_color(&mut self, x: usize, y: usize, color: Color, coverage: f32) {
+        if x < self.width && y < self.height {
+            let index = y * self.width + x;
+            self.buffer[index] = blend(self.buffer[index], color, coverage);
+        }
+    }
+
+    // Mezcla `color` de `from` a `to` (incluidos) en la fila `y`; con `opacity` = 1 lo
+    // pinta encima
+    fn fill_span(&mut self, y: i32, from: i32, to: i32, color: Color, opacity: f32) {
+        if y < 0 || y >= self.height as i32 {
+            return;
+        }
+        let (from, to) = (from.max(0), to.min(self.width as i32 - 1));
+        if from > to {
+            return;
+        }
+        let row = y as usize * self.width;
+        if opacity >= 1.0 {
+            self.buffer[row + from as usize..=row + to as usize].fill(color.to_hex());
+        } else {
+            (from..=to).for_each(|x| self.blend_color(x as usize, y as usize, color, opacity));
+        }
+    }
+
+    fn plot(&mut self, x: i32, y: i32, color: u32) {
+        if x >= 0 && y >= 0 {
+            self.set_current_color(x as usize, y as usize, color);
+        }
+    }
+
+    fn draw_circle(&mut self, cx: i32, cy: i32, radius: i32, thickness: usize, color: u32) {
+        draw2d::circle(cx, cy, radius, thickness, |x, y| self.plot(x, y, color));
+    }
+
+    fn fill_circle(&mut self, cx: i32, cy: i32, radius: i32, color: Color) {
+        draw2d::fill_circle(cx, cy, radius, |y, from, to| self.fill_span(y, from, to, color, 1.0));
+    }
+
+    // Polígono en píxeles, cóncavo o no, relleno por par-impar y recortado además a `viewport`
+    fn fill_polygon(&mut self, points: &[(f32, f32)], viewport: &Viewport, color: Color, opacity: f32) {
+        let (left, right) = (viewport.x as i32, (viewport.x + viewport.width) as i32 - 1);
+        let rows = viewport.y as i32..(viewport.y + viewport.height) as i32;
+        draw2d::fill_polygon(points, rows, |y, from, to| self.fill_span(y, from.max(left), to.min(right), color, opacity));
+    }
+}
+
+// Rectángulo de tijera: fuera de él no se dibuja. Se aplica encima del
+// viewport, en píxeles del framebuffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Scissor {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl Scissor {
+    // Parte de `columns` y `rows` que queda dentro del rectángulo (vacía si no se tocan)
+    fn clamp(&self, columns: Range<usize>, rows: Range<usize>) -> (Range<usize>, Range<usize>) {
+        let clamp = |range: Range<usize>, start: usize, len: usize| {
+            let (start, end) = (range.start.max(start), range.end.min(start + len));
+            start..end.max(start)
+        };
+        (clamp(columns, self.x, self.width), clamp(rows, self.y, self.height))
+    }
+}
+
+// Rectángulo del framebuffer donde se dibuja una vista, en píxeles, y cómo se
+// guarda la profundidad en el z-buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Viewport {
+    x: usize,
+    y: usize,
+    width: usize,
+    height: usize,
+    // Z invertida: el plano cercano queda en 1 y el lejano en 0, y gana el mayor
+    reversed_z: bool,
+}
+
+impl Viewport {
+    fn full(framebuffer: &Framebuffer) -> Self {
+        Self { x: 0, y: 0, width: framebuffer.width, height: framebuffer.height, reversed_z: false }
+    }
+
+    // Cuatro vistas en una cuadrícula de 2x2: arriba a la izquierda, arriba a la
+    // derecha, abajo a la izquierda y abajo a la derecha
+    fn quad(framebuffer: &Framebuffer) -> [Self; 4] {
+        let (left, top) = (framebuffer.width / 2, framebuffer.height / 2);
+        let (right, bottom) = (framebuffer.width - left, framebuffer.height - top);
+        let full = Self::full(framebuffer);
+        [
+            Self { x: 0, y: 0, width: left, height: top, ..full },
+            Self { x: left, y: 0, width: right, height: top, ..full },
+            Self { x: 0, y: top, width: left, height: bottom, ..full },
+            Self { x: left, y: top, width: right, height: bottom, ..full },
+        ]
+    }
+
+    fn aspect(&self) -> f32 {
+        self.width as f32 / self.height.max(1) as f32
+    }
+
+    fn matrix(&self) -> Mat4 {
+        let matrix = viewport_matrix(self.x as f32, self.y as f32, self.width as f32, self.height as f32);
+        if self.reversed_z {
+            // z -> 1 - z
+            Mat4::new_translation(&Vec3::z()) * Mat4::new_nonuniform_scaling(&Vec3::new(1.0, 1.0, -1.0)) * matrix
+        } else {
+            matrix
+        }
+    }
+
+    // True si `depth` está más cerca del ojo que lo que ya guarda el z-buffer
+    fn is_closer(&self, depth: f32, stored: f32) -> bool {
+        if self.reversed_z { depth > stored } else { depth < stored }
+    }
+
+    // `depth` acercada al ojo en `bias` unidades del z-buffer
+    fn toward_eye(&self, depth: f32, bias: f32) -> f32 {
+        if self.reversed_z { depth + bias } else { depth - bias }
+    }
+
+    // El mismo rectángulo, para recortar líneas
+    fn clip_rect(&self) -> ClipRect {
+        ClipRect { x: self.x as f32, y: self.y as f32, width: self.width as f32, height: self.height as f32 }
+    }
+
+    // Píxel del framebuffer donde cae un fragmento, o `None` si queda fuera del
+    // viewport. Los rasterizadores entregan el índice del píxel cuyo centro
+    // (x + 0.5, y + 0.5) muestrearon, así que se redondea hacia abajo. Se compara
+    // antes de convertir a usize: `as` lleva los negativos a 0 y pintaría la
+    // primera columna o fila con lo que cae a la izquierda o arriba
+    fn pixel(&self, position: Vec2) -> Option<(usize, usize)> {
+        let (x, y) = (position.x.floor(), position.y.floor());
+        let inside = x >= self.x as f32 && x < (self.x + self.width) as f32 && y >= self.y as f32 && y < (self.y + self.height) as f32;
+        inside.then_some((x as usize, y as usize))
+    }
+}
+
+// Cómo se reparte el dibujo entre los hilos
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Pipeline {
+    #[default]
+    Bands, // Una franja horizontal por hilo; cada hilo recorre todos los triángulos
+    Tiles, // Teselas cuadradas, cada una con la lista de los triángulos que la tocan
+}
+
+// Qué se dibuja de cada triángulo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RenderMode {
+    #[default]
+    Fill,             // Triángulos rellenos
+    Wireframe,        // Solo las aristas
+    FillAndWireframe, // Las aristas encima del relleno
+    Points,           // Un cuadrado por vértice, sin triángulos
+}
+
+#[derive(Clone)]
 pub struct Uniforms {
-    pub model_matrix: Mat4,
+    pub model_matrix: Mat4,      // Se aplica encima de la transformación de cada modelo
+    pub view_matrix: Mat4,       // Mundo -> cámara
+    pub projection_matrix: Mat4, // Cámara -> coordenadas de recorte
+    pub normal_matrix: Mat3,     // Normales de mundo -> cámara (inversa transpuesta de vista * modelo)
+    pub rasterizer: Rasterizer,  // Recorrido de píxeles de `triangle`
+    pub cull_mode: CullMode,     // Caras que no se dibujan
+    pub winding: Winding,        // Orden de los vértices de las caras de frente
+    pub render_mode: RenderMode, // Relleno, aristas o los dos
+    pub wire_color: Color,       // Color de las aristas
+    pub wire_width: usize,       // Grosor de las aristas en píxeles
+    pub wire_antialias: bool,    // Aristas suavizadas (siempre de un píxel)
+    pub point_size: usize,       // Lado en píxeles de cada punto en `RenderMode::Points`
+    pub threads: usize,          // Hilos que reparten el dibujo (1 = todo en el hilo principal)
+    pub pipeline: Pipeline,      // Franjas o teselas
+    pub tile_size: usize,        // Lado en píxeles de las teselas de `Pipeline::Tiles`
+    pub scissor: Option<Scissor>, // Solo se dibuja dentro de este rectángulo (`None` = todo)
+    // Desplazamiento de profundidad de los triángulos al compararlos con el z-buffer:
+    // constante + pendiente * `depth_slope` del triángulo, hacia el ojo
+    pub depth_bias_constant: f32,
+    pub depth_bias_slope: f32,
+    pub shading: Shading,        // Luz por cara, por vértice o por píxel
+    // Depuración del relleno: cada triángulo del modelo de un color fijo según su
+    // índice, sin luz ni niebla pero con el z-buffer de siempre
+    pub triangle_colors: bool,
+    pub vertex_shader: Arc<dyn VertexShader>, // Lleva cada vértice a coordenadas de recorte
+    // Color de cada píxel de los triángulos; sin uno propio se usa el de `shading`
+    pub fragment_shader: Option<Arc<dyn FragmentShader>>,
 }
 
 impl Uniforms {
-    pub fn new(translation: Vec3, scale: f32, rotation: Mat4) -> Self {
-        let model_matrix = Mat4::new_translation(&translation)
-            * rotation
-            * Mat4::new_nonuniform_scaling(&Vec3::new(scale, scale, scale));
-        Self { model_matrix }
+    pub fn new(model_matrix: Mat4, view_matrix: Mat4, projection_matrix: Mat4) -> Self {
+        let normal_matrix = normal_matrix(&(view_matrix * model_matrix));
+        Self {
+            model_matrix,
+            view_matrix,
+            projection_matrix,
+            normal_matrix,
+            rasterizer: Rasterizer::default(),
+            cull_mode: CullMode::default(),
+            winding: Winding::default(),
+            render_mode: RenderMode::default(),
+            wire_color: Color::from_hex(0xFFFFFF),
+            wire_width: 1,
+            wire_antialias: false,
+            point_size: 3,
+            threads: 1,
+            pipeline: Pipeline::default(),
+            tile_size: 32,
+            scissor: None,
+            depth_bias_constant: 0.0,
+            depth_bias_slope: 0.0,
+            shading: Shading::default(),
+            triangle_colors: false,
+            vertex_shader: Arc::new(DefaultVertexShader),
+            fragment_shader: None,
+        }
+    }
+
+    // Lo que ven los shaders al dibujar `model`: su transformación combinada con la
+    // de estos uniforms. La inversa transpuesta de un producto es el producto de las
+    // inversas transpuestas
+    fn for_model(&self, model: &Model3D) -> Uniforms {
+        Uniforms {
+            model_matrix: self.model_matrix * model.model_matrix(),
+            normal_matrix: self.normal_matrix * normal_matrix(&model.model_matrix()),
+            ..self.clone()
+        }
+    }
+
+    // El shader de fragmentos que se usa: el propio o el del modo de luz
+    fn shader(&self) -> &dyn FragmentShader {
+        self.fragment_shader.as_deref().unwrap_or_else(|| builtin(self.shading))
+    }
+}
+
+// Lo que usa la luz y lo que cambia con el tiempo, aparte de las matrices y el estado
+// del pipeline de `Uniforms`. `render` lo recibe junto a los uniforms y los shaders
+// también. Antes todo iba en `Uniforms`:
+//
+//     let uniforms = Uniforms { lights, fog, ..Uniforms::new(model, view, projection, time, delta_time) };
+//     render_scene(&mut framebuffer, &mut z_buffer, &uniforms, &viewport, &scene);
+//     // y en un shader: uniforms.lights, uniforms.time
+//
+// Ahora:
+//
+//     let uniforms = Uniforms::new(model, view, projection);
+//     let params = ShadingParams { lights, fog, ..ShadingParams::new(camera.eye(), time, delta_time) };
+//     render_scene(&mut framebuffer, &mut z_buffer, &uniforms, &params, &viewport, &scene);
+//     // y en un shader: fn shade(&self, fragment, uniforms, params) con params.lights, params.time
+#[derive(Debug, Clone)]
+pub struct ShadingParams {
+    pub lights: Lights,   // En el mundo; los shaders las reciben en espacio de cámara
+    pub eye: Vec3,        // Posición de la cámara en el mundo (en espacio de cámara es el origen)
+    pub fog: Fog,         // Se aplica al color que da el shader de fragmentos
+    pub rim: Option<Rim>, // Luz de contorno de los shaders que iluminan por píxel
+    // Para mallas abiertas: la normal se da vuelta hacia el ojo antes de iluminar, así
+    // el lado de atrás no queda negro. Manda sobre `Uniforms::cull_mode`: no se
+    // descarta ninguna cara, porque las de atrás son justamente las que se quieren ver
+    pub two_sided: bool,
+    pub time: f32,        // Segundos desde que arrancó el programa
+    pub delta_time: f32,  // Segundos desde el cuadro anterior
+}
+
+impl ShadingParams {
+    pub fn new(eye: Vec3, time: f32, delta_time: f32) -> Self {
+        ShadingParams { lights: Lights::default(), eye, fog: Fog::default(), rim: None, two_sided: false, time, delta_time }
+    }
+
+    // Los mismos parámetros con las luces en espacio de cámara, donde se ilumina
+    fn in_camera(&self, view_matrix: &Mat4) -> ShadingParams {
+        ShadingParams { lights: self.lights.in_camera(view_matrix), ..self.clone() }
+    }
+}
+
+// Contorno de Shift+R sin `--rim-color`: azul, para que se vea sobre un fondo oscuro
+const RIM_COLOR: u32 = 0x3080FF;
+
+// Velocidad del modo de vuelo en unidades de mundo por segundo (el modelo mide 1)
+const FLY_SPEED: f32 = 1.0;
+
+// Velocidades de las teclas: radianes por segundo, altos de pantalla por segundo y
+// factor de zoom (e^n por segundo)
+const KEY_ROTATE_SPEED: f32 = std::f32::consts::FRAC_PI_2;
+const KEY_PAN_SPEED: f32 = 0.5;
+const KEY_ZOOM_SPEED: f32 = 1.0;
+
+// Movimiento suave: qué tan rápido la cámara dibujada alcanza a la de la entrada
+// (1/s) y cuánto frena el giro que sigue al soltar el arcball con impulso
+const SMOOTHING_RATE: f32 = 12.0;
+const SPIN_FRICTION: f32 = 3.0;
+
+// Tiempo sin tocar el mouse ni el teclado tras el que la vista empieza a girar sola
+const IDLE_DELAY: Duration = Duration::from_secs(3);
+
+// Cuánto se acercan las aristas al ojo para ganarle al relleno de su propio
+// triángulo, además de la pendiente de profundidad (en unidades del z-buffer)
+const WIRE_DEPTH_BIAS: f32 = 1e-6;
+
+// Ángulos en grados del cono de la linterna (tecla Y): entera hasta el primero y
+// nada desde el segundo
+const FLASHLIGHT_CONE: (f32, f32) = (12.0, 18.0);
+
+// Color de las guías que se dibujan encima de la vista, como el círculo del arcball
+const GIZMO_COLOR: u32 = 0xC0C0C0;
+
+// Radianes por segundo que giran las figuras de la demo (Shift+P)
+const PRIMITIVES_SPIN: f32 = 0.6;
+
+// Vista guardada con Ctrl+S, en la carpeta desde donde se ejecuta
+const VIEW_STATE_PATH: &str = "view.json";
+
+// Textura del shader "Textura" sin `--texture`, relativa a donde se ejecuta como el modelo
+const TEST_TEXTURE_PATH: &str = "assets/uv.png";
+
+// Contadores de un cuadro para ver cuánto trabajo se ahorra el descarte
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+struct RenderStats {
+    models_culled: usize,        // Modelos con la caja fuera del frustum
+    triangles_submitted: usize,  // Triángulos de los modelos que pasaron la caja
+    triangles_rasterized: usize, // Los que llegaron a generar fragmentos
+    triangles_degenerate: usize, // Los que quedaron sin área en pantalla
+    triangles_backface: usize,   // Los descartados por mirar hacia el otro lado
+    points_drawn: usize,         // Vértices dibujados en el modo de puntos
+    tiles_binned: usize,         // Teselas con algo que dibujar, con `Pipeline::Tiles`
+    tile_triangles: usize,       // Suma de los triángulos de cada tesela (uno grande cuenta varias veces)
+    max_tile_triangles: usize,   // Los de la tesela más cargada
+    render_time: Duration,       // Lo que tardó en dibujarse el cuadro, con la reducción
+    ssaa: usize,                 // Muestras por lado de cada píxel (1 = sin supermuestreo)
+    downsample_time: Duration,   // Lo que tardó en reducirse el supermuestreo
+}
+
+impl std::ops::AddAssign for RenderStats {
+    fn add_assign(&mut self, other: Self) {
+        self.models_culled += other.models_culled;
+        self.triangles_submitted += other.triangles_submitted;
+        self.triangles_rasterized += other.triangles_rasterized;
+        self.triangles_degenerate += other.triangles_degenerate;
+        self.triangles_backface += other.triangles_backface;
+        self.points_drawn += other.points_drawn;
+        self.tiles_binned += other.tiles_binned;
+        self.tile_triangles += other.tile_triangles;
+        self.max_tile_triangles = self.max_tile_triangles.max(other.max_tile_triangles);
+        self.render_time += other.render_time;
+        self.ssaa = self.ssaa.max(other.ssaa);
+        self.downsample_time += other.downsample_time;
+    }
+}
+
+impl fmt::Display for RenderStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let culled = self.triangles_submitted - self.triangles_rasterized;
+        let percent = 100.0 * culled as f32 / self.triangles_submitted.max(1) as f32;
+        write!(
+            f,
+            "{} triángulos enviados, {} dibujados ({:.0}% descartados, {} sin área, {} de espaldas), {} modelos fuera de la vista, {:.1} ms",
+            thousands(self.triangles_submitted),
+            thousands(self.triangles_rasterized),
+            percent,
+            thousands(self.triangles_degenerate),
+            thousands(self.triangles_backface),
+            thousands(self.models_culled),
+            self.render_time.as_secs_f64() * 1000.0,
+        )?;
+        if self.points_drawn > 0 {
+            write!(f, ", {} puntos", thousands(self.points_drawn))?;
+        }
+        if self.ssaa > 1 {
+            write!(
+                f,
+                ", supermuestreo {}x{} ({} veces los píxeles, {:.1} ms en reducirlo)",
+                self.ssaa,
+                self.ssaa,
+                self.ssaa * self.ssaa,
+                self.downsample_time.as_secs_f64() * 1000.0,
+            )?;
+        }
+        if self.tiles_binned > 0 {
+            write!(
+                f,
+                ", {} teselas con {:.1} triángulos en promedio (máximo {})",
+                thousands(self.tiles_binned),
+                self.tile_triangles as f32 / self.tiles_binned as f32,
+                thousands(self.max_tile_triangles),
+            )?;
+        }
+        Ok(())
     }
 }
 
+// Valor con el que se limpia el z-buffer: más lejos que cualquier fragmento
+fn depth_clear_value(reversed_z: bool) -> f32 {
+    if reversed_z { f32::NEG_INFINITY } else { f32::INFINITY }
+}
+
+// División por w y viewport; solo deben llegar vértices delante del plano cercano (w > 0)
+fn project(clip_vertex: &ClipVertex, viewport_matrix: &Mat4) -> Vertex {
+    let clip = clip_vertex.clip;
+    let ndc = nalgebra_glm::vec4(clip.x / clip.w, clip.y / clip.w, clip.z / clip.w, 1.0);
+    let screen = viewport_matrix * ndc;
+    Vertex {
+        transformed_position: Vec3::new(screen.x, screen.y, screen.z),
+        transformed_w: clip.w,
+        ..clip_vertex.vertex.clone()
+    }
+}
+
+// Punto de espacio de mundo en espacio de cámara, donde se calcula la luz
+fn camera_position(view_matrix: &Mat4, world: &Vec3) -> Vec3 {
+    (view_matrix * nalgebra_glm::vec4(world.x, world.y, world.z, 1.0)).xyz()
+}
+
+// Color de depuración del triángulo `index` del modelo: el índice mezclado como un
+// hash da el tono, así dos triángulos seguidos (las dos mitades de un cuadrilátero)
+// casi nunca se parecen, y el mismo triángulo tiene el mismo color en cada cuadro
+fn triangle_color(index: usize) -> Color {
+    let mut hash = (index as u32).wrapping_mul(0x9E37_79B9);
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85EB_CA6B);
+    hash ^= hash >> 13;
+    Color::from_hsv((hash >> 8) as f32 / (1 << 24) as f32, 0.65, 0.95)
+}
+
+// `normal` dada vuelta si apunta lejos del ojo, que está en el origen; las dos en
+// espacio de cámara
+fn toward_eye(normal: Vec3, position: &Vec3) -> Vec3 {
+    if normal.dot(position) > 0.0 { -normal } else { normal }
+}
+
+// Lo que queda de un modelo en pantalla después de recortar y descartar: se arma
+// una vez y luego cada hilo dibuja de aquí su franja
+#[derive(Default)]
+struct ScreenGeometry {
+    vertices: Vec<Vertex>,        // Vértices proyectados, más los que agrega el recorte
+    triangles: Vec<([usize; 3], Color)>, // Índices a `vertices`, sin los que no tienen área, y la luz de su cara (o su color con `triangle_colors`)
+    edges: Vec<(Vertex, Vertex)>, // Aristas proyectadas, corridas hacia el ojo
+    points: Vec<Vertex>,          // Vértices del modo de puntos, ya iluminados
+}
+
+// Rectángulo del framebuffer que dibuja un solo hilo (una franja o una tesela), con
+// su parte de los colores y del z-buffer: los hilos no comparten nada y no hace falta
+// bloquear. `colors` y `depths` tienen una fila de `columns` por cada fila de `rows`.
+// Solo se pinta en `visible`, las columnas de `columns` que dejan el viewport y la tijera
+struct Target<'a> {
+    columns: Range<usize>,
+    visible: Range<usize>,
+    rows: Range<usize>,
+    colors: &'a mut [u32],
+    depths: &'a mut [f32],
+}
+
+impl Target<'_> {
+    // Prueba de profundidad del fragmento, si cae en el rectángulo y en el viewport.
+    // `bias` solo cuenta al comparar: se guarda la profundidad real, así lo que se
+    // dibuje después compite con la superficie y no con la versión desplazada
+    // `shade` da el color final y solo se llama si el fragmento pasa la prueba; si
+    // devuelve `None` el fragmento se descarta sin tocar el z-buffer
+    fn plot(&mut self, viewport: &Viewport, bias: f32, fragment: Fragment, shade: impl FnOnce(&Fragment) -> Option<Color>) {
+        // Lo que cae fuera del viewport pertenece a otra vista
+        let Some((x, y)) = viewport.pixel(fragment.position) else { return };
+        if !self.visible.contains(&x) || !self.rows.contains(&y) {
+            return;
+        }
+        let index = (y - self.rows.start) * self.columns.len() + (x - self.columns.start);
+
+        // Verificar y actualizar el z-buffer. Un píxel cubierto a medias se mezcla
+        // y no guarda su profundidad: lo que está detrás sigue viéndose
+        if viewport.is_closer(viewport.toward_eye(fragment.depth, bias), self.depths[index]) {
+            let Some(color) = shade(&fragment) else { return };
+            if fragment.coverage < 1.0 {
+                self.colors[index] = blend(self.colors[index], color, fragment.coverage);
+            } else {
+                self.depths[index] = fragment.depth;
+                self.colors[index] = color.to_hex();
+            }
+        }
+    }
+
+    // Triángulos, luego aristas y luego puntos, en el orden en que llegan. Las aristas
+    // van después de todos los rellenos para que solo las tape la geometría que de
+    // verdad está delante
+    fn draw<'g>(
+        &mut self,
+        uniforms: &Uniforms,
+        params: &ShadingParams,
+        viewport: &Viewport,
+        vertices: &[Vertex],
+        (triangles, edges, points): (
+            impl Iterator<Item = &'g ([usize; 3], Color)>,
+            impl Iterator<Item = &'g (Vertex, Vertex)>,
+            impl Iterator<Item = &'g Vertex>,
+        ),
+    ) {
+        // El rectángulo de cada triángulo se recorta de entrada a lo visible: la tijera
+        // no cuesta nada por píxel
+        let columns = self.visible.start as i32..self.visible.end as i32;
+        let rows = self.rows.start as i32..self.rows.end as i32;
+        let biased = uniforms.depth_bias_constant != 0.0 || uniforms.depth_bias_slope != 0.0;
+        let shader = uniforms.shader();
+        for &([a, b, c], face_light) in triangles {
+            let (a, b, c) = (&vertices[a], &vertices[b], &vertices[c]);
+            let bias = if biased { uniforms.depth_bias_constant + uniforms.depth_bias_slope * depth_slope(a, b, c) } else { 0.0 };
+            triangle_with(a, b, c, uniforms.rasterizer, columns.clone(), rows.clone(), |mut fragment| {
+                fragment.face_light = face_light;
+                if params.two_sided {
+                    fragment.normal = toward_eye(fragment.normal, &camera_position(&uniforms.view_matrix, &fragment.world_position));
+                }
+                self.plot(viewport, bias, fragment, |fragment| {
+                    if uniforms.triangle_colors {
+                        return Some(fragment.face_light);
+                    }
+                    let color = shader.shade(fragment, uniforms, params)?;
+                    Some(params.fog.apply(color, -camera_position(&uniforms.view_matrix, &fragment.world_position).z))
+                })
+            });
+        }
+        let mut plot = |fragment: Fragment| self.plot(viewport, 0.0, fragment, |fragment| Some(fragment.color));
+        for (a, b) in edges {
+            if uniforms.wire_antialias {
+                line_aa(a, b, &mut plot);
+            } else {
+                line_with(a, b, uniforms.wire_width, &mut plot);
+            }
+        }
+        // Cuadrado de `point_size` x `point_size` centrado en el píxel del vértice
+        let half = (uniforms.point_size / 2) as f32;
+        for vertex in points {
+            let x0 = vertex.transformed_position.x.floor() - half;
+            let y0 = vertex.transformed_position.y.floor() - half;
+            for dy in 0..uniforms.point_size {
+                for dx in 0..uniforms.point_size {
+                    plot(Fragment::new(x0 + dx as f32, y0 + dy as f32, vertex.color, vertex.transformed_position.z));
+                }
+            }
+        }
+    }
+}
+
+// `map` sobre `items` repartido en `threads` hilos, en el mismo orden
+fn parallel_map<T: Sync, U: Send>(items: &[T], threads: usize, map: impl Fn(&T) -> U + Sync) -> Vec<U> {
+    if threads <= 1 || items.len() < 2 {
+        return items.iter().map(map).collect();
+    }
+    let chunk = items.len().div_ceil(threads);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = items
+            .chunks(chunk)
+            .map(|part| scope.spawn(|| part.iter().map(&map).collect::<Vec<U>>()))
+            .collect();
+        handles.into_iter().flat_map(|handle| handle.join().expect("hilo de render")).collect()
+    })
+}
+
 fn render(
     framebuffer: &mut Framebuffer,
-    z_buffer: &mut Vec<f32>,
+    z_buffer: &mut [f32],
+    uniforms: &Uniforms,
+    params: &ShadingParams,
+    viewport: &Viewport,
+    model: &Model3D,
+) -> RenderStats {
+    let mut stats = RenderStats::default();
+    let uniforms = &uniforms.for_model(model);
+    let params = &params.in_camera(&uniforms.view_matrix);
+    // Modelo -> mundo -> cámara
+    let model_view = uniforms.view_matrix * uniforms.model_matrix;
+    let clip_matrix = uniforms.projection_matrix * model_view;
+    let to_clip = |position: &Vec3| clip_matrix * nalgebra_glm::vec4(position.x, position.y, position.z, 1.0);
+
+    // Si las 8 esquinas de la caja quedan del lado de afuera de un mismo plano,
+    // ningún triángulo del modelo puede verse
+    let Some(bounds) = model.bounding_box() else { return stats };
+    if uniforms.vertex_shader.keeps_bounds() && bounds.corners().iter().fold(u8::MAX, |code, corner| code & frustum_outcode(&to_clip(corner))) != 0 {
+        stats.models_culled = 1;
+        return stats;
+    }
+
+    let geometry = screen_geometry(uniforms, params, viewport, model, &mut stats);
+    rasterize(framebuffer, z_buffer, uniforms, params, viewport, &geometry, &mut stats);
+    stats
+}
+
+// Vértices a pantalla, recorte y descarte de triángulos: todo lo que se hace una
+// vez por triángulo y no por píxel
+// `uniforms` son los de `Uniforms::for_model` y `params` los de `ShadingParams::in_camera`
+fn screen_geometry(uniforms: &Uniforms, params: &ShadingParams, viewport: &Viewport, model: &Model3D, stats: &mut RenderStats) -> ScreenGeometry {
+    // Último paso: de coordenadas normalizadas a los píxeles del viewport
+    let viewport_matrix = viewport.matrix();
+    let lights = &params.lights;
+    // Los puntos se iluminan aparte, con la normal hacia donde convenga
+    let gouraud = uniforms.shader().lit_vertices() && uniforms.render_mode != RenderMode::Points;
+
+    // Con geometría indexada cada vértice compartido se transforma una sola vez.
+    // Se guardan en coordenadas de recorte: la división por w viene después del recorte
+    let transformed_vertices: Vec<(u8, ClipVertex)> = parallel_map(&model.vertices, uniforms.threads, |original| {
+        let vertex = uniforms.vertex_shader.shade(original, uniforms, params);
+        let clip = nalgebra_glm::vec4(vertex.transformed_position.x, vertex.transformed_position.y, vertex.transformed_position.z, vertex.transformed_w);
+        // Gouraud: el vértice sale ya iluminado y `triangle` interpola el color. Sin
+        // normal mira hacia el ojo, igual que la cara en flat shading
+        let color = if gouraud {
+            let position = camera_position(&uniforms.view_matrix, &vertex.position);
+            let normal = if vertex.transformed_normal == Vec3::zeros() { Vec3::z() } else { vertex.transformed_normal };
+            let normal = if params.two_sided { toward_eye(normal, &position) } else { normal };
+            lights.lambert(&Surface::of(&vertex), &normal, &position).clamped()
+        } else {
+            vertex.color
+        };
+        // Los patrones en espacio de modelo se pegan a la superficie sin importar lo
+        // que haga el shader de vértices
+        (frustum_outcode(&clip), ClipVertex { clip, vertex: Vertex { color, model_position: original.position, ..vertex } })
+    });
+
+    // Solo se usan los vértices adentro de todos los planos (w > 0) o los que
+    // salen del recorte; los demás se proyectan igual para no mover los índices
+    let project = |clip_vertex: &ClipVertex| project(clip_vertex, &viewport_matrix);
+    let mut geometry = ScreenGeometry {
+        vertices: parallel_map(&transformed_vertices, uniforms.threads, |(_, clip_vertex)| project(clip_vertex)),
+        ..ScreenGeometry::default()
+    };
+
+    // Nube de puntos: se recorren los vértices sueltos, sin armar triángulos
+    if uniforms.render_mode == RenderMode::Points {
+        for ((code, _), vertex) in transformed_vertices.iter().zip(&geometry.vertices) {
+            // Afuera de algún plano, incluido el cercano: así nunca se divide por un w <= 0
+            if *code != 0 {
+                continue;
+            }
+            // Misma luz que los triángulos. Un punto no tiene lado de atrás, así que
+            // la normal se da vuelta hacia el ojo; sin normal, se ve entero
+            let position = camera_position(&uniforms.view_matrix, &vertex.position);
+            let normal = toward_eye(vertex.transformed_normal, &position);
+            let color = if normal == Vec3::zeros() { vertex.color } else { lights.lambert(&Surface::of(vertex), &normal, &position).clamped() };
+            geometry.points.push(Vertex { color, ..vertex.clone() });
+        }
+        stats.points_drawn += geometry.points.len();
+        return geometry;
+    }
+
+    for (index, [i1, i2, i3]) in model.triangles().enumerate() {
+        stats.triangles_submitted += 1;
+        let ((c1, v1), (c2, v2), (c3, v3)) = (&transformed_vertices[i1], &transformed_vertices[i2], &transformed_vertices[i3]);
+        // Los tres vértices afuera del mismo plano: el triángulo no entra al frustum
+        if c1 & c2 & c3 != 0 {
+            continue;
+        }
+
+        // Lo que cruza un plano se recorta contra él: así el rectángulo que recorre
+        // `triangle` no pasa del viewport, y dividir por un w <= 0 (detrás del
+        // plano cercano) reflejaría la geometría
+        let planes = c1 | c2 | c3;
+        let polygon: Vec<usize> = if planes == 0 {
+            vec![i1, i2, i3]
+        } else {
+            let clipped = clip_triangle([v1.clone(), v2.clone(), v3.clone()], planes);
+            let first = geometry.vertices.len();
+            geometry.vertices.extend(clipped.iter().map(project));
+            (first..geometry.vertices.len()).collect()
+        };
+        if polygon.len() < 3 {
+            continue;
+        }
+        let vertices = &geometry.vertices;
+        let corner = |i: usize| &vertices[polygon[i]];
+
+        // El recorte conserva el orden de los vértices: el área del abanico completo
+        // dice hacia dónde mira el triángulo original
+        let area: i64 = (1..polygon.len() - 1).map(|i| signed_area(corner(0), corner(i), corner(i + 1))).sum();
+        if area != 0 && !params.two_sided && uniforms.cull_mode.culls(uniforms.winding, area) {
+            stats.triangles_backface += 1;
+            continue;
+        }
+
+        // El polígono recortado es convexo: se dibuja como abanico desde el primer vértice.
+        // Los pedazos sin área (vértices repetidos o alineados) no recorren su rectángulo
+        let fan: Vec<usize> = (1..polygon.len() - 1).filter(|&i| !is_degenerate(corner(0), corner(i), corner(i + 1))).collect();
+        let Some(&first) = fan.first() else {
+            stats.triangles_degenerate += 1;
+            continue;
+        };
+        stats.triangles_rasterized += 1;
+
+        // Las aristas del triángulo original, no las del abanico: se recortan por
+        // separado para no dibujar los bordes que agrega el recorte
+        if matches!(uniforms.render_mode, RenderMode::Wireframe | RenderMode::FillAndWireframe) {
+            // Un píxel de la línea puede caer hasta un píxel al lado de la arista, donde
+            // el relleno ya cambió de profundidad según la pendiente del triángulo
+            let bias = WIRE_DEPTH_BIAS + depth_slope(corner(0), corner(first), corner(first + 1));
+            let bias = if viewport.reversed_z { bias } else { -bias };
+            let with_bias = |clip_vertex: &ClipVertex| {
+                let vertex = project(clip_vertex);
+                Vertex {
+                    color: uniforms.wire_color,
+                    transformed_position: vertex.transformed_position + Vec3::new(0.0, 0.0, bias),
+                    ..vertex
+                }
+            };
+            for (a, b) in [(v1, v2), (v2, v3), (v3, v1)] {
+                // El recorte contra el frustum ya las deja casi adentro; el del
+                // viewport quita lo que quede afuera por redondeo
+                let Some((a, b)) = clip_line(a, b, planes) else { continue };
+                if let Some(edge) = clip_to_rect(&with_bias(&a), &with_bias(&b), viewport.clip_rect()) {
+                    geometry.edges.push(edge);
+                }
+            }
+        }
+        if matches!(uniforms.render_mode, RenderMode::Fill | RenderMode::FillAndWireframe) {
+            // La luz difusa de la cara para `Fragment::face_light` se calcula con el
+            // triángulo original, en su centro: todos los pedazos del abanico son la
+            // misma cara. Sin recortar: la ambiente y la luz propia se suman al pintar
+            let center = camera_position(&uniforms.view_matrix, &((v1.vertex.position + v2.vertex.position + v3.vertex.position) / 3.0));
+            let normal = face_normal(&v1.vertex, &v2.vertex, &v3.vertex);
+            let normal = if params.two_sided { toward_eye(normal, &center) } else { normal };
+            let light = if uniforms.triangle_colors { triangle_color(index) } else { lights.diffuse(&normal, &center) };
+            geometry.triangles.extend(fan.iter().map(|&i| ([polygon[0], polygon[i], polygon[i + 1]], light)));
+        }
+    }
+
+    geometry
+}
+
+fn rasterize(
+    framebuffer: &mut Framebuffer,
+    z_buffer: &mut [f32],
     uniforms: &Uniforms,
-    vertex_array: &[Vertex],
+    params: &ShadingParams,
+    viewport: &Viewport,
+    geometry: &ScreenGeometry,
+    stats: &mut RenderStats,
 ) {
-    let transformed_vertices: Vec<Vertex> = vertex_array
-        .iter()
-        .map(|vertex| {
-            let position = nalgebra_glm::vec4(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
-            let transformed = uniforms.model_matrix * position;
-            let transformed_position = Vec3::new(transformed.x, transformed.y, transformed.z);
-            Vertex {
-                position: vertex.position,
-                color: vertex.color,
-                transformed_position,
-                ..*vertex
+    match uniforms.pipeline {
+        Pipeline::Bands => rasterize_bands(framebuffer, z_buffer, uniforms, params, viewport, geometry),
+        Pipeline::Tiles => rasterize_tiles(framebuffer, z_buffer, uniforms, params, viewport, geometry, stats),
+    }
+}
+
+// Filas y columnas del framebuffer que cubre el viewport, dentro de la tijera si hay
+fn viewport_pixels(framebuffer: &Framebuffer, viewport: &Viewport, scissor: Option<&Scissor>) -> (Range<usize>, Range<usize>) {
+    let columns = viewport.x.min(framebuffer.width)..(viewport.x + viewport.width).min(framebuffer.width);
+    let rows = viewport.y.min(framebuffer.height)..(viewport.y + viewport.height).min(framebuffer.height);
+    match scissor {
+        Some(scissor) => scissor.clamp(columns, rows),
+        None => (columns, rows),
+    }
+}
+
+// Dibuja la geometría en franjas horizontales del viewport, una por hilo. Cada
+// franja recorre todo en el mismo orden que un solo hilo, así que el resultado es
+// idéntico con cualquier cantidad de hilos
+fn rasterize_bands(
+    framebuffer: &mut Framebuffer,
+    z_buffer: &mut [f32],
+    uniforms: &Uniforms,
+    params: &ShadingParams,
+    viewport: &Viewport,
+    geometry: &ScreenGeometry,
+) {
+    let width = framebuffer.width;
+    let (columns, rows) = viewport_pixels(framebuffer, viewport, uniforms.scissor.as_ref());
+    if columns.is_empty() || rows.is_empty() {
+        return;
+    }
+    let rows_per_band = rows.len().div_ceil(uniforms.threads.max(1));
+    let colors = framebuffer.buffer[rows.start * width..rows.end * width].chunks_mut(rows_per_band * width);
+    let depths = z_buffer[rows.start * width..rows.end * width].chunks_mut(rows_per_band * width);
+    let bands = colors.zip(depths).enumerate().map(|(i, (colors, depths))| {
+        let start = rows.start + i * rows_per_band;
+        Target { columns: 0..width, visible: columns.clone(), rows: start..start + colors.len() / width, colors, depths }
+    });
+
+    let draw = |mut band: Target| {
+        let primitives = (geometry.triangles.iter(), geometry.edges.iter(), geometry.points.iter());
+        band.draw(uniforms, params, viewport, &geometry.vertices, primitives);
+    };
+    if uniforms.threads <= 1 {
+        bands.for_each(draw);
+    } else {
+        std::thread::scope(|scope| {
+            for band in bands {
+                scope.spawn(move || draw(band));
+            }
+        });
+    }
+}
+
+// Tesela de `tile_size` x `tile_size` píxeles con copia propia de sus colores y
+// profundidades, y la lista de lo que la toca (índices a `ScreenGeometry`)
+struct Tile {
+    columns: Range<usize>,
+    rows: Range<usize>,
+    colors: Vec<u32>,
+    depths: Vec<f32>,
+    triangles: Vec<usize>,
+    edges: Vec<usize>,
+    points: Vec<usize>,
+}
+
+impl Tile {
+    fn is_empty(&self) -> bool {
+        self.triangles.is_empty() && self.edges.is_empty() && self.points.is_empty()
+    }
+}
+
+// Primero reparte cada triángulo, arista y punto en las teselas que toca su rectángulo
+// envolvente; después cada hilo dibuja teselas enteras sin mirar lo que no les cae
+// encima, en una copia chica que cabe en caché, y al final se copian al framebuffer.
+// Dentro de cada tesela el orden es el de la geometría: da lo mismo que `rasterize_bands`
+fn rasterize_tiles(
+    framebuffer: &mut Framebuffer,
+    z_buffer: &mut [f32],
+    uniforms: &Uniforms,
+    params: &ShadingParams,
+    viewport: &Viewport,
+    geometry: &ScreenGeometry,
+    stats: &mut RenderStats,
+) {
+    let width = framebuffer.width;
+    let (columns, rows) = viewport_pixels(framebuffer, viewport, uniforms.scissor.as_ref());
+    if columns.is_empty() || rows.is_empty() {
+        return;
+    }
+    let size = uniforms.tile_size.max(1);
+    let (tiles_x, tiles_y) = (columns.len().div_ceil(size), rows.len().div_ceil(size));
+    let mut tiles: Vec<Tile> = (0..tiles_x * tiles_y)
+        .map(|i| {
+            let (x, y) = (columns.start + (i % tiles_x) * size, rows.start + (i / tiles_x) * size);
+            Tile {
+                columns: x..(x + size).min(columns.end),
+                rows: y..(y + size).min(rows.end),
+                colors: Vec::new(),
+                depths: Vec::new(),
+                triangles: Vec::new(),
+                edges: Vec::new(),
+                points: Vec::new(),
             }
         })
         .collect();
 
-    for triangle_vertices in transformed_vertices.chunks(3) {
-        if triangle_vertices.len() == 3 {
-            let fragments = triangle(
-                &triangle_vertices[0],
-                &triangle_vertices[1],
-                &triangle_vertices[2],
-            );
+    // Teselas que toca el rectángulo de píxeles [min, max] (incluidos), recortado al viewport
+    let covered = |(min_x, min_y): (f32, f32), (max_x, max_y): (f32, f32)| {
+        let tile_of = |value: f32, range: &Range<usize>, count: usize| {
+            ((value.floor() - range.start as f32) / size as f32).clamp(0.0, (count - 1) as f32) as usize
+        };
+        let off_screen = max_x < columns.start as f32 || min_x >= columns.end as f32 || max_y < rows.start as f32 || min_y >= rows.end as f32;
+        let (x0, x1) = (tile_of(min_x, &columns, tiles_x), tile_of(max_x, &columns, tiles_x));
+        let (y0, y1) = (tile_of(min_y, &rows, tiles_y), tile_of(max_y, &rows, tiles_y));
+        (y0..=y1).flat_map(move |y| (x0..=x1).map(move |x| y * tiles_x + x)).filter(move |_| !off_screen)
+    };
+    let position = |vertex: &Vertex| (vertex.transformed_position.x, vertex.transformed_position.y);
+    let bounds = |points: &[(f32, f32)], margin: f32| {
+        let min = points.iter().fold((f32::MAX, f32::MAX), |(x, y), p| (x.min(p.0), y.min(p.1)));
+        let max = points.iter().fold((f32::MIN, f32::MIN), |(x, y), p| (x.max(p.0), y.max(p.1)));
+        ((min.0 - margin, min.1 - margin), (max.0 + margin, max.1 + margin))
+    };
+    for (index, &([a, b, c], _)) in geometry.triangles.iter().enumerate() {
+        let corners = [position(&geometry.vertices[a]), position(&geometry.vertices[b]), position(&geometry.vertices[c])];
+        let (min, max) = bounds(&corners, 0.0);
+        covered(min, max).for_each(|tile| tiles[tile].triangles.push(index));
+    }
+    // Las líneas gruesas y los puntos se salen un poco del segmento o del vértice
+    let line_margin = uniforms.wire_width as f32 / 2.0 + 1.0;
+    for (index, (a, b)) in geometry.edges.iter().enumerate() {
+        let (min, max) = bounds(&[position(a), position(b)], line_margin);
+        covered(min, max).for_each(|tile| tiles[tile].edges.push(index));
+    }
+    let point_margin = uniforms.point_size as f32 / 2.0 + 1.0;
+    for (index, vertex) in geometry.points.iter().enumerate() {
+        let (min, max) = bounds(&[position(vertex)], point_margin);
+        covered(min, max).for_each(|tile| tiles[tile].points.push(index));
+    }
+
+    let mut tiles: Vec<Tile> = tiles.into_iter().filter(|tile| !tile.is_empty()).collect();
+    stats.tiles_binned += tiles.len();
+    for tile in &mut tiles {
+        stats.tile_triangles += tile.triangles.len();
+        stats.max_tile_triangles = stats.max_tile_triangles.max(tile.triangles.len());
+        // Copia de lo que ya hay: la tesela sigue el dibujo de los modelos anteriores
+        for y in tile.rows.clone() {
+            let row = y * width;
+            tile.colors.extend_from_slice(&framebuffer.buffer[row + tile.columns.start..row + tile.columns.end]);
+            tile.depths.extend_from_slice(&z_buffer[row + tile.columns.start..row + tile.columns.end]);
+        }
+    }
+
+    let draw = |tile: &mut Tile| {
+        let mut target = Target {
+            columns: tile.columns.clone(),
+            visible: tile.columns.clone(),
+            rows: tile.rows.clone(),
+            colors: &mut tile.colors,
+            depths: &mut tile.depths,
+        };
+        let triangles = tile.triangles.iter().map(|&i| &geometry.triangles[i]);
+        let edges = tile.edges.iter().map(|&i| &geometry.edges[i]);
+        let points = tile.points.iter().map(|&i| &geometry.points[i]);
+        target.draw(uniforms, params, viewport, &geometry.vertices, (triangles, edges, points));
+    };
+    let threads = uniforms.threads.max(1).min(tiles.len());
+    if threads <= 1 {
+        tiles.iter_mut().for_each(draw);
+    } else {
+        // Se reparten intercaladas: las teselas vecinas, con trabajo parecido, quedan
+        // en hilos distintos
+        let mut shares: Vec<Vec<&mut Tile>> = (0..threads).map(|_| Vec::new()).collect();
+        for (i, tile) in tiles.iter_mut().enumerate() {
+            shares[i % threads].push(tile);
+        }
+        std::thread::scope(|scope| {
+            for share in shares {
+                scope.spawn(move || share.into_iter().for_each(draw));
+            }
+        });
+    }
 
-            for fragment in fragments {
-                let x = fragment.position.x as usize;
-                let y = fragment.position.y as usize;
+    for tile in &tiles {
+        for (i, y) in tile.rows.clone().enumerate() {
+            let (row, tile_row) = (y * width, i * tile.columns.len());
+            let span = tile_row..tile_row + tile.columns.len();
+            framebuffer.buffer[row + tile.columns.start..row + tile.columns.end].copy_from_slice(&tile.colors[span.clone()]);
+            z_buffer[row + tile.columns.start..row + tile.columns.end].copy_from_slice(&tile.depths[span]);
+        }
+    }
+}
+
+// Dibuja todos los modelos visibles contra el mismo z-buffer, así la
+// profundidad se resuelve entre objetos distintos
+fn render_scene(
+    framebuffer: &mut Framebuffer,
+    z_buffer: &mut [f32],
+    uniforms: &Uniforms,
+    params: &ShadingParams,
+    viewport: &Viewport,
+    scene: &Scene,
+) -> RenderStats {
+    let mut stats = RenderStats::default();
+    for model in scene.visible_models() {
+        stats += render(framebuffer, z_buffer, uniforms, params, viewport, model);
+    }
+    stats
+}
 
-                if x < framebuffer.width && y < framebuffer.height {
-                    let index = y * framebuffer.width + x;
+// Cuánto tiñe la silueta de la caja envolvente lo que hay detrás
+const BOUNDS_FILL_OPACITY: f32 = 0.12;
 
-                    // Verificar y actualizar el z-buffer
-                    if fragment.depth < z_buffer[index] {
-                        z_buffer[index] = fragment.depth;
+// Caja envolvente de cada modelo visible, con línea punteada para que no se confunda
+// con las aristas. Es una guía: se dibuja encima de todo, sin mirar el z-buffer
+fn render_bounds(framebuffer: &mut Framebuffer, uniforms: &Uniforms, viewport: &Viewport, scene: &Scene) {
+    let viewport_matrix = viewport.matrix();
+    // Índices de `Aabb::corners`: las dos tapas como polilíneas cerradas y las cuatro aristas verticales
+    const STROKES: [&[usize]; 6] = [&[0, 1, 3, 2, 0], &[4, 5, 7, 6, 4], &[0, 4], &[1, 5], &[2, 6], &[3, 7]];
+    for model in scene.visible_models() {
+        let Some(bounds) = model.bounding_box() else { continue };
+        let clip_matrix = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * model.model_matrix();
+        let corners = bounds.corners().map(|corner| ClipVertex {
+            clip: clip_matrix * nalgebra_glm::vec4(corner.x, corner.y, corner.z, 1.0),
+            vertex: Vertex { color: uniforms.wire_color, ..Vertex::new(corner, Vec3::zeros(), Vec2::zeros()) },
+        });
+        // Silueta teñida debajo de las aristas: la envolvente de las esquinas en
+        // pantalla. Con una esquina detrás del ojo la proyección se da vuelta y no se tiñe
+        if corners.iter().all(|corner| corner.clip.w > 0.0) {
+            let screen = corners.each_ref().map(|corner| {
+                let position = project(corner, &viewport_matrix).transformed_position;
+                (position.x, position.y)
+            });
+            framebuffer.fill_polygon(&draw2d::convex_hull(&screen), viewport, uniforms.wire_color, BOUNDS_FILL_OPACITY);
+        }
+        for stroke in STROKES {
+            let mut counter = 0;
+            for pair in stroke.windows(2) {
+                let Some((a, b)) = clip_line(&corners[pair[0]], &corners[pair[1]], u8::MAX) else { continue };
+                let (a, b) = (project(&a, &viewport_matrix), project(&b, &viewport_matrix));
+                let Some((a, b)) = clip_to_rect(&a, &b, viewport.clip_rect()) else { continue };
+                line_stippled(&a, &b, 1, Stipple::DASHED, &mut counter, |fragment| {
+                    if let Some((x, y)) = viewport.pixel(fragment.position) {
                         framebuffer.set_current_color(x, y, fragment.color.to_hex());
                     }
-                }
+                });
             }
         }
     }
 }
 
+// Distancia al ojo de un punto a profundidad `depth` del z-buffer (0 en el plano
+// cercano y 1 en el lejano), con la inversa de la proyección
+fn eye_distance(inverse_projection: &Mat4, depth: f32) -> f32 {
+    let eye = inverse_projection * nalgebra_glm::vec4(0.0, 0.0, depth * 2.0 - 1.0, 1.0);
+    -eye.z / eye.w
+}
+
+// Distancia al ojo de cada píxel de `viewport`, fila por fila, con el ancho y el
+// alto de la parte que cae en el framebuffer. En perspectiva la profundidad guardada
+// se amontona cerca del plano lejano; la distancia, en cambio, crece parejo. Los
+// píxeles sin nada dibujado guardan el valor con que se limpió y quedan en infinito
+fn eye_distances(framebuffer: &Framebuffer, z_buffer: &[f32], projection_matrix: &Mat4, viewport: &Viewport) -> (usize, usize, Vec<f32>) {
+    let inverse = projection_matrix.try_inverse().unwrap_or_else(Mat4::identity);
+    let distance = |depth: f32| {
+        if !depth.is_finite() {
+            return f32::INFINITY;
+        }
+        eye_distance(&inverse, if viewport.reversed_z { 1.0 - depth } else { depth })
+    };
+    let (columns, rows) = (viewport.width.min(framebuffer.width.saturating_sub(viewport.x)), viewport.height.min(framebuffer.height.saturating_sub(viewport.y)));
+    let distances = (0..rows)
+        .flat_map(|y| z_buffer[(viewport.y + y) * framebuffer.width + viewport.x..][..columns].iter().map(|&depth| distance(depth)))
+        .collect();
+    (columns, rows, distances)
+}
+
+// Qué se ve en lugar de la escena al depurar el z-buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum DepthView {
+    #[default]
+    Off,
+    Planes,    // Grises entre el plano cercano y el lejano
+    AutoRange, // Grises entre el píxel más cercano y el más lejano del cuadro
+    ShadowMap, // El mapa de sombras del sol, visto desde la luz
+}
+
+// Color de los píxeles sin nada en `render_depth`: no se confunde con ningún gris
+const DEPTH_BACKGROUND: u32 = 0x000040;
+
+// Reemplaza lo dibujado en `viewport` por la distancia al ojo de cada píxel: blanco
+// lo más cercano y negro lo más lejano. Lo que no tiene profundidad (el fondo, y los
+// bordes suavizados, que no la guardan) queda azul oscuro
+fn render_depth(framebuffer: &mut Framebuffer, z_buffer: &[f32], projection_matrix: &Mat4, viewport: &Viewport, view: DepthView) {
+    let (columns, rows, distances) = eye_distances(framebuffer, z_buffer, projection_matrix, viewport);
+    let finite = distances.iter().copied().filter(|distance| distance.is_finite());
+    let (near, far) = match view {
+        DepthView::Off | DepthView::ShadowMap => return,
+        DepthView::Planes => {
+            let inverse = projection_matrix.try_inverse().unwrap_or_else(Mat4::identity);
+            (eye_distance(&inverse, 0.0), eye_distance(&inverse, 1.0))
+        }
+        DepthView::AutoRange => finite.fold((f32::INFINITY, f32::NEG_INFINITY), |(near, far), distance| (near.min(distance), far.max(distance))),
+    };
+    let range = (far - near).max(f32::EPSILON);
+    for y in 0..rows {
+        for x in 0..columns {
+            let distance = distances[y * columns + x];
+            let color = if distance.is_finite() {
+                let gray = (1.0 - (distance - near) / range).clamp(0.0, 1.0);
+                Color::from_rgb([gray; 3]).to_hex()
+            } else {
+                DEPTH_BACKGROUND
+            };
+            framebuffer.set_current_color(viewport.x + x, viewport.y + y, color);
+        }
+    }
+}
+
+// Primera pasada de las sombras: la profundidad de los modelos visibles vista desde
+// una luz que viaja hacia `direction`, con una ortográfica que envuelve la esfera
+// que los contiene. Usa el mismo shader de vértices que `uniforms`, así lo que se
+// deforma también hace sombra. `None` si no hay nada que dibujar
+fn render_shadow_map(scene: &Scene, uniforms: &Uniforms, params: &ShadingParams, direction: Vec3, size: usize, bias: f32) -> Option<ShadowMap> {
+    let corners: Vec<Vec3> = scene
+        .visible_models()
+        .filter_map(|model| model.transformed_bounding_box(&(uniforms.model_matrix * model.model_matrix())))
+        .flat_map(|bounds| bounds.corners())
+        .collect();
+    let bounds = Aabb::from_points(&corners)?;
+    let (center, radius) = (bounds.center(), (bounds.size().norm() * 0.5).max(1e-3));
+    let direction = direction.try_normalize(f32::EPSILON).unwrap_or_else(|| -Vec3::y());
+    let up = if direction.y.abs() > 0.99 { Vec3::z() } else { Vec3::y() };
+    // La luz se aleja dos radios: la esfera queda entre los planos a 1 y 3 radios
+    let view = nalgebra_glm::look_at(&(center - direction * 2.0 * radius), &center, &up);
+    let projection = nalgebra_glm::ortho(-radius, radius, -radius, radius, radius, 3.0 * radius);
+
+    let mut colors = Framebuffer::new(size, size);
+    let mut depths = vec![f32::INFINITY; size * size];
+    let viewport = Viewport::full(&colors);
+    let light_uniforms = Uniforms {
+        // Sin descartar caras: el piso no tiene lado de atrás
+        cull_mode: CullMode::None,
+        threads: uniforms.threads,
+        pipeline: uniforms.pipeline,
+        vertex_shader: uniforms.vertex_shader.clone(),
+        fragment_shader: Some(Arc::new(DepthOnlyShader)),
+        ..Uniforms::new(uniforms.model_matrix, view, projection)
+    };
+    let params = ShadingParams { eye: center - direction * 2.0 * radius, ..params.clone() };
+    render_scene(&mut colors, &mut depths, &light_uniforms, &params, &viewport, scene);
+    Some(ShadowMap { size, depths, light_matrix: viewport.matrix() * projection * view, bias })
+}
+
+// Depuración: el mapa de sombras en grises (blanco cerca de la luz) en el cuadrado
+// más grande que entra en `viewport`, arriba a la izquierda; sin nada, azul oscuro
+fn render_shadow_view(framebuffer: &mut Framebuffer, viewport: &Viewport, map: &ShadowMap) {
+    let side = viewport.width.min(viewport.height);
+    for y in 0..side {
+        for x in 0..side {
+            let depth = map.depths[(y * map.size / side) * map.size + x * map.size / side];
+            let color = if depth.is_finite() { Color::from_rgb([1.0 - depth.clamp(0.0, 1.0); 3]).to_hex() } else { DEPTH_BACKGROUND };
+            framebuffer.set_current_color(viewport.x + x, viewport.y + y, color);
+        }
+    }
+}
+
+// Cuánto más lejos del ojo debe estar un vecino, en proporción, para que haya contorno
+const OUTLINE_THRESHOLD: f32 = 0.05;
+
+// Contorno de las siluetas y de los saltos de profundidad, sobre lo ya dibujado en
+// `viewport`: se pinta cada píxel cubierto que tiene, a `width` píxeles o menos en
+// alguna de las cuatro direcciones, un vecino vacío o bastante más lejos. El
+// z-buffer se pasa antes a distancia al ojo: en perspectiva la profundidad guardada
+// se amontona cerca de 1 y un umbral fijo no serviría
+fn render_outline(framebuffer: &mut Framebuffer, z_buffer: &[f32], projection_matrix: &Mat4, viewport: &Viewport, color: Color, width: usize) {
+    let (columns, rows, distances) = eye_distances(framebuffer, z_buffer, projection_matrix, viewport);
+
+    let color = color.to_hex();
+    for y in 0..rows {
+        for x in 0..columns {
+            let near = distances[y * columns + x];
+            if !near.is_finite() {
+                continue;
+            }
+            // Los vecinos de afuera del viewport son de otra vista y no cuentan
+            let farther = |x: usize, y: usize| distances[y * columns + x] > near * (1.0 + OUTLINE_THRESHOLD);
+            let edge = (1..=width).any(|d| {
+                (x >= d && farther(x - d, y))
+                    || (x + d < columns && farther(x + d, y))
+                    || (y >= d && farther(x, y - d))
+                    || (y + d < rows && farther(x, y + d))
+            });
+            if edge {
+                framebuffer.set_current_color(viewport.x + x, viewport.y + y, color);
+            }
+        }
+    }
+}
+
+// Carga el modelo, informa sus estadísticas y lo deja centrado con tamaño 1
+fn load_model(path: &str, options: &ImportOptions) -> Result<Model3D, LoadError> {
+    let mut model = Model3D::load(path, options)?;
+    println!("{}: {}", path, model.stats());
+    if model.groups().len() > 1 {
+        for group in model.groups() {
+            println!("  grupo {}: {} triángulos", group.name, group.triangles.len());
+        }
+    }
+    // Con el modelo centrado y de tamaño 1, `scale` = 1.0 ocupa el 80% de la ventana
+    if let Some(normalization) = model.normalize() {
+        let center = normalization.original_bounds.center();
+        println!("  centrado desde ({:.3}, {:.3}, {:.3}) y escalado ×{:.4}", center.x, center.y, center.z, normalization.scale);
+    }
+    Ok(model)
+}
+
+// Lo que se veía antes de entrar a una demo (Shift+G, Shift+U o Shift+P), para volver al salir
+struct Demo {
+    model: ModelId, // El modelo que muestra la demo
+    model_visible: bool,
+    ground_visible: bool,
+    shader: usize,
+}
+
+// Entra a la demo de `demo_model`, que se ve sola y con `demo_shader` si lo hay, o
+// sale de ella y vuelve a lo de antes. Pedir otra demo sale primero de la activa
+fn toggle_demo(
+    scene: &mut Scene,
+    demo: &mut Option<Demo>,
+    (demo_model, demo_shader): (ModelId, Option<usize>),
+    (model_id, ground_id): (ModelId, ModelId),
+    shader: &mut usize,
+) {
+    let entering = demo.as_ref().is_none_or(|active| active.model != demo_model);
+    if let Some(previous) = demo.take() {
+        scene.set_visible(model_id, previous.model_visible);
+        scene.set_visible(ground_id, previous.ground_visible);
+        scene.set_visible(previous.model, false);
+        *shader = previous.shader;
+    }
+    if entering {
+        *demo = Some(Demo { model: demo_model, model_visible: scene.is_visible(model_id), ground_visible: scene.is_visible(ground_id), shader: *shader });
+        scene.set_visible(model_id, false);
+        scene.set_visible(ground_id, false);
+        scene.set_visible(demo_model, true);
+        *shader = demo_shader.unwrap_or(*shader);
+    }
+}
+
+// Un cubo, un toro parado y un cilindro en fila, unidos en una sola malla y cada
+// uno con su color
+fn primitives() -> Model3D {
+    let standing = nalgebra_glm::rotate_x(&Mat4::identity(), std::f32::consts::FRAC_PI_2);
+    let parts = [
+        (Model3D::cube(0.6), 0xC04040, Mat4::new_translation(&Vec3::new(-1.0, 0.0, 0.0))),
+        (Model3D::torus(0.3, 0.1, 32, 16), 0x40A040, standing),
+        (Model3D::cylinder(0.3, 0.6, 24, true), 0x4060C0, Mat4::new_translation(&Vec3::new(1.0, 0.0, 0.0))),
+    ];
+    let mut merged = Model3D::new();
+    for (mut part, color, transform) in parts {
+        part.set_material(&Material { diffuse: Color::from_hex(color), ..Material::default() });
+        merged.merge(&part, &transform);
+    }
+    // Mide 2.6 de punta a punta: así queda del tamaño de un modelo normalizado
+    merged.set_scale(1.0 / 2.6);
+    merged
+}
+
+// Apoya el piso en la parte más baja del modelo
+fn place_ground(scene: &mut Scene, model_id: ModelId, ground_id: ModelId) {
+    let floor = scene.get(model_id).and_then(Model3D::bounding_box).map_or(0.0, |bounds| bounds.min.y);
+    if let Some(ground) = scene.get_mut(ground_id) {
+        ground.set_position(Vec3::new(0.0, floor, 0.0));
+    }
+}
+
+// Si la recarga falla se conserva la malla anterior
+fn reload_model(scene: &mut Scene, model_id: ModelId, ground_id: ModelId, path: &str, options: &ImportOptions) {
+    match load_model(path, options) {
+        Ok(model) => {
+            scene.replace(model_id, model);
+            place_ground(scene, model_id, ground_id);
+        }
+        Err(err) => eprintln!("Error al recargar {}: {}", path, err),
+    }
+}
+
+// Escala para que los modelos indicados entren juntos en la ventana
+fn fit_scale(scene: &Scene, ids: &[ModelId]) -> f32 {
+    let corners: Vec<Vec3> = ids
+        .iter()
+        .filter_map(|&id| scene.get(id))
+        .filter_map(|model| model.transformed_bounding_box(&model.model_matrix()))
+        .flat_map(|bounds| [bounds.min, bounds.max])
+        .collect();
+    let largest = Aabb::from_points(&corners).map_or(1.0, |bounds| bounds.size().max());
+    if largest > f32::EPSILON { 1.0 / largest } else { 1.0 }
+}
+
+// Reparte los modelos (de tamaño 1) en una fila centrada en el origen
+fn arrange_in_row(scene: &mut Scene, ids: &[ModelId]) {
+    let spacing = 1.2;
+    let start = -spacing * (ids.len() - 1) as f32 / 2.0;
+    for (index, &id) in ids.iter().enumerate() {
+        if let Some(model) = scene.get_mut(id) {
+            model.set_position(Vec3::new(start + spacing * index as f32, 0.0, 0.0));
+        }
+    }
+}
+
+// Apunta la cámara al centro de los modelos y devuelve la escala con la que ocupan
+// ~80% del viewport vistos con la orientación actual
+fn frame_models(camera: &mut Camera, projection: &Projection, scene: &Scene, ids: &[ModelId], fit_distance: f32) -> Option<f32> {
+    // Caja en espacio de cámara: su ancho y alto son lo que se ve en pantalla
+    let view = camera.view_matrix();
+    let corners: Vec<Vec3> = ids
+        .iter()
+        .filter_map(|&id| scene.get(id))
+        .filter_map(|model| model.transformed_bounding_box(&(view * model.model_matrix())))
+        .flat_map(|bounds| [bounds.min, bounds.max])
+        .collect();
+    let bounds = Aabb::from_points(&corners)?;
+    let size = bounds.size();
+
+    let center = view.try_inverse()? * nalgebra_glm::vec4(bounds.center().x, bounds.center().y, bounds.center().z, 1.0);
+    camera.target = center.xyz();
+
+    // Un modelo ancho se limita por el ancho de la ventana, no por el alto
+    let extent = size.y.max(size.x / projection.aspect).max(f32::EPSILON);
+    let visible_height = extent / 0.8;
+    Some(match projection.mode {
+        // La cara frontal está medio fondo más cerca que el centro
+        ProjectionMode::Perspective => {
+            let distance = visible_height / projection.view_height_at(1.0) + size.z * 0.5;
+            fit_distance / distance
+        }
+        ProjectionMode::Orthographic => projection.view_height_at(fit_distance) / visible_height,
+    })
+}
+
+// Lee la vista guardada; si el archivo está dañado avisa y sigue con la vista actual
+fn load_view_state(report_missing: bool) -> Option<ViewState> {
+    match ViewState::load(VIEW_STATE_PATH) {
+        Ok(state) => Some(state),
+        Err(ViewStateError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound && !report_missing => None,
+        Err(err) => {
+            eprintln!("Aviso: no se pudo cargar {}: {}", VIEW_STATE_PATH, err);
+            None
+        }
+    }
+}
+
+// Vistas de la cuadrícula: frente, lado y arriba en ortográfica alrededor del punto
+// de mira, y la cámara libre abajo a la derecha. Cada una con el aspecto de su rectángulo
+fn quad_views(framebuffer: &Framebuffer, camera: &Camera, projection: &Projection, fit_distance: f32, scale: f32) -> [(Viewport, Camera, Projection); 4] {
+    let [front, side, top, free] = Viewport::quad(framebuffer);
+    let target = camera.target;
+    let fixed = |viewport: &Viewport, direction: Vec3, up: Vec3| {
+        let camera = Camera::new(target + direction * fit_distance, target, up);
+        let projection = Projection {
+            mode: ProjectionMode::Orthographic,
+            ortho_height: projection.view_height_at(fit_distance) / scale,
+            aspect: viewport.aspect(),
+            ..*projection
+        };
+        (*viewport, camera, projection)
+    };
+    [
+        fixed(&front, Vec3::z(), Vec3::y()),
+        fixed(&side, Vec3::x(), Vec3::y()),
+        fixed(&top, Vec3::y(), -Vec3::z()),
+        (free, *camera, Projection { aspect: free.aspect(), ..*projection }),
+    ]
+}
+
+// El título muestra el modelo y el shader de fragmentos activo
+fn set_title(window: &Window, path: &str, shader: &str) {
+    let name = Path::new(path).file_name().map_or_else(|| path.into(), |name| name.to_string_lossy());
+    window.set_title(&format!("Rust Graphics - {} - {}", name, shader));
+}
 
 fn main() {
-    let mut scale = 1.0;
-    let mut camera_angle_x = 0.0;
-    let mut camera_angle_y = 0.0;
+    let args = match Args::parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(ArgsError::Help) => {
+            println!("{}", USAGE);
+            return;
+        }
+        Err(err) => {
+            eprintln!("{}\n\n{}", err, USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    // El modelo se carga antes de abrir la ventana para que una ruta mala falle limpio
+    let mut model_path = args.model_path.clone();
+    // Los .obj sin `vn` se ven facetados o negros: se les calculan normales suaves
+    let import = ImportOptions { group_colors: args.group_colors.clone(), smooth_missing_normals: true };
+    let model = match load_model(&model_path, &import) {
+        Ok(model) => model,
+        Err(err) => {
+            eprintln!("Error al cargar {}: {}", model_path, err);
+            std::process::exit(1);
+        }
+    };
+
+    let mut scale = args.scale;
+    // El arrastre con el botón del medio gira la cámara alrededor del modelo (arcball)
+    let initial_camera = Camera::new(Vec3::new(0.0, 0.0, 3.0), Vec3::zeros(), Vec3::y());
+    let mut camera = initial_camera;
     let mut is_rotating = false;
+    let mut camera_mode = CameraMode::Orbit;
+    // En modo de vuelo, mantener el botón izquierdo gira la vista
+    let mut is_looking = false;
+    let mut pressed_keys: HashSet<VirtualKeyCode> = HashSet::new();
+    let start_time = Instant::now();
+    let mut last_frame = start_time;
+    let mut last_interaction = start_time;
+    // El arrastre con el botón derecho mueve el punto de mira en el plano de la pantalla
+    let mut is_panning = false;
+    // Con el movimiento suave, la cámara dibujada sigue a `camera` con retardo y el
+    // arcball soltado en movimiento sigue girando con esta velocidad (esfera/s)
+    let mut smoothing = args.smooth;
+    let mut throw_velocity = Vec2::zeros();
+    let mut last_arcball = Instant::now();
     let mut last_mouse_position = (0.0, 0.0);
 
-    let width = 800;
-    let height = 600;
-    let half_width = width as f32 / 2.0;
-    let half_height = height as f32 / 2.0;
+    let width = args.width;
+    let height = args.height;
 
     let event_loop = EventLoop::new();
     let window = WindowBuilder::new()
@@ -132,35 +1484,469 @@ fn main() {
     let mut pixels = Pixels::new(width as u32, height as u32, surface_texture).unwrap();
 
     let mut framebuffer = Framebuffer::new(width, height);
-    framebuffer.clear(Color { r: 0.0, g: 0.2, b: 0.0 }.to_hex());
+    framebuffer.clear(args.clear_color.to_hex());
 
     //framebuffer.clear(Color::black().to_hex());
-    let mut z_buffer = vec![f32::INFINITY; width * height];
+    let mut reversed_z = args.reversed_z;
+    // Con supermuestreo se dibuja en `samples`, `ssaa` veces más grande por lado, y se
+    // reduce a `framebuffer`, que siempre mide lo mismo que la ventana
+    let mut ssaa = args.ssaa;
+    let mut samples = Framebuffer::new(width * ssaa, height * ssaa);
+    let mut z_buffer = vec![depth_clear_value(reversed_z); samples.width * samples.height];
+
+    // La relación de aspecto se toma del framebuffer en cada cuadro
+    let mut projection = Projection::new(45.0_f32.to_radians(), 1.0, args.near, args.far);
+    // Distancia del ojo con la que el modelo de tamaño 1 ocupa el 80% de la ventana;
+    // `scale` acerca el ojo en esa proporción
+    let fit_distance = projection.fit_distance(1.0, 0.8);
+
+    // Los shaders que se eligen con 1 a 9 y H. Aquí se pueden registrar otros:
+    // `shaders.register_shader("mío", Box::new(MiShader))`
+    let mut shaders = ShaderRegistry::default();
+    let mut texture = Texture::load_or_fallback(args.texture.as_deref().unwrap_or(TEST_TEXTURE_PATH));
+    if let Some(filter) = args.texture_filter {
+        texture.set_filter(filter);
+    }
+    texture.wrap = args.texture_wrap;
+    let mut texture = Arc::new(texture);
+    let textured = shaders.register_shader("Textura", Box::new(TextureShader { texture: texture.clone() }));
+    let mut shader = shaders.position(args.shading.name()).unwrap_or(0);
+    let toon = shaders.position("Toon");
+    let mut toon_bands = ToonShader::default().bands.len();
 
-    let obj = Obj::load("assets/naveT.obj").expect("Failed to load OBJ file");
-    let mut model = Model3D::new();
-    model.add_vertices_from_obj(&obj);
+    // Si hay una vista guardada se arranca con ella
+    if let Some(state) = load_view_state(false) {
+        camera = state.camera;
+        camera_mode = state.camera_mode;
+        projection.mode = state.projection_mode;
+        scale = state.scale.clamp(0.1, 100.0);
+        if state.shader < shaders.len() {
+            shader = state.shader;
+        }
+    }
+    let mut view_camera = camera;
+    let mut quad_view = false;
+    // Contadores del último cuadro dibujado, que se imprimen con T
+    let mut last_stats = RenderStats::default();
+    let mut rasterizer = Rasterizer::default();
+    let mut cull_mode = args.cull_mode;
+    let mut render_mode = RenderMode::default();
+    let mut triangle_colors = false;
+    let mut point_size = args.point_size;
+    let mut show_bounds = false;
+    let mut flat_normals = false; // Lo que calculó la última Shift+N; al cargar quedan las del archivo
+    let mut pipeline = args.pipeline;
+    let mut lights = Lights::default();
+    let mut flashlight = false;
+    let mut shadows = args.shadows;
+    let mut two_sided = args.two_sided;
+    let rim = Rim { color: args.rim_color.unwrap_or(Color::from_hex(RIM_COLOR)), power: args.rim_power };
+    let mut rim_on = args.rim_color.is_some();
+    let mut outline = args.outline;
+    let mut depth_view = DepthView::default();
+    // Del color del fondo, para que lo lejano se funda con él
+    let mut fog = Fog { color: args.fog_color.unwrap_or(args.clear_color), ..Fog::default() };
+    let mut display_scale = scale;
+
+    // Piso bajo la nave, acostado en XZ mirando hacia +Y
+    let mut ground = Model3D::plane(2.0, 2.0, 8);
+    ground.set_rotation(Vec3::new(-std::f32::consts::FRAC_PI_2, 0.0, 0.0));
+    for vertex in &mut ground.vertices {
+        vertex.color = Color { r: 0.3, g: 0.3, b: 0.35 };
+    }
+
+    let mut scene = Scene::new();
+    let model_id = scene.add_model(model);
+    let ground_id = scene.add_model(ground);
+    place_ground(&mut scene, model_id, ground_id);
+
+    // Demo de las ondas (Shift+G): un plano bien subdividido, acostado como el piso,
+    // que ondula con `WaveVertexShader` bajo el agua que se corre. Al salir se vuelve a
+    // lo que se veía y al shader de antes
+    let mut waves = Model3D::plane(2.0, 2.0, 64);
+    waves.set_rotation(Vec3::new(-std::f32::consts::FRAC_PI_2, 0.0, 0.0));
+    let waves_id = scene.add_model(waves);
+    scene.set_visible(waves_id, false);
+    let wave_shader: Arc<dyn VertexShader> = Arc::new(WaveVertexShader::default());
+    // Demo de la textura (Shift+U): una esfera con UV, que las da vuelta enteras en U
+    let sphere_id = scene.add_model(Model3D::uv_sphere(0.5, 24, 48));
+    scene.set_visible(sphere_id, false);
+    // Demo de las figuras (Shift+P): las de `primitives`, que giran solas sobre Y
+    let primitives_id = scene.add_model(primitives());
+    scene.set_visible(primitives_id, false);
+    let mut demo: Option<Demo> = None;
+
+    // Al exportar de nuevo desde Blender el modelo se recarga solo
+    let mut watcher = FileWatcher::new(&model_path, Duration::from_secs(1));
+    set_title(&window, &model_path, shaders.name(shader));
+
+    // Modelos extra de un arrastre con varios archivos, que se quitan en el siguiente
+    let mut extra_ids: Vec<ModelId> = Vec::new();
+    // winit entrega un `DroppedFile` por archivo antes de `MainEventsCleared`
+    let mut dropped_in_batch = false;
 
     event_loop.run(move |event, _, control_flow| {
-        *control_flow = ControlFlow::Wait;
+        // Se dibuja sin parar: el giro automático anima aunque no lleguen eventos
+        *control_flow = ControlFlow::Poll;
         match event {
             Event::WindowEvent { event, .. } => match event {
                 WindowEvent::CloseRequested => *control_flow = ControlFlow::Exit,
-                WindowEvent::MouseWheel { delta, .. } => {
-                    if let MouseScrollDelta::LineDelta(_, y) = delta {
-                        scale = (scale + y * 0.1).clamp(0.1, 100.0);
+                // Al redimensionar, el framebuffer toma el tamaño nuevo y el resto se deriva de él
+                WindowEvent::Resized(size) if size.width > 0 && size.height > 0 => {
+                    pixels.resize_surface(size.width, size.height);
+                    pixels.resize_buffer(size.width, size.height);
+                    framebuffer = Framebuffer::new(size.width as usize, size.height as usize);
+                    samples = Framebuffer::new(framebuffer.width * ssaa, framebuffer.height * ssaa);
+                    z_buffer = vec![depth_clear_value(reversed_z); samples.width * samples.height];
+                }
+                WindowEvent::MouseWheel { delta: MouseScrollDelta::LineDelta(_, y), .. } => {
+                    last_interaction = Instant::now();
+                    let previous_scale = scale;
+                    scale = (scale + y * 0.1).clamp(0.1, 100.0);
+
+                    // El punto bajo el cursor (en el plano del punto de mira) se queda quieto:
+                    // el alto visible cambia en `previous_scale / scale`, así que el punto de
+                    // mira se corre hacia el cursor esa misma fracción
+                    if camera_mode == CameraMode::Orbit {
+                        let world_per_pixel = projection.visible_height(camera.distance()) / framebuffer.height as f32;
+                        let cursor_x = (last_mouse_position.0 - framebuffer.width as f32 / 2.0) * world_per_pixel;
+                        let cursor_y = (framebuffer.height as f32 / 2.0 - last_mouse_position.1) * world_per_pixel;
+                        let shift = 1.0 - previous_scale / scale;
+                        camera.pan(cursor_x * shift, cursor_y * shift);
+                    }
+                }
+                WindowEvent::KeyboardInput {
+                    input: KeyboardInput { virtual_keycode: Some(key), state, .. },
+                    ..
+                } => {
+                    // `insert` devuelve false con la repetición automática de una tecla mantenida
+                    let just_pressed = match state {
+                        ElementState::Pressed => {
+                            last_interaction = Instant::now();
+                            pressed_keys.insert(key)
+                        }
+                        ElementState::Released => {
+                            pressed_keys.remove(&key);
+                            false
+                        }
+                    };
+                    let ctrl = pressed_keys.contains(&VirtualKeyCode::LControl) || pressed_keys.contains(&VirtualKeyCode::RControl);
+                    let shift = pressed_keys.contains(&VirtualKeyCode::LShift) || pressed_keys.contains(&VirtualKeyCode::RShift);
+                    if just_pressed {
+                        match key {
+                            // Ctrl+S guarda la vista actual y Ctrl+L la vuelve a cargar
+                            VirtualKeyCode::S if ctrl => {
+                                let state = ViewState { camera, camera_mode, projection_mode: projection.mode, scale, shader };
+                                match state.save(VIEW_STATE_PATH) {
+                                    Ok(()) => println!("Vista guardada en {}", VIEW_STATE_PATH),
+                                    Err(err) => eprintln!("Error al guardar {}: {}", VIEW_STATE_PATH, err),
+                                }
+                            }
+                            VirtualKeyCode::L if ctrl => {
+                                if let Some(state) = load_view_state(true) {
+                                    camera = state.camera;
+                                    camera_mode = state.camera_mode;
+                                    projection.mode = state.projection_mode;
+                                    scale = state.scale.clamp(0.1, 100.0);
+                                    if state.shader < shaders.len() {
+                                        shader = state.shader;
+                                        set_title(&window, &model_path, shaders.name(shader));
+                                    }
+                                    is_looking = false;
+                                }
+                            }
+                            VirtualKeyCode::G if shift => {
+                                let water = shaders.position("Agua");
+                                toggle_demo(&mut scene, &mut demo, (waves_id, water), (model_id, ground_id), &mut shader);
+                                set_title(&window, &model_path, shaders.name(shader));
+                            }
+                            VirtualKeyCode::U if shift => {
+                                toggle_demo(&mut scene, &mut demo, (sphere_id, Some(textured)), (model_id, ground_id), &mut shader);
+                                set_title(&window, &model_path, shaders.name(shader));
+                            }
+                            VirtualKeyCode::P if shift => {
+                                toggle_demo(&mut scene, &mut demo, (primitives_id, None), (model_id, ground_id), &mut shader);
+                                set_title(&window, &model_path, shaders.name(shader));
+                            }
+                            // G muestra u oculta el piso
+                            VirtualKeyCode::G => {
+                                let visible = scene.is_visible(ground_id);
+                                scene.set_visible(ground_id, !visible);
+                            }
+                            // P alterna entre perspectiva y ortográfica sin mover la cámara
+                            VirtualKeyCode::P => projection.toggle_mode(),
+                            // Shift+R prende o apaga la luz de contorno
+                            VirtualKeyCode::R if shift => rim_on = !rim_on,
+                            // R recarga el modelo a mano
+                            VirtualKeyCode::R => {
+                                reload_model(&mut scene, model_id, ground_id, &model_path, &import);
+                                watcher.mark_seen();
+                            }
+                            // Tab alterna entre orbitar y volar
+                            VirtualKeyCode::Tab => {
+                                camera_mode = match camera_mode {
+                                    CameraMode::Orbit => CameraMode::Fly,
+                                    CameraMode::Fly => {
+                                        // El nuevo punto de mira queda delante, a la distancia del zoom actual
+                                        camera.refocus(fit_distance / scale);
+                                        CameraMode::Orbit
+                                    }
+                                };
+                                is_looking = false;
+                            }
+                            // V (o 5 del teclado numérico) alterna entre una vista y cuatro
+                            VirtualKeyCode::V | VirtualKeyCode::Numpad5 => quad_view = !quad_view,
+                            // Shift+T pinta cada triángulo de su propio color, para ver cómo
+                            // quedó dividida la malla y si el relleno deja huecos
+                            VirtualKeyCode::T if shift => {
+                                triangle_colors = !triangle_colors;
+                                println!("Colores por triángulo: {}", if triangle_colors { "sí" } else { "no" });
+                            }
+                            VirtualKeyCode::T => println!("{}", last_stats),
+                            // Shift+B cambia el filtro de la textura. El shader registrado la
+                            // comparte: se cambia una copia y se reemplaza el shader
+                            VirtualKeyCode::B if shift => {
+                                let mut changed = Texture::clone(&texture);
+                                changed.set_filter(texture.filter().toggled());
+                                texture = Arc::new(changed);
+                                shaders.replace(textured, Box::new(TextureShader { texture: texture.clone() }));
+                                println!("Filtro de la textura: {:?}", texture.filter());
+                            }
+                            // B cambia el recorrido de píxeles, para comparar tiempos
+                            VirtualKeyCode::B => {
+                                rasterizer = match rasterizer {
+                                    Rasterizer::BoundingBox => Rasterizer::Incremental,
+                                    Rasterizer::Incremental => Rasterizer::BoundingBox,
+                                };
+                                println!("Rasterizador: {:?}", rasterizer);
+                            }
+                            // H pasa al shader siguiente: luz por cara, por vértice, por píxel
+                            // (Blinn-Phong y Phong) y los que se hayan registrado después
+                            VirtualKeyCode::H if !shaders.is_empty() => {
+                                shader = (shader + 1) % shaders.len();
+                                println!("Shader: {}", shaders.name(shader));
+                                set_title(&window, &model_path, shaders.name(shader));
+                            }
+                            // [ y ] quitan o agregan un nivel de luz al toon (niveles parejos)
+                            VirtualKeyCode::LBracket | VirtualKeyCode::RBracket => {
+                                if let Some(toon) = toon {
+                                    toon_bands = if key == VirtualKeyCode::LBracket { toon_bands - 1 } else { toon_bands + 1 }.clamp(2, 8);
+                                    shaders.replace(toon, Box::new(ToonShader::with_bands(toon_bands)));
+                                    println!("Niveles del toon: {}", toon_bands);
+                                }
+                            }
+                            // 0 muestra u oculta el contorno
+                            VirtualKeyCode::Key0 => outline = !outline,
+                            // Shift+Y prende o apaga la sombra del sol
+                            VirtualKeyCode::Y if shift => {
+                                shadows = !shadows;
+                                println!("Sombras: {}", if shadows { "sí" } else { "no" });
+                            }
+                            // Y prende o apaga la linterna
+                            VirtualKeyCode::Y => flashlight = !flashlight,
+                            // U mueve las luces puntuales un octavo de vuelta alrededor del modelo
+                            VirtualKeyCode::U => lights.orbit_points(std::f32::consts::FRAC_PI_4),
+                            // W pasa por relleno, aristas y los dos (al volar, W avanza)
+                            VirtualKeyCode::W if camera_mode == CameraMode::Orbit => {
+                                render_mode = match render_mode {
+                                    RenderMode::Fill => RenderMode::Wireframe,
+                                    RenderMode::Wireframe => RenderMode::FillAndWireframe,
+                                    RenderMode::FillAndWireframe => RenderMode::Points,
+                                    RenderMode::Points => RenderMode::Fill,
+                                };
+                                println!("Modo de dibujo: {:?}", render_mode);
+                            }
+                            // 1 a 9 eligen el shader; con Shift cambian el tamaño de los puntos
+                            VirtualKeyCode::Key1
+                            | VirtualKeyCode::Key2
+                            | VirtualKeyCode::Key3
+                            | VirtualKeyCode::Key4
+                            | VirtualKeyCode::Key5
+                            | VirtualKeyCode::Key6
+                            | VirtualKeyCode::Key7
+                            | VirtualKeyCode::Key8
+                            | VirtualKeyCode::Key9 => {
+                                let number = key as usize - VirtualKeyCode::Key1 as usize + 1;
+                                if shift {
+                                    point_size = number;
+                                } else if number <= shaders.len() {
+                                    shader = number - 1;
+                                    println!("Shader: {}", shaders.name(shader));
+                                    set_title(&window, &model_path, shaders.name(shader));
+                                }
+                            }
+                            // Shift+N cambia las normales del modelo: planas (una por cara, aristas
+                            // marcadas) o suaves (promediadas entre las caras que comparten vértice)
+                            VirtualKeyCode::N if shift => {
+                                flat_normals = !flat_normals;
+                                if let Some(model) = scene.get_mut(model_id) {
+                                    if flat_normals {
+                                        model.compute_flat_normals();
+                                    } else {
+                                        model.compute_smooth_normals();
+                                    }
+                                }
+                                println!("Normales: {}", if flat_normals { "planas" } else { "suaves" });
+                            }
+                            // N alterna el reparto entre hilos: franjas o teselas
+                            VirtualKeyCode::N => {
+                                pipeline = match pipeline {
+                                    Pipeline::Bands => Pipeline::Tiles,
+                                    Pipeline::Tiles => Pipeline::Bands,
+                                };
+                                println!("Reparto entre hilos: {:?}", pipeline);
+                            }
+                            // X cambia el supermuestreo: 1, 2 o 4 muestras por lado de cada píxel
+                            VirtualKeyCode::X => {
+                                ssaa = match ssaa {
+                                    1 => 2,
+                                    2 => 4,
+                                    _ => 1,
+                                };
+                                samples = Framebuffer::new(framebuffer.width * ssaa, framebuffer.height * ssaa);
+                                z_buffer = vec![depth_clear_value(reversed_z); samples.width * samples.height];
+                                println!("Supermuestreo: {}x{}", ssaa, ssaa);
+                            }
+                            // O muestra u oculta las cajas envolventes
+                            VirtualKeyCode::O => show_bounds = !show_bounds,
+                            // Shift+C ilumina las dos caras; mientras está prendido no se
+                            // descarta ninguna, sea cual sea el modo de C
+                            VirtualKeyCode::C if shift => {
+                                two_sided = !two_sided;
+                                println!("Dos caras: {}", if two_sided { "sí" } else { "no" });
+                            }
+                            // C pasa por los modos de descarte de caras
+                            VirtualKeyCode::C => {
+                                cull_mode = match cull_mode {
+                                    CullMode::Back => CullMode::Front,
+                                    CullMode::Front => CullMode::None,
+                                    CullMode::None => CullMode::Back,
+                                };
+                                println!("Caras descartadas: {:?}", cull_mode);
+                            }
+                            // Shift+Z pasa por la escena, la profundidad entre los planos y la
+                            // profundidad ajustada a lo que hay en el cuadro
+                            VirtualKeyCode::Z if shift => {
+                                depth_view = match depth_view {
+                                    DepthView::Off => DepthView::Planes,
+                                    DepthView::Planes => DepthView::AutoRange,
+                                    DepthView::AutoRange => DepthView::ShadowMap,
+                                    DepthView::ShadowMap => DepthView::Off,
+                                };
+                                println!("Profundidad: {:?}", depth_view);
+                            }
+                            // Z alterna la profundidad invertida
+                            VirtualKeyCode::Z => reversed_z = !reversed_z,
+                            // M activa o desactiva el movimiento suave
+                            VirtualKeyCode::M => {
+                                smoothing = !smoothing;
+                                throw_velocity = Vec2::zeros();
+                            }
+                            // Inicio vuelve a la vista con la que arrancó el programa
+                            VirtualKeyCode::Home => {
+                                camera = initial_camera;
+                                camera_mode = CameraMode::Orbit;
+                                is_looking = false;
+                                scale = args.scale;
+                            }
+                            // Shift+F pasa por sin niebla, lineal, exponencial y exponencial al cuadrado
+                            VirtualKeyCode::F if shift => {
+                                fog.next_mode();
+                                println!("Niebla: {:?}", fog.mode);
+                            }
+                            // F encuadra los modelos cargados sin cambiar la orientación
+                            VirtualKeyCode::F => {
+                                let mut shown = vec![model_id];
+                                shown.extend(&extra_ids);
+                                if let Some(framed) = frame_models(&mut camera, &projection, &scene, &shown, fit_distance) {
+                                    camera_mode = CameraMode::Orbit;
+                                    is_looking = false;
+                                    scale = framed.clamp(0.1, 100.0);
+                                }
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                WindowEvent::DroppedFile(path) => {
+                    let path = path.to_string_lossy().into_owned();
+                    // Un archivo roto o que no es un modelo deja la escena como estaba
+                    match load_model(&path, &import) {
+                        Ok(model) => {
+                            if dropped_in_batch {
+                                // Los demás archivos del mismo arrastre se ponen al lado del primero
+                                extra_ids.push(scene.add_model(model));
+                            } else {
+                                for id in extra_ids.drain(..) {
+                                    scene.remove(id);
+                                }
+                                scene.replace(model_id, model);
+                                place_ground(&mut scene, model_id, ground_id);
+                                watcher.set_path(&path);
+                                set_title(&window, &path, shaders.name(shader));
+                                model_path = path;
+                                dropped_in_batch = true;
+                            }
+                            let mut shown = vec![model_id];
+                            shown.extend(&extra_ids);
+                            arrange_in_row(&mut scene, &shown);
+                            // Como con F, la cámara encuadra lo que se soltó sin cambiar la orientación
+                            match frame_models(&mut camera, &projection, &scene, &shown, fit_distance) {
+                                Some(framed) => {
+                                    camera_mode = CameraMode::Orbit;
+                                    is_looking = false;
+                                    scale = framed.clamp(0.1, 100.0);
+                                }
+                                None => scale = fit_scale(&scene, &shown),
+                            }
+                        }
+                        Err(err) => eprintln!("Error al cargar {}: {}", path, err),
                     }
                 }
                 WindowEvent::MouseInput { button: MouseButton::Middle, state, .. } => {
+                    last_interaction = Instant::now();
                     is_rotating = state == ElementState::Pressed;
+                    // Solo hay impulso si el cursor seguía moviéndose al soltar
+                    if is_rotating || !smoothing || last_arcball.elapsed() > Duration::from_millis(50) {
+                        throw_velocity = Vec2::zeros();
+                    }
+                }
+                WindowEvent::MouseInput { button: MouseButton::Left, state, .. } => {
+                    last_interaction = Instant::now();
+                    is_looking = camera_mode == CameraMode::Fly && state == ElementState::Pressed;
+                }
+                WindowEvent::MouseInput { button: MouseButton::Right, state, .. } => {
+                    last_interaction = Instant::now();
+                    is_panning = state == ElementState::Pressed;
                 }
                 WindowEvent::CursorMoved { position, .. } => {
                     let (x, y) = (position.x as f32, position.y as f32);
+                    if is_rotating || is_looking || is_panning {
+                        last_interaction = Instant::now();
+                    }
                     if is_rotating {
-                        let dx = (x - last_mouse_position.0) * 0.01;
-                        let dy = (y - last_mouse_position.1) * 0.01;
-                        camera_angle_x += dy;
-                        camera_angle_y += dx;
+                        // Esfera virtual centrada en el viewport que toca su lado más corto
+                        let (half_width, half_height) = (framebuffer.width as f32 / 2.0, framebuffer.height as f32 / 2.0);
+                        let radius = half_width.min(half_height);
+                        let to_sphere = |(x, y): (f32, f32)| Vec2::new((x - half_width) / radius, (half_height - y) / radius);
+                        let (from, to) = (to_sphere(last_mouse_position), to_sphere((x, y)));
+                        camera.arcball(from, to);
+                        let elapsed = last_arcball.elapsed().as_secs_f32().max(1e-3);
+                        throw_velocity = (to - from) / elapsed;
+                        last_arcball = Instant::now();
+                    }
+                    if is_looking {
+                        let dx = (x - last_mouse_position.0) * 0.005;
+                        let dy = (y - last_mouse_position.1) * 0.005;
+                        camera.look(-dx, -dy);
+                    }
+                    if is_panning {
+                        // Escalado por el alto visible para que, a cualquier zoom, lo que
+                        // está en el punto de mira siga al cursor
+                        let world_per_pixel = projection.visible_height(camera.distance()) / framebuffer.height as f32;
+                        let dx = (x - last_mouse_position.0) * world_per_pixel;
+                        let dy = (y - last_mouse_position.1) * world_per_pixel;
+                        camera.pan(-dx, dy);
                     }
                     last_mouse_position = (x, y);
                 }
@@ -168,17 +1954,186 @@ fn main() {
             },
             Event::RedrawRequested(_) => {
                 //framebuffer.clear(Color::black().to_hex());
-                framebuffer.clear(Color { r: 0.0, g: 0.2, b: 0.0 }.to_hex());
+                samples.clear(args.clear_color.to_hex());
+
+                z_buffer.iter_mut().for_each(|z| *z = depth_clear_value(reversed_z));
+
+                // El movimiento se integra con el tiempo real del cuadro para que la
+                // velocidad no dependa de los FPS (con tope por si la ventana se congela)
+                let now = Instant::now();
+                let delta_time = now.duration_since(last_frame).as_secs_f32().min(0.1);
+                last_frame = now;
 
-                z_buffer.iter_mut().for_each(|z| *z = f32::INFINITY);
+                // Teclas mantenidas: se leen en cada cuadro para que el movimiento sea continuo
+                let held = |key| if pressed_keys.contains(&key) { 1.0_f32 } else { 0.0 };
+                let shift = held(VirtualKeyCode::LShift).max(held(VirtualKeyCode::RShift));
+                // Con Ctrl las letras son atajos (Ctrl+S) y no mueven la cámara
+                let ctrl = held(VirtualKeyCode::LControl).max(held(VirtualKeyCode::RControl));
+                let arrows_x = held(VirtualKeyCode::Left) - held(VirtualKeyCode::Right);
+                let arrows_y = held(VirtualKeyCode::Up) - held(VirtualKeyCode::Down);
+                if !pressed_keys.is_empty() {
+                    last_interaction = now;
+                }
+
+                match camera_mode {
+                    // En perspectiva el zoom acerca el ojo; en ortográfica el ojo se queda
+                    // y se achica el alto visible, con la misma escala en ambos modos
+                    CameraMode::Orbit => {
+                        // Flechas: giran alrededor del modelo. Shift+flechas o IJKL: mueven el
+                        // modelo en la pantalla (la cámara se desplaza al revés). +/-: zoom
+                        let (yaw, pitch) = if shift > 0.0 { (0.0, 0.0) } else { (arrows_x, arrows_y) };
+                        camera.orbit(yaw * KEY_ROTATE_SPEED * delta_time, -pitch * KEY_ROTATE_SPEED * delta_time);
+
+                        let move_x = held(VirtualKeyCode::L) - held(VirtualKeyCode::J) - arrows_x * shift;
+                        let move_y = held(VirtualKeyCode::I) - held(VirtualKeyCode::K) + arrows_y * shift;
+                        let step = KEY_PAN_SPEED * projection.visible_height(camera.distance()) * (1.0 - ctrl) * delta_time;
+                        camera.pan(-move_x * step, -move_y * step);
+
+                        let zoom_in = held(VirtualKeyCode::Plus).max(held(VirtualKeyCode::Equals)).max(held(VirtualKeyCode::NumpadAdd));
+                        let zoom_out = held(VirtualKeyCode::Minus).max(held(VirtualKeyCode::NumpadSubtract));
+                        scale = (scale * ((zoom_in - zoom_out) * KEY_ZOOM_SPEED * delta_time).exp()).clamp(0.1, 100.0);
+
+                        match projection.mode {
+                            ProjectionMode::Perspective => camera.set_distance(fit_distance / scale),
+                            // El alto visible se ajusta más abajo, con la escala suavizada
+                            ProjectionMode::Orthographic => camera.set_distance(fit_distance),
+                        }
+                        // Impulso del arcball: sigue girando hacia donde iba y se frena
+                        if !is_rotating && throw_velocity.norm() > 0.01 {
+                            camera.arcball(Vec2::zeros(), throw_velocity * delta_time);
+                            throw_velocity *= (-SPIN_FRICTION * delta_time).exp();
+                            last_interaction = now;
+                        }
+                        // Sin interacción reciente la vista gira sola alrededor del eje Y;
+                        // el giro parte de la orientación actual y ahí se queda al tocar algo
+                        if now.duration_since(last_interaction) >= IDLE_DELAY {
+                            camera.orbit(args.spin_speed.to_radians() * delta_time, 0.0);
+                        }
+                    }
+                    // WASD avanza y se desliza, Q/E bajan y suben, Shift acelera
+                    CameraMode::Fly => {
+                        // Las flechas giran la vista como el arrastre con el botón izquierdo
+                        camera.look(arrows_x * KEY_ROTATE_SPEED * delta_time, arrows_y * KEY_ROTATE_SPEED * delta_time);
+                        let step = FLY_SPEED * (1.0 + 2.0 * shift) * (1.0 - ctrl) * delta_time;
+                        camera.fly(
+                            (held(VirtualKeyCode::W) - held(VirtualKeyCode::S)) * step,
+                            (held(VirtualKeyCode::D) - held(VirtualKeyCode::A)) * step,
+                            (held(VirtualKeyCode::E) - held(VirtualKeyCode::Q)) * step,
+                        );
+                    }
+                }
+                // La cámara dibujada se acerca exponencialmente a la de la entrada;
+                // sin suavizado la alcanza en el mismo cuadro
+                let follow = if smoothing { 1.0 - (-SMOOTHING_RATE * delta_time).exp() } else { 1.0 };
+                view_camera.approach(&camera, follow);
+                display_scale += (scale - display_scale) * follow;
+                if camera_mode == CameraMode::Orbit && projection.mode == ProjectionMode::Orthographic {
+                    projection.ortho_height = projection.view_height_at(fit_distance) / display_scale;
+                }
 
-                let rotation_x = rotate_x(&Mat4::identity(), camera_angle_x);
-                let rotation_y = rotate_y(&Mat4::identity(), camera_angle_y);
-                let camera_transform = rotation_y * rotation_x;
+                projection.aspect = framebuffer.width as f32 / framebuffer.height as f32;
+                let time = now.duration_since(start_time).as_secs_f32();
+                if scene.is_visible(primitives_id) {
+                    if let Some(primitives) = scene.get_mut(primitives_id) {
+                        primitives.rotate_y(PRIMITIVES_SPIN * delta_time);
+                    }
+                }
+                let views = if quad_view {
+                    quad_views(&samples, &view_camera, &projection, fit_distance, display_scale).to_vec()
+                } else {
+                    vec![(Viewport::full(&samples), view_camera, projection)]
+                };
 
-                let uniforms = Uniforms::new(Vec3::new(half_width, half_height, 0.0), scale, camera_transform);
+                // Los viewports no se solapan, así que comparten el z-buffer sin pisarse
+                // La linterna sale del ojo de la cámara libre hacia donde mira; en las
+                // cuatro vistas alumbra lo mismo
+                let mut frame_lights = lights.clone();
+                if flashlight {
+                    let (inner, outer) = (FLASHLIGHT_CONE.0.to_radians(), FLASHLIGHT_CONE.1.to_radians());
+                    let white = Color::from_hex(0xFFFFFF);
+                    frame_lights.lights.push(Light::spot(view_camera.eye(), view_camera.forward(), inner, outer, 0.05, white, 1.0));
+                }
+                let vertex_shader: Arc<dyn VertexShader> = if demo.as_ref().is_some_and(|demo| demo.model == waves_id) { wave_shader.clone() } else { Arc::new(DefaultVertexShader) };
+                let params = ShadingParams {
+                    lights: frame_lights,
+                    fog,
+                    rim: rim_on.then_some(rim),
+                    two_sided,
+                    ..ShadingParams::new(view_camera.eye(), time, delta_time)
+                };
 
-                render(&mut framebuffer, &mut z_buffer, &uniforms, &model.vertices);
+                let mut stats = RenderStats::default();
+                let render_start = Instant::now();
+                // La sombra es del sol, la primera luz direccional. El mapa no depende de
+                // la cámara: uno sirve para las cuatro vistas
+                let sun = params.lights.lights.iter().enumerate().find_map(|(index, light)| match light.kind {
+                    LightKind::Directional { direction } => Some((index, direction)),
+                    _ => None,
+                });
+                let shadow_map = match sun {
+                    Some((index, direction)) if shadows || depth_view == DepthView::ShadowMap => {
+                        let uniforms = Uniforms {
+                            threads: args.threads,
+                            pipeline,
+                            vertex_shader: vertex_shader.clone(),
+                            ..Uniforms::new(Mat4::identity(), Mat4::identity(), Mat4::identity())
+                        };
+                        render_shadow_map(&scene, &uniforms, &params, direction, args.shadow_size, args.shadow_bias).map(|map| (index, Arc::new(map)))
+                    }
+                    _ => None,
+                };
+                let mut params = params;
+                if shadows {
+                    params.lights.shadow = shadow_map.as_ref().map(|(index, map)| Shadow::new(map.clone(), *index));
+                }
+                for (viewport, camera, projection) in &views {
+                    let viewport = &Viewport { reversed_z, ..*viewport };
+                    let uniforms = Uniforms {
+                        rasterizer,
+                        cull_mode,
+                        winding: args.winding,
+                        render_mode,
+                        triangle_colors,
+                        wire_color: args.wire_color,
+                        // Las líneas y los puntos se agrandan con el supermuestreo para verse igual
+                        wire_width: args.wire_width * ssaa,
+                        wire_antialias: args.wire_antialias,
+                        point_size: point_size * ssaa,
+                        threads: args.threads,
+                        pipeline,
+                        tile_size: args.tile_size,
+                        vertex_shader: vertex_shader.clone(),
+                        fragment_shader: shaders.get(shader).map(|(_, shader)| shader.clone()),
+                        ..Uniforms::new(Mat4::identity(), camera.view_matrix(), projection.matrix())
+                    };
+                    let params = ShadingParams { eye: camera.eye(), ..params.clone() };
+                    stats += render_scene(&mut samples, &mut z_buffer, &uniforms, &params, viewport, &scene);
+                    if depth_view == DepthView::ShadowMap {
+                        if let Some((_, map)) = &shadow_map {
+                            render_shadow_view(&mut samples, viewport, map);
+                        }
+                    } else if depth_view != DepthView::Off {
+                        render_depth(&mut samples, &z_buffer, &uniforms.projection_matrix, viewport, depth_view);
+                    } else if outline {
+                        render_outline(&mut samples, &z_buffer, &uniforms.projection_matrix, viewport, args.outline_color, args.outline_width * ssaa);
+                    }
+                    if show_bounds {
+                        render_bounds(&mut samples, &uniforms, viewport, &scene);
+                    }
+                }
+                let downsample_start = Instant::now();
+                framebuffer.downsample(&samples, ssaa);
+                // Al girar con el botón del medio, el círculo del arcball (arrastrar
+                // fuera de él rueda la vista) y el punto alrededor del que se gira
+                if is_rotating && camera_mode == CameraMode::Orbit && !quad_view {
+                    let (cx, cy) = (framebuffer.width as i32 / 2, framebuffer.height as i32 / 2);
+                    framebuffer.draw_circle(cx, cy, cx.min(cy), 1, GIZMO_COLOR);
+                    framebuffer.fill_circle(cx, cy, 3, Color::from_hex(GIZMO_COLOR));
+                }
+                stats.ssaa = ssaa;
+                stats.downsample_time = downsample_start.elapsed();
+                stats.render_time = render_start.elapsed();
+                last_stats = stats;
 
                 let frame = pixels.get_frame();
                 for (i, pixel) in framebuffer.buffer.iter().enumerate() {
@@ -188,9 +2143,664 @@ fn main() {
                 pixels.render().unwrap();
             }
             Event::MainEventsCleared => {
+                dropped_in_batch = false;
+                if watcher.poll() {
+                    reload_model(&mut scene, model_id, ground_id, &model_path, &import);
+                }
                 window.request_redraw();
             }
             _ => {}
         }
     });
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::shader::DepthShader;
+
+    const WIDTH: usize = 64;
+    const HEIGHT: usize = 48;
+    const BACKGROUND: u32 = 0x102030;
+
+    // Canales en 0 o 1: pasan por `to_hex` sin redondeo
+    const FILL: u32 = 0xFF00FF;
+
+    // Todo de un color, sin luz ni niebla: las pruebas miran qué píxeles se pintan
+    // y con qué profundidad
+    struct Solid(u32);
+
+    impl FragmentShader for Solid {
+        fn shade(&self, _fragment: &Fragment, _uniforms: &Uniforms, _params: &ShadingParams) -> Option<Color> {
+            Some(Color::from_hex(self.0))
+        }
+    }
+
+    fn solid(view: Mat4, projection: Mat4) -> Uniforms {
+        Uniforms {
+            fragment_shader: Some(Arc::new(Solid(FILL))),
+            cull_mode: CullMode::None,
+            ..Uniforms::new(Mat4::identity(), view, projection)
+        }
+    }
+
+    // Triángulos sueltos, de 3 en 3 posiciones
+    fn triangles(positions: &[Vec3]) -> Model3D {
+        let mut model = Model3D::new();
+        model.vertices = positions.iter().map(|&position| Vertex::new_with_color(position, Color::black())).collect();
+        model
+    }
+
+    fn draw(uniforms: &Uniforms, models: &[&Model3D]) -> (Framebuffer, Vec<f32>, RenderStats) {
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(BACKGROUND);
+        let mut z_buffer = vec![f32::INFINITY; WIDTH * HEIGHT];
+        let viewport = Viewport::full(&framebuffer);
+        let params = ShadingParams::new(Vec3::zeros(), 0.0, 0.0);
+        let mut stats = RenderStats::default();
+        for model in models {
+            stats += render(&mut framebuffer, &mut z_buffer, uniforms, &params, &viewport, model);
+        }
+        (framebuffer, z_buffer, stats)
+    }
+
+    // Sin proyección las posiciones ya son coordenadas normalizadas (w = 1)
+    #[test]
+    fn huge_triangle_is_clipped_to_the_viewport() {
+        let huge = triangles(&[Vec3::new(-10.0, -10.0, 0.5), Vec3::new(30.0, -10.0, 0.5), Vec3::new(-10.0, 30.0, 0.5)]);
+        let uniforms = solid(Mat4::identity(), Mat4::identity());
+
+        // Lo que llega al rasterizador no se sale de la pantalla: recorre solo los
+        // píxeles visibles, no los del rectángulo de un triángulo diez veces más grande
+        let viewport = Viewport::full(&Framebuffer::new(WIDTH, HEIGHT));
+        let params = ShadingParams::new(Vec3::zeros(), 0.0, 0.0);
+        let geometry = screen_geometry(&uniforms.for_model(&huge), &params, &viewport, &huge, &mut RenderStats::default());
+        assert!(!geometry.triangles.is_empty() && geometry.triangles.len() <= 5);
+        for &(corners, _) in &geometry.triangles {
+            for corner in corners {
+                let position = geometry.vertices[corner].transformed_position;
+                assert!((-1e-3..=WIDTH as f32 + 1e-3).contains(&position.x), "{:?}", position);
+                assert!((-1e-3..=HEIGHT as f32 + 1e-3).contains(&position.y), "{:?}", position);
+            }
+        }
+
+        // Y se ve igual que dos triángulos que cubren justo la pantalla
+        let quad = triangles(
+            &[
+                Vec3::new(-1.0, -1.0, 0.5),
+                Vec3::new(1.0, -1.0, 0.5),
+                Vec3::new(1.0, 1.0, 0.5),
+                Vec3::new(-1.0, -1.0, 0.5),
+                Vec3::new(1.0, 1.0, 0.5),
+                Vec3::new(-1.0, 1.0, 0.5),
+            ],
+        );
+        let (huge_colors, huge_depths, _) = draw(&uniforms, &[&huge]);
+        let (quad_colors, quad_depths, _) = draw(&uniforms, &[&quad]);
+        assert!(huge_colors.buffer.iter().all(|&pixel| pixel == FILL));
+        assert_eq!(huge_colors.buffer, quad_colors.buffer);
+        assert_eq!(huge_depths, quad_depths);
+    }
+
+    // Tablero de 8 x 8 según la UV interpolada del fragmento
+    struct Checker;
+
+    const CHECKER_DARK: u32 = 0x00FF00;
+
+    fn checker_parity(u: f32, v: f32) -> bool {
+        ((u * 8.0).floor() + (v * 8.0).floor()).rem_euclid(2.0) < 1.0
+    }
+
+    impl FragmentShader for Checker {
+        fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms, _params: &ShadingParams) -> Option<Color> {
+            let uv = fragment.tex_coords;
+            Some(Color::from_hex(if checker_parity(uv.x, uv.y) { FILL } else { CHECKER_DARK }))
+        }
+    }
+
+    // Píxeles pintados que no tienen el color del tablero que se ve de verdad, y los
+    // pintados en total. La referencia lanza un rayo por el centro de cada píxel
+    // contra el plano z = 0, donde está el cuadrado del modelo (UV = posición / 2 + 0.5)
+    fn checker_mismatches(framebuffer: &Framebuffer, uniforms: &Uniforms) -> (usize, usize) {
+        let inverse = (uniforms.projection_matrix * uniforms.view_matrix).try_inverse().unwrap();
+        let unproject = |x: f32, y: f32, z: f32| {
+            let point = inverse * nalgebra_glm::vec4(x, y, z, 1.0);
+            point.xyz() / point.w
+        };
+        let (mut wrong, mut painted) = (0, 0);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let pixel = framebuffer.buffer[y * WIDTH + x];
+                if pixel == BACKGROUND {
+                    continue;
+                }
+                painted += 1;
+                let ndc = ((x as f32 + 0.5) / WIDTH as f32 * 2.0 - 1.0, 1.0 - (y as f32 + 0.5) / HEIGHT as f32 * 2.0);
+                let (near, far) = (unproject(ndc.0, ndc.1, -1.0), unproject(ndc.0, ndc.1, 1.0));
+                let hit = near + (far - near) * (near.z / (near.z - far.z));
+                let expected = if checker_parity(hit.x / 2.0 + 0.5, hit.y / 2.0 + 0.5) { FILL } else { CHECKER_DARK };
+                wrong += (pixel != expected) as usize;
+            }
+        }
+        (wrong, painted)
+    }
+
+    #[test]
+    fn perspective_correct_checker_matches_the_reference_and_affine_does_not() {
+        let camera = Camera::new(Vec3::new(0.0, -1.6, 0.35), Vec3::new(0.0, 0.3, 0.0), Vec3::z());
+        let projection = Projection::new(60f32.to_radians(), WIDTH as f32 / HEIGHT as f32, 0.1, 10.0);
+        let uniforms = Uniforms { fragment_shader: Some(Arc::new(Checker)), ..solid(camera.view_matrix(), projection.matrix()) };
+        let quad = Model3D::plane(2.0, 2.0, 1);
+
+        let (perspective, _, _) = draw(&uniforms, &[&quad]);
+
+        // Lo mismo sin corregir: con w = 1 los pesos de pantalla se usan tal cual
+        let mut affine = Framebuffer::new(WIDTH, HEIGHT);
+        affine.clear(BACKGROUND);
+        let mut z_buffer = vec![f32::INFINITY; WIDTH * HEIGHT];
+        let viewport = Viewport::full(&affine);
+        let params = ShadingParams::new(Vec3::zeros(), 0.0, 0.0);
+        let mut stats = RenderStats::default();
+        let mut geometry = screen_geometry(&uniforms.for_model(&quad), &params, &viewport, &quad, &mut stats);
+        geometry.vertices.iter_mut().for_each(|vertex| vertex.transformed_w = 1.0);
+        rasterize(&mut affine, &mut z_buffer, &uniforms, &params, &viewport, &geometry, &mut stats);
+
+        let (perspective_wrong, painted) = checker_mismatches(&perspective, &uniforms);
+        let (affine_wrong, affine_painted) = checker_mismatches(&affine, &uniforms);
+        assert_eq!(painted, affine_painted);
+        assert!(painted > WIDTH * HEIGHT / 4);
+        // Solo pueden fallar los píxeles cuyo centro cae justo sobre un borde del tablero
+        assert!(perspective_wrong * 100 <= painted, "{} de {}", perspective_wrong, painted);
+        assert!(affine_wrong * 100 >= painted * 15, "{} de {}", affine_wrong, painted);
+    }
+
+    #[test]
+    fn degenerate_triangles_are_counted_and_leave_the_depth_buffer_alone() {
+        let model = triangles(&[
+            Vec3::new(-0.5, -0.5, 0.2), Vec3::new(0.5, -0.5, 0.2), Vec3::new(0.0, 0.5, 0.2),
+            // Un vértice repetido y tres alineados
+            Vec3::new(0.1, 0.1, 0.3), Vec3::new(0.1, 0.1, 0.3), Vec3::new(0.7, 0.4, 0.9),
+            Vec3::new(-0.9, -0.9, 0.1), Vec3::new(0.0, 0.0, 0.5), Vec3::new(0.9, 0.9, 0.9),
+        ]);
+        let (framebuffer, z_buffer, stats) = draw(&solid(Mat4::identity(), Mat4::identity()), &[&model]);
+        assert_eq!((stats.triangles_rasterized, stats.triangles_degenerate), (1, 2));
+        // Lo único que escribió es el triángulo bueno, a su profundidad
+        for (depth, &color) in z_buffer.iter().zip(&framebuffer.buffer) {
+            if color == FILL {
+                assert!(depth.is_finite());
+            } else {
+                assert_eq!((*depth, color), (f32::INFINITY, BACKGROUND));
+            }
+        }
+        assert!(framebuffer.buffer.contains(&FILL));
+    }
+
+    const WIRE: u32 = 0x00FFFF;
+
+    // Píxeles de arista de `framebuffer` que no están en `reference`, sin contar los
+    // del contorno (los que tocan el fondo en `filled`): ahí la misma arista de la
+    // silueta puede salir de la cara de atrás con otros píxeles al redondear
+    fn extra_inner_edges(framebuffer: &Framebuffer, reference: &Framebuffer, filled: &Framebuffer) -> usize {
+        let inner = |x: usize, y: usize| {
+            (y - 1..=y + 1).all(|y| (x - 1..=x + 1).all(|x| filled.buffer[y * WIDTH + x] != BACKGROUND))
+        };
+        (1..HEIGHT - 1)
+            .flat_map(|y| (1..WIDTH - 1).map(move |x| (x, y)))
+            .filter(|&(x, y)| framebuffer.buffer[y * WIDTH + x] == WIRE && reference.buffer[y * WIDTH + x] != WIRE && inner(x, y))
+            .count()
+    }
+
+    #[test]
+    fn fill_and_wireframe_draws_only_the_visible_edges_of_a_cube() {
+        // Desde este lado se ven las caras +X, +Y y +Z
+        let camera = Camera::new(Vec3::new(3.0, 3.5, 4.5), Vec3::zeros(), Vec3::y());
+        let projection = Projection::new(50f32.to_radians(), WIDTH as f32 / HEIGHT as f32, 0.1, 20.0);
+        let uniforms = Uniforms {
+            render_mode: RenderMode::FillAndWireframe,
+            wire_color: Color::from_hex(WIRE),
+            ..solid(camera.view_matrix(), projection.matrix())
+        };
+        let cube = Model3D::cube(2.0);
+
+        // La referencia son las aristas de las caras de adelante solas: descartando las
+        // de atrás no hace falta la profundidad para saber cuáles se ven
+        let front = Uniforms { render_mode: RenderMode::Wireframe, cull_mode: CullMode::Back, ..uniforms.clone() };
+        let (reference, _, _) = draw(&front, &[&cube]);
+
+        // Sin descartar caras las aristas de atrás también se dibujan, y el relleno
+        // las tapa: ninguna aparece dentro del cubo, y no se pierde ninguna de adelante
+        let (filled, _, _) = draw(&uniforms, &[&cube]);
+        assert_eq!(extra_inner_edges(&filled, &reference, &filled), 0);
+        let lost = (0..WIDTH * HEIGHT).filter(|&i| reference.buffer[i] == WIRE && filled.buffer[i] != WIRE).count();
+        assert_eq!(lost, 0);
+        assert!(filled.buffer.contains(&FILL));
+
+        // Sin relleno que las tape, las de atrás sí cruzan el cubo
+        let wireframe = Uniforms { render_mode: RenderMode::Wireframe, ..uniforms.clone() };
+        let (framebuffer, _, _) = draw(&wireframe, &[&cube]);
+        assert!(extra_inner_edges(&framebuffer, &reference, &filled) > 20);
+    }
+
+    #[test]
+    fn blend_color_mixes_by_coverage() {
+        let mut framebuffer = Framebuffer::new(2, 1);
+        framebuffer.clear(0x000000);
+        framebuffer.blend_color(0, 0, Color::from_hex(0xFFFFFF), 0.5);
+        framebuffer.blend_color(1, 0, Color::from_hex(0xFFFFFF), 0.0);
+        // Afuera de la ventana no hace nada
+        framebuffer.blend_color(2, 0, Color::from_hex(0xFFFFFF), 1.0);
+        assert_eq!(framebuffer.buffer, vec![0x7F7F7F, 0x000000]);
+        framebuffer.blend_color(1, 0, Color::from_hex(0x00FF00), 1.0);
+        framebuffer.blend_color(0, 0, Color::from_hex(0x7F7F7F), 1.0);
+        assert_eq!(framebuffer.buffer, vec![0x7F7F7F, 0x00FF00]);
+    }
+
+    #[test]
+    fn antialiased_wireframe_blends_with_the_background() {
+        let triangle = triangles(&[Vec3::new(-0.8, -0.7, 0.5), Vec3::new(0.9, -0.2, 0.5), Vec3::new(0.1, 0.8, 0.5)]);
+        let wireframe = Uniforms {
+            render_mode: RenderMode::Wireframe,
+            wire_color: Color::from_hex(WIRE),
+            ..solid(Mat4::identity(), Mat4::identity())
+        };
+        let (hard, _, _) = draw(&wireframe, &[&triangle]);
+        assert!(hard.buffer.iter().all(|&pixel| pixel == BACKGROUND || pixel == WIRE));
+
+        let (soft, _, _) = draw(&Uniforms { wire_antialias: true, ..wireframe }, &[&triangle]);
+        let mixed = soft.buffer.iter().filter(|&&pixel| pixel != BACKGROUND && pixel != WIRE).count();
+        assert!(mixed > soft.buffer.iter().filter(|&&pixel| pixel == WIRE).count());
+    }
+
+    #[test]
+    fn framebuffer_polygons_are_clipped_to_the_window() {
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(BACKGROUND);
+        // Un triángulo que tapa la ventana con mucho de sobra
+        let huge = [(-500.0, -500.0), (900.0, -20.0), (-20.0, 900.0)];
+        framebuffer.fill_polygon(&huge, &Viewport::full(&framebuffer), Color::from_hex(FILL), 1.0);
+        assert!(framebuffer.buffer.iter().all(|&pixel| pixel == FILL));
+
+        // En una de las cuatro vistas no pasa a las otras
+        framebuffer.clear(BACKGROUND);
+        let [_, top_right, _, _] = Viewport::quad(&framebuffer);
+        framebuffer.fill_polygon(&huge, &top_right, Color::from_hex(FILL), 1.0);
+        for (i, &pixel) in framebuffer.buffer.iter().enumerate() {
+            let inside = top_right.pixel(Vec2::new((i % WIDTH) as f32, (i / WIDTH) as f32)).is_some();
+            assert_eq!(pixel, if inside { FILL } else { BACKGROUND });
+        }
+    }
+
+    #[test]
+    fn translucent_spans_tint_what_is_behind() {
+        let mut framebuffer = Framebuffer::new(4, 1);
+        framebuffer.clear(0x000000);
+        framebuffer.fill_span(0, 1, 2, Color::from_hex(0xFFFFFF), 0.5);
+        assert_eq!(framebuffer.buffer, vec![0x000000, 0x7F7F7F, 0x7F7F7F, 0x000000]);
+    }
+
+    // Huella del color y la profundidad de cada píxel
+    fn buffers_hash(framebuffer: &Framebuffer, z_buffer: &[f32]) -> (u64, u64) {
+        use std::hash::{Hash, Hasher};
+        let mut colors = std::collections::hash_map::DefaultHasher::new();
+        framebuffer.buffer.hash(&mut colors);
+        let mut depths = std::collections::hash_map::DefaultHasher::new();
+        z_buffer.iter().for_each(|depth| depth.to_bits().hash(&mut depths));
+        (colors.finish(), depths.finish())
+    }
+
+    #[test]
+    fn bands_and_tiles_match_the_single_thread_output() {
+        // Un cubo girado que atraviesa una esfera, con luz por píxel y las aristas
+        // encima: muchos triángulos que se tapan entre sí y cruzan los bordes de las
+        // franjas y las teselas
+        let camera = Camera::new(Vec3::new(2.0, 1.5, 3.5), Vec3::zeros(), Vec3::y());
+        let projection = Projection::new(50f32.to_radians(), WIDTH as f32 / HEIGHT as f32, 0.1, 20.0);
+        let serial = Uniforms {
+            model_matrix: Mat4::new_rotation(Vec3::new(0.4, 0.7, 0.1)),
+            shading: Shading::BlinnPhong,
+            render_mode: RenderMode::FillAndWireframe,
+            ..Uniforms::new(Mat4::identity(), camera.view_matrix(), projection.matrix())
+        };
+        let (cube, sphere) = (Model3D::cube(1.6), Model3D::uv_sphere(1.0, 12, 24));
+        let (framebuffer, z_buffer, _) = draw(&serial, &[&cube, &sphere]);
+        assert!(framebuffer.buffer.iter().filter(|&&pixel| pixel != BACKGROUND).count() > WIDTH * HEIGHT / 6);
+        let expected = buffers_hash(&framebuffer, &z_buffer);
+
+        let bands = [2, 3, 4, 7].map(|threads| Uniforms { threads, ..serial.clone() });
+        let tiles = [(1, 8), (2, 8), (3, 16), (4, 5)].map(|(threads, tile_size)| Uniforms { threads, tile_size, pipeline: Pipeline::Tiles, ..serial.clone() });
+        for uniforms in bands.iter().chain(&tiles) {
+            let (framebuffer, z_buffer, _) = draw(uniforms, &[&cube, &sphere]);
+            assert_eq!(buffers_hash(&framebuffer, &z_buffer), expected, "{:?} con {} hilos", uniforms.pipeline, uniforms.threads);
+        }
+    }
+
+    // Cuadrado de 2 * `half` de lado en X e Y, con la Z que da `depth` en cada esquina
+    fn quad(half: f32, depth: impl Fn(f32, f32) -> f32) -> Model3D {
+        let corner = |x: f32, y: f32| Vec3::new(x * half, y * half, depth(x * half, y * half));
+        triangles(&[corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0)])
+    }
+
+    #[test]
+    fn interpenetrating_quads_cross_where_their_planes_meet() {
+        // La cámara en el origen mira hacia -Z con 90 grados de campo vertical. Un
+        // cuadrado de frente a 3 de distancia y otro muy inclinado que lo atraviesa
+        let projection = Projection::new(std::f32::consts::FRAC_PI_2, WIDTH as f32 / HEIGHT as f32, 0.1, 20.0);
+        let level = |_: f32, _: f32| -3.0;
+        let steep = |x: f32, y: f32| -3.0 + 2.0 * (x - 0.6) + 0.5 * y;
+        let (front, tilted) = (quad(2.0, level), quad(1.5, steep));
+
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(BACKGROUND);
+        let mut z_buffer = vec![f32::INFINITY; WIDTH * HEIGHT];
+        let viewport = Viewport::full(&framebuffer);
+        let params = ShadingParams::new(Vec3::zeros(), 0.0, 0.0);
+        let uniforms = solid(Mat4::identity(), projection.matrix());
+        render(&mut framebuffer, &mut z_buffer, &uniforms, &params, &viewport, &front);
+        let second = Uniforms { fragment_shader: Some(Arc::new(Solid(CHECKER_DARK))), ..uniforms };
+        render(&mut framebuffer, &mut z_buffer, &second, &params, &viewport, &tilted);
+
+        // Se lanza un rayo por el centro de cada píxel contra los dos planos: donde
+        // los dos cuadrados están, tiene que verse el que corta el rayo primero
+        let (mut judged, mut wrong, mut level_seen) = (0, 0, 0);
+        for y in 0..HEIGHT {
+            for x in 0..WIDTH {
+                let ray = Vec3::new(
+                    ((x as f32 + 0.5) / WIDTH as f32 * 2.0 - 1.0) * WIDTH as f32 / HEIGHT as f32,
+                    1.0 - (y as f32 + 0.5) / HEIGHT as f32 * 2.0,
+                    -1.0,
+                );
+                // -t = -3 + 2 (t rx - 0.6) + 0.5 t ry
+                let (level_t, steep_t) = (3.0, 4.2 / (1.0 + 2.0 * ray.x + 0.5 * ray.y));
+                let inside = |t: f32, half: f32| t > 0.0 && (ray.x * t).abs() < half && (ray.y * t).abs() < half;
+                // Muy cerca de la intersección decide el redondeo
+                if !inside(level_t, 2.0) || !inside(steep_t, 1.5) || (level_t - steep_t).abs() < 0.05 {
+                    continue;
+                }
+                judged += 1;
+                let expected = if level_t < steep_t { FILL } else { CHECKER_DARK };
+                level_seen += (expected == FILL) as usize;
+                wrong += (framebuffer.buffer[y * WIDTH + x] != expected) as usize;
+            }
+        }
+        assert!(judged > WIDTH * HEIGHT / 8, "{}", judged);
+        // La línea de la intersección cruza la zona: de los dos lados hay píxeles
+        assert!(level_seen > judged / 5 && level_seen < judged * 4 / 5, "{} de {}", level_seen, judged);
+        assert_eq!(wrong, 0);
+    }
+
+    #[test]
+    fn fragments_left_or_above_the_viewport_have_no_pixel() {
+        let viewport = Viewport { x: 10, y: 20, width: 30, height: 15, ..Viewport::full(&Framebuffer::new(WIDTH, HEIGHT)) };
+        assert_eq!(viewport.pixel(Vec2::new(10.0, 20.0)), Some((10, 20)));
+        assert_eq!(viewport.pixel(Vec2::new(39.9, 34.9)), Some((39, 34)));
+        // `as usize` los dejaría en la primera columna o fila del viewport
+        for outside in [(9.5, 25.0), (15.0, 19.99), (-0.5, -0.5), (40.0, 25.0), (15.0, 35.0), (f32::NAN, 25.0)] {
+            assert_eq!(viewport.pixel(Vec2::new(outside.0, outside.1)), None, "{:?}", outside);
+        }
+    }
+
+    #[test]
+    fn triangle_over_the_corner_stays_in_the_corner() {
+        // Sin proyección: (-1, 1) es la esquina de arriba a la izquierda
+        let corner = triangles(&[Vec3::new(-1.5, 1.5, 0.5), Vec3::new(-0.6, 1.4, 0.5), Vec3::new(-1.3, 0.5, 0.5)]);
+        let uniforms = solid(Mat4::identity(), Mat4::identity());
+        let (framebuffer, _, _) = draw(&uniforms, &[&corner]);
+        assert_eq!(framebuffer.buffer[0], FILL);
+        for (i, &pixel) in framebuffer.buffer.iter().enumerate() {
+            if pixel == FILL {
+                // Nada del otro lado: ni la última columna ni la última fila
+                assert!(i % WIDTH < WIDTH / 2 && i / WIDTH < HEIGHT / 2, "píxel {:?}", (i % WIDTH, i / WIDTH));
+            }
+        }
+
+        // Lo mismo en la vista de abajo a la derecha: lo que se sale de ella queda sin pintar
+        let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+        framebuffer.clear(BACKGROUND);
+        let mut z_buffer = vec![f32::INFINITY; WIDTH * HEIGHT];
+        let viewport = Viewport { x: 32, y: 24, width: 32, height: 24, ..Viewport::full(&framebuffer) };
+        let params = ShadingParams::new(Vec3::zeros(), 0.0, 0.0);
+        render(&mut framebuffer, &mut z_buffer, &uniforms, &params, &viewport, &corner);
+        assert_eq!(framebuffer.buffer[24 * WIDTH + 32], FILL);
+        for (i, &pixel) in framebuffer.buffer.iter().enumerate() {
+            let (x, y) = (i % WIDTH, i / WIDTH);
+            if x < 32 || y < 24 || x == WIDTH - 1 || y == HEIGHT - 1 {
+                assert_eq!(pixel, BACKGROUND, "píxel {:?}", (x, y));
+            }
+        }
+    }
+
+    #[test]
+    fn scissor_leaves_everything_outside_unchanged() {
+        let (width, height) = (160, 120);
+        let scissor = Scissor { x: 30, y: 10, width: 100, height: 100 };
+        let outside = |i: usize| {
+            let (x, y) = (i % width, i / width);
+            !(30..130).contains(&x) || !(10..110).contains(&y)
+        };
+        // Un fondo con algo dibujado, para ver que no se toca
+        let background: Vec<u32> = (0..width * height).map(|i| (i as u32).wrapping_mul(2_654_435_761) & 0xFFFFFF).collect();
+        let full_screen = triangles(&[Vec3::new(-3.0, -3.0, 0.5), Vec3::new(3.0, -3.0, 0.5), Vec3::new(0.0, 3.0, 0.5)]);
+
+        for (pipeline, threads) in [(Pipeline::Bands, 1), (Pipeline::Bands, 3), (Pipeline::Tiles, 2)] {
+            let uniforms = Uniforms {
+                scissor: Some(scissor),
+                pipeline,
+                threads,
+                tile_size: 16,
+                render_mode: RenderMode::FillAndWireframe,
+                ..solid(Mat4::identity(), Mat4::identity())
+            };
+            let mut framebuffer = Framebuffer { width, height, buffer: background.clone() };
+            let mut z_buffer = vec![f32::INFINITY; width * height];
+            let viewport = Viewport::full(&framebuffer);
+            let params = ShadingParams::new(Vec3::zeros(), 0.0, 0.0);
+            render(&mut framebuffer, &mut z_buffer, &uniforms, &params, &viewport, &full_screen);
+            for i in 0..width * height {
+                if outside(i) {
+                    assert_eq!((framebuffer.buffer[i], z_buffer[i]), (background[i], f32::INFINITY), "{:?} en {:?}", pipeline, (i % width, i / width));
+                } else {
+                    assert_eq!(framebuffer.buffer[i], FILL, "{:?} en {:?}", pipeline, (i % width, i / width));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn depth_bias_decides_between_coplanar_quads() {
+        // El mismo plano inclinado, partido por una diagonal o por la otra: las
+        // profundidades interpoladas coinciden salvo por el redondeo
+        let corner = |x: f32, y: f32| Vec3::new(x, y, -3.0 + 1.2 * x + 0.4 * y);
+        let (a, b, c, d) = (corner(-1.0, -1.0), corner(1.0, -1.0), corner(1.0, 1.0), corner(-1.0, 1.0));
+        let base = triangles(&[a, b, c, a, c, d]);
+        let decal = triangles(&[a, b, d, b, c, d]);
+        let projection = Projection::new(std::f32::consts::FRAC_PI_2, WIDTH as f32 / HEIGHT as f32, 0.1, 20.0);
+        let plain = solid(Mat4::identity(), projection.matrix());
+        let marked = |bias: f32, slope: f32| Uniforms {
+            fragment_shader: Some(Arc::new(Solid(CHECKER_DARK))),
+            depth_bias_constant: bias,
+            depth_bias_slope: slope,
+            ..plain.clone()
+        };
+        let params = ShadingParams::new(Vec3::zeros(), 0.0, 0.0);
+        // Dibuja los dos en el orden dado y devuelve la imagen y la profundidad
+        let draw_pair = |first: (&Uniforms, &Model3D), second: (&Uniforms, &Model3D)| {
+            let mut framebuffer = Framebuffer::new(WIDTH, HEIGHT);
+            framebuffer.clear(BACKGROUND);
+            let mut z_buffer = vec![f32::INFINITY; WIDTH * HEIGHT];
+            let viewport = Viewport::full(&framebuffer);
+            for (uniforms, model) in [first, second] {
+                render(&mut framebuffer, &mut z_buffer, uniforms, &params, &viewport, model);
+            }
+            (framebuffer, z_buffer)
+        };
+        let painted = |framebuffer: &Framebuffer, color: u32| framebuffer.buffer.iter().filter(|&&pixel| pixel == color).count();
+
+        // El plano solo: cuántos píxeles cubre y a qué profundidad
+        let (alone, alone_depth) = draw_pair((&plain, &base), (&plain, &base));
+        let surface = painted(&alone, FILL);
+        assert!(surface > WIDTH * HEIGHT / 8);
+
+        // Sin desplazamiento no lo decide el orden sino el redondeo de cada píxel:
+        // el que se dibuja después no gana en todos, o el de antes no pierde en todos
+        let (decal_last, _) = draw_pair((&plain, &base), (&marked(0.0, 0.0), &decal));
+        let (decal_first, _) = draw_pair((&marked(0.0, 0.0), &decal), (&plain, &base));
+        assert!(painted(&decal_last, CHECKER_DARK) < surface || painted(&decal_first, CHECKER_DARK) > 0);
+
+        // Con desplazamiento, constante o según la pendiente, el marcado gana en
+        // cualquier orden y en todos los píxeles del plano
+        for bias in [marked(1e-3, 0.0), marked(0.0, 1.0)] {
+            let (last, last_depth) = draw_pair((&plain, &base), (&bias, &decal));
+            let (first, _) = draw_pair((&bias, &decal), (&plain, &base));
+            assert_eq!((painted(&last, CHECKER_DARK), painted(&first, CHECKER_DARK)), (surface, surface));
+            // El desplazamiento cuenta al comparar y no se guarda
+            for (stored, expected) in last_depth.iter().zip(&alone_depth) {
+                assert!(stored == expected || (stored - expected).abs() < 1e-4, "{} contra {}", stored, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn downsample_averages_each_block() {
+        let mut samples = Framebuffer::new(4, 2);
+        samples.buffer = vec![0xFFFFFF, 0x000000, 0x102030, 0x102030, 0x000000, 0xFFFFFF, 0x102030, 0x506070];
+        let mut target = Framebuffer::new(2, 1);
+        target.downsample(&samples, 2);
+        // Cada canal por separado, redondeando hacia abajo
+        assert_eq!(target.buffer, vec![0x7F7F7F, 0x203040]);
+
+        // Con factor 1 queda igual
+        let mut copy = Framebuffer::new(4, 2);
+        copy.downsample(&samples, 1);
+        assert_eq!(copy.buffer, samples.buffer);
+    }
+
+    #[test]
+    fn supersampled_edges_fall_between_the_two_colors() {
+        let triangle = triangles(&[Vec3::new(-0.9, -0.8, 0.5), Vec3::new(0.7, -0.6, 0.5), Vec3::new(-0.2, 0.9, 0.5)]);
+        let uniforms = solid(Mat4::identity(), Mat4::identity());
+        let (plain, _, _) = draw(&uniforms, &[&triangle]);
+
+        let mut samples = Framebuffer::new(WIDTH * 4, HEIGHT * 4);
+        samples.clear(BACKGROUND);
+        let mut z_buffer = vec![f32::INFINITY; samples.buffer.len()];
+        let params = ShadingParams::new(Vec3::zeros(), 0.0, 0.0);
+        let viewport = Viewport::full(&samples);
+        render(&mut samples, &mut z_buffer, &uniforms, &params, &viewport, &triangle);
+        let mut smooth = Framebuffer::new(WIDTH, HEIGHT);
+        smooth.downsample(&samples, 4);
+
+        // Adentro y afuera del borde es lo mismo; en el borde hay mezclas, y cada una
+        // con cada canal entre el del fondo y el del relleno
+        let channels = |pixel: u32| [(pixel >> 16) & 0xFF, (pixel >> 8) & 0xFF, pixel & 0xFF];
+        let mut edge = 0;
+        for (&sharp, &soft) in plain.buffer.iter().zip(&smooth.buffer) {
+            if soft == FILL || soft == BACKGROUND {
+                assert_eq!(sharp, soft);
+                continue;
+            }
+            edge += 1;
+            for ((channel, low), high) in channels(soft).into_iter().zip(channels(BACKGROUND)).zip(channels(FILL)) {
+                assert!(channel >= low.min(high) && channel <= low.max(high), "{:06x}", soft);
+            }
+            assert!(sharp == FILL || sharp == BACKGROUND);
+        }
+        let painted = |framebuffer: &Framebuffer| framebuffer.buffer.iter().filter(|&&pixel| pixel == FILL).count();
+        assert!(edge > 40, "{}", edge);
+        assert!(painted(&smooth) < painted(&plain) && painted(&smooth) > painted(&plain) / 2);
+    }
+
+    // La transformación que `screen_geometry` hacía en línea antes de que existiera el
+    // shader de vértices, copiada tal cual
+    struct InlineTransform;
+
+    impl VertexShader for InlineTransform {
+        fn shade(&self, vertex: &Vertex, uniforms: &Uniforms, _params: &ShadingParams) -> Vertex {
+            let clip_matrix = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix;
+            let position = nalgebra_glm::vec4(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
+            let clip = clip_matrix * position;
+            let world = (uniforms.model_matrix * position).xyz();
+            let transformed_normal = (uniforms.normal_matrix * vertex.normal).try_normalize(f32::EPSILON).unwrap_or_else(Vec3::zeros);
+            Vertex {
+                position: world,
+                transformed_position: clip.xyz(),
+                transformed_w: clip.w,
+                transformed_normal,
+                ..vertex.clone()
+            }
+        }
+    }
+
+    #[test]
+    fn primitives_demo_is_the_size_of_a_normalized_model() {
+        let model = primitives();
+        let parts = [Model3D::cube(0.6), Model3D::torus(0.3, 0.1, 32, 16), Model3D::cylinder(0.3, 0.6, 24, true)];
+        assert_eq!(model.triangle_count(), parts.iter().map(Model3D::triangle_count).sum::<usize>());
+        let bounds = model.bounding_box().unwrap().transformed(&model.model_matrix());
+        assert!((bounds.size().max() - 1.0).abs() < 1e-4, "{:?}", bounds.size());
+    }
+
+    #[test]
+    fn depth_shader_matches_the_depth_view_between_the_planes() {
+        let camera = Camera::new(Vec3::new(1.5, 2.0, 3.0), Vec3::zeros(), Vec3::y());
+        let projection = Projection::new(50f32.to_radians(), WIDTH as f32 / HEIGHT as f32, 1.0, 8.0);
+        let uniforms = Uniforms {
+            fragment_shader: Some(Arc::new(DepthShader)),
+            ..Uniforms::new(Mat4::identity(), camera.view_matrix(), projection.matrix())
+        };
+        let (shaded, z_buffer, _) = draw(&uniforms, &[&Model3D::cube(1.5)]);
+        let mut expected = Framebuffer::new(WIDTH, HEIGHT);
+        let viewport = Viewport::full(&expected);
+        render_depth(&mut expected, &z_buffer, &uniforms.projection_matrix, &viewport, DepthView::Planes);
+
+        let covered: Vec<usize> = (0..WIDTH * HEIGHT).filter(|&index| z_buffer[index].is_finite()).collect();
+        assert!(covered.len() > WIDTH * HEIGHT / 10);
+        for index in covered {
+            let (got, want) = (shaded.buffer[index], expected.buffer[index]);
+            assert!(got >> 16 == got & 0xFF && (got >> 8) & 0xFF == got & 0xFF, "{:06X}", got);
+            assert!(((got & 0xFF) as i32 - (want & 0xFF) as i32).abs() <= 2, "{}: {:06X} {:06X}", index, got, want);
+        }
+    }
+
+    #[test]
+    fn default_vertex_shader_matches_the_old_inline_transform() {
+        let camera = Camera::new(Vec3::new(1.5, 2.0, 4.0), Vec3::zeros(), Vec3::y());
+        let projection = Projection::new(50f32.to_radians(), WIDTH as f32 / HEIGHT as f32, 0.1, 20.0);
+        let mut cube = Model3D::cube(1.2);
+        cube.set_position(Vec3::new(-0.8, 0.2, 0.0));
+        cube.set_rotation(Vec3::new(0.3, 0.9, 0.0));
+        let mut sphere = Model3D::uv_sphere(0.8, 10, 20);
+        sphere.set_position(Vec3::new(0.7, -0.1, 0.3));
+        sphere.set_scale(1.3);
+
+        // Con luz por vértice y por píxel: usan la posición de mundo y la normal que
+        // deja el shader, no solo la de recorte
+        for shading in [Shading::Gouraud, Shading::BlinnPhong] {
+            let default = Uniforms {
+                shading,
+                model_matrix: Mat4::new_rotation(Vec3::new(0.0, 0.4, 0.0)),
+                ..Uniforms::new(Mat4::identity(), camera.view_matrix(), projection.matrix())
+            };
+            let inline = Uniforms { vertex_shader: Arc::new(InlineTransform), ..default.clone() };
+            let (expected, expected_depth, _) = draw(&inline, &[&cube, &sphere]);
+            let (framebuffer, z_buffer, _) = draw(&default, &[&cube, &sphere]);
+            assert!(expected.buffer.iter().filter(|&&pixel| pixel != BACKGROUND).count() > WIDTH * HEIGHT / 6);
+            assert_eq!(framebuffer.buffer, expected.buffer, "{:?}", shading);
+            assert_eq!(z_buffer, expected_depth, "{:?}", shading);
+
+            // Y el pipeline de verdad usa el shader: uno que infla la malla cambia la imagen
+            let inflated = Uniforms { vertex_shader: Arc::new(Inflated), ..default.clone() };
+            let (framebuffer, _, _) = draw(&inflated, &[&cube, &sphere]);
+            assert_ne!(framebuffer.buffer, expected.buffer);
+        }
+    }
+
+    // Mueve cada vértice a lo largo de su normal antes de la transformación de siempre
+    struct Inflated;
+
+    impl VertexShader for Inflated {
+        fn shade(&self, vertex: &Vertex, uniforms: &Uniforms, params: &ShadingParams) -> Vertex {
+            let moved = Vertex { position: vertex.position + vertex.normal * 0.3, ..vertex.clone() };
+            DefaultVertexShader.shade(&moved, uniforms, params)
+        }
+    }
+}
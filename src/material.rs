@@ -0,0 +1,73 @@
+// material.rs
+
+use std::fs;
+use std::path::Path;
+use crate::color::Color;
+use crate::obj::{parse_floats, ObjError};
+use crate::vertex::{DEFAULT_AMBIENT, DEFAULT_SHININESS, DEFAULT_SPECULAR};
+
+#[derive(Debug, Clone)]
+pub struct Material {
+    pub name: String,
+    pub ambient: Color,  // Ka
+    pub diffuse: Color,  // Kd
+    pub specular: Color, // Ks
+    pub shininess: f32,  // Ns: exponente del brillo
+    pub emissive: Color, // Ke: luz propia, como el escape de un motor
+}
+
+impl Material {
+    // Carga todos los materiales (`newmtl`) de un archivo .mtl
+    pub fn load_library(path: &Path) -> Result<Vec<Material>, ObjError> {
+        let source = fs::read_to_string(path)?;
+        Self::parse_library(&source)
+    }
+
+    pub fn parse_library(source: &str) -> Result<Vec<Material>, ObjError> {
+        let mut materials: Vec<Material> = Vec::new();
+
+        for (number, content) in source.lines().enumerate() {
+            let error = |reason| ObjError::Parse { line: number + 1, content: content.to_string(), reason };
+
+            let data = content.split('#').next().unwrap_or("");
+            let mut tokens = data.split_whitespace();
+            let Some(keyword) = tokens.next() else { continue };
+
+            if keyword == "newmtl" {
+                materials.push(Material {
+                    name: tokens.collect::<Vec<_>>().join(" "),
+                    ..Material::default()
+                });
+                continue;
+            }
+
+            // Las propiedades anteriores a cualquier `newmtl` no tienen a quién aplicarse
+            let Some(material) = materials.last_mut() else { continue };
+            match keyword {
+                "Ka" => material.ambient = Color::from_rgb(parse_floats(tokens, 3).map_err(error)?),
+                "Kd" => material.diffuse = Color::from_rgb(parse_floats(tokens, 3).map_err(error)?),
+                "Ks" => material.specular = Color::from_rgb(parse_floats(tokens, 3).map_err(error)?),
+                "Ns" => material.shininess = parse_floats::<1>(tokens, 1).map_err(error)?[0],
+                "Ke" => material.emissive = Color::from_rgb(parse_floats(tokens, 3).map_err(error)?),
+                // Texturas, `illum`, `d`, etc. todavía no se usan
+                _ => {}
+            }
+        }
+
+        Ok(materials)
+    }
+}
+
+// Material usado cuando la cara no tiene `usemtl` o el .mtl no se pudo cargar
+impl Default for Material {
+    fn default() -> Self {
+        Material {
+            name: String::from("default"),
+            ambient: DEFAULT_AMBIENT,
+            diffuse: Color { r: 0.8, g: 0.8, b: 0.8 },
+            specular: DEFAULT_SPECULAR,
+            shininess: DEFAULT_SHININESS,
+            emissive: Color::black(),
+        }
+    }
+}
@@ -1,35 +1,1230 @@
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::fmt;
+use std::ops::Range;
 use crate::vertex::Vertex;
+use crate::color::Color;
+use crate::obj::{Obj, ObjError};
+use crate::stl::{Stl, StlError};
+use crate::ply::{Ply, PlyError};
+use crate::gltf::{Gltf, GltfError};
+use crate::material::Material;
+use crate::utils::thousands;
+use nalgebra_glm::{Mat3, Mat4, Vec2, Vec3};
 
-#[derive(Debug)]
-pub struct Edge {
-    pub start: Vertex,
-    pub end: Vertex,
+// Distancia bajo la cual dos posiciones se consideran el mismo punto
+const POSITION_EPSILON: f32 = 1e-4;
+// Relación área / arista² bajo la cual un triángulo se considera degenerado
+const DEGENERATE_EPSILON: f32 = 1e-6;
+// Área (relativa al cuadrado de la diagonal del modelo) bajo la cual un
+// triángulo cuenta como degenerado en las estadísticas
+const DEGENERATE_AREA_EPSILON: f32 = 1e-10;
+
+// Paleta para distinguir grupos (casco, cabina, alas...) a simple vista
+const GROUP_PALETTE: [Color; 8] = [
+    Color { r: 0.90, g: 0.30, b: 0.25 },
+    Color { r: 0.25, g: 0.60, b: 0.90 },
+    Color { r: 0.95, g: 0.75, b: 0.20 },
+    Color { r: 0.35, g: 0.80, b: 0.40 },
+    Color { r: 0.70, g: 0.40, b: 0.85 },
+    Color { r: 0.95, g: 0.55, b: 0.20 },
+    Color { r: 0.30, g: 0.85, b: 0.80 },
+    Color { r: 0.85, g: 0.45, b: 0.65 },
+];
+
+// Grupo con nombre, el rango de `vertices` que le pertenece y sus triángulos
+#[derive(Debug, Clone)]
+pub struct Group {
+    pub name: String,
+    pub vertices: Range<usize>,
+    pub triangles: Range<usize>,
+}
+
+// Cómo colorear los vértices al importar un .obj
+#[derive(Debug, Clone, Default)]
+pub enum GroupColors {
+    #[default]
+    Material,                   // Color difuso del material de cada cara
+    Palette,                    // Un color de la paleta por grupo
+    Map(HashMap<String, Color>), // Color por nombre de grupo (paleta si no está)
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct ImportOptions {
+    pub group_colors: GroupColors,
+    pub smooth_missing_normals: bool, // Generar normales suaves si el .obj no trae `vn`
+}
+
+// Qué atributos, además de la posición, deben coincidir para soldar dos vértices
+#[derive(Debug, Clone, Copy, Default)]
+pub struct WeldOptions {
+    pub normals: bool,
+    pub tex_coords: bool,
 }
 
+// Caja alineada a los ejes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Aabb {
+    pub min: Vec3,
+    pub max: Vec3,
+}
+
+impl Aabb {
+    // Caja mínima que contiene todos los puntos (None si no hay puntos)
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Vec3>) -> Option<Self> {
+        let mut points = points.into_iter();
+        let first = *points.next()?;
+        Some(points.fold(Aabb { min: first, max: first }, |aabb, p| Aabb {
+            min: aabb.min.inf(p),
+            max: aabb.max.sup(p),
+        }))
+    }
+
+    pub fn center(&self) -> Vec3 {
+        (self.min + self.max) * 0.5
+    }
+
+    pub fn size(&self) -> Vec3 {
+        self.max - self.min
+    }
+
+    pub fn corners(&self) -> [Vec3; 8] {
+        let (min, max) = (self.min, self.max);
+        [
+            Vec3::new(min.x, min.y, min.z),
+            Vec3::new(max.x, min.y, min.z),
+            Vec3::new(min.x, max.y, min.z),
+            Vec3::new(max.x, max.y, min.z),
+            Vec3::new(min.x, min.y, max.z),
+            Vec3::new(max.x, min.y, max.z),
+            Vec3::new(min.x, max.y, max.z),
+            Vec3::new(max.x, max.y, max.z),
+        ]
+    }
+
+    // Caja que contiene las ocho esquinas transformadas por `matrix`
+    pub fn transformed(&self, matrix: &Mat4) -> Aabb {
+        let corners = self.corners().map(|corner| {
+            let p = matrix * nalgebra_glm::vec4(corner.x, corner.y, corner.z, 1.0);
+            Vec3::new(p.x, p.y, p.z) / p.w
+        });
+        Aabb::from_points(&corners).expect("una caja siempre tiene esquinas")
+    }
+}
+
+// Resultado de `Model3D::normalize`: la caja original y la transformación aplicada
+#[derive(Debug, Clone, Copy)]
+pub struct Normalization {
+    pub original_bounds: Aabb, // Su centro quedó en el origen
+    pub scale: f32,
+}
+
+// Error al cargar un modelo de cualquiera de los formatos soportados
 #[derive(Debug)]
-pub struct Face {
-    pub vertices: [Vertex; 3],
+pub enum LoadError {
+    Obj(ObjError),
+    Stl(StlError),
+    Ply(PlyError),
+    Gltf(GltfError),
+    UnsupportedFormat(String), // Extensión que no corresponde a ningún cargador
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LoadError::Obj(err) => write!(f, "{}", err),
+            LoadError::Stl(err) => write!(f, "{}", err),
+            LoadError::Ply(err) => write!(f, "{}", err),
+            LoadError::Gltf(err) => write!(f, "{}", err),
+            LoadError::UnsupportedFormat(extension) => write!(f, "formato no soportado `{}`", extension),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+// Posición, orientación y escala del modelo en la escena
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub position: Vec3,
+    pub rotation: Vec3, // Ángulos de Euler en radianes, aplicados en orden X, Y, Z
+    pub scale: Vec3,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self { position: Vec3::zeros(), rotation: Vec3::zeros(), scale: Vec3::new(1.0, 1.0, 1.0) }
+    }
+}
+
+impl Transform {
+    // Matriz modelo: escala, luego rotación y al final traslación
+    pub fn matrix(&self) -> Mat4 {
+        let rotation = nalgebra_glm::rotate_z(&Mat4::identity(), self.rotation.z)
+            * nalgebra_glm::rotate_y(&Mat4::identity(), self.rotation.y)
+            * nalgebra_glm::rotate_x(&Mat4::identity(), self.rotation.x);
+        Mat4::new_translation(&self.position) * rotation * Mat4::new_nonuniform_scaling(&self.scale)
+    }
+}
+
+// Resumen de la geometría cargada, para detectar caras perdidas o atributos faltantes
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MeshStats {
+    pub vertex_count: usize,
+    pub triangle_count: usize,
+    pub degenerate_triangles: usize, // Triángulos de área prácticamente nula
+    pub extents: Vec3,               // Tamaño de la caja envolvente (cero si está vacío)
+    pub has_normals: bool,
+    pub has_tex_coords: bool,
+}
+
+impl fmt::Display for MeshStats {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{} vértices, {} triángulos, {} degenerados, {}, {}, tamaño {:.3} x {:.3} x {:.3}",
+            thousands(self.vertex_count),
+            thousands(self.triangle_count),
+            thousands(self.degenerate_triangles),
+            if self.has_normals { "con normales" } else { "sin normales" },
+            if self.has_tex_coords { "con UVs" } else { "sin UVs" },
+            self.extents.x,
+            self.extents.y,
+            self.extents.z,
+        )
+    }
 }
 
 #[derive(Debug)]
 pub struct Model3D {
     pub vertices: Vec<Vertex>,
-    pub edges: Vec<Edge>,
-    pub faces: Vec<Face>,
+    // Triángulos como índices a `vertices` (de 3 en 3). Vacío significa geometría
+    // sin índices: cada 3 vértices consecutivos forman un triángulo
+    pub indices: Vec<u32>,
+    pub groups: Vec<Group>,
+    // Se aplica en `render` antes de la matriz de `Uniforms`
+    pub transform: Transform,
+    // Caja envolvente calculada bajo demanda; se invalida al agregar o mover vértices
+    bounds_cache: Cell<Option<Option<Aabb>>>,
 }
 
 impl Model3D {
     pub fn new() -> Self {
         Self {
             vertices: Vec::new(),
-            edges: Vec::new(),
-            faces: Vec::new(),
+            indices: Vec::new(),
+            groups: Vec::new(),
+            transform: Transform::default(),
+            bounds_cache: Cell::new(None),
+        }
+    }
+
+    pub fn add_vertices_from_obj(&mut self, obj: &Obj, options: &ImportOptions) {
+        // Para anexar geometría indexada, lo que ya hubiera debe tener índices
+        self.ensure_indexed();
+        let default_material = Material::default();
+        let (first_vertex, first_face) = (self.vertices.len(), self.triangle_count());
+
+        for (index, obj_group) in obj.groups().iter().enumerate() {
+            let palette_color = GROUP_PALETTE[index % GROUP_PALETTE.len()];
+            let group_color = match &options.group_colors {
+                GroupColors::Material => None,
+                GroupColors::Palette => Some(palette_color),
+                GroupColors::Map(colors) => Some(colors.get(&obj_group.name).copied().unwrap_or(palette_color)),
+            };
+
+            let (vertex_array, index_array) = obj.get_indexed_vertex_array(obj_group.faces.clone());
+            let base = self.vertices.len();
+            let first_triangle = self.triangle_count();
+            self.vertices.extend(vertex_array);
+
+            for (face, triangle) in obj_group.faces.clone().zip(index_array.chunks_exact(3)) {
+                // Sin color de grupo, cada vértice toma el difuso del material de su cara
+                // (los vértices solo se comparten entre caras del mismo material). El
+                // ambiente, el brillo y la luz propia salen siempre del material
+                let material = obj.face_material(face).unwrap_or(&default_material);
+                for &vertex in triangle {
+                    let vertex = &mut self.vertices[base + vertex as usize];
+                    apply_material(vertex, material);
+                    vertex.color = group_color.unwrap_or(material.diffuse);
+                }
+            }
+            self.indices.extend(index_array.iter().map(|&vertex| (base as u32) + vertex));
+
+            self.groups.push(Group {
+                name: obj_group.name.clone(),
+                vertices: base..self.vertices.len(),
+                triangles: first_triangle..self.triangle_count(),
+            });
+        }
+
+        self.invalidate_bounds();
+
+        if options.smooth_missing_normals && !obj.has_normals() {
+            self.smooth_normals_in(first_face..self.triangle_count(), first_vertex..self.vertices.len());
+        }
+    }
+
+    pub fn is_indexed(&self) -> bool {
+        !self.indices.is_empty()
+    }
+
+    pub fn triangle_count(&self) -> usize {
+        if self.is_indexed() { self.indices.len() / 3 } else { self.vertices.len() / 3 }
+    }
+
+    // Índices a `vertices` de los tres vértices del triángulo `triangle`
+    pub fn triangle(&self, triangle: usize) -> [usize; 3] {
+        let first = triangle * 3;
+        if self.is_indexed() {
+            [self.indices[first], self.indices[first + 1], self.indices[first + 2]].map(|i| i as usize)
+        } else {
+            [first, first + 1, first + 2]
+        }
+    }
+
+    pub fn triangles(&self) -> impl Iterator<Item = [usize; 3]> + '_ {
+        (0..self.triangle_count()).map(|triangle| self.triangle(triangle))
+    }
+
+    // Convierte la geometría sin índices a indexada con índices 0, 1, 2, ...
+    fn ensure_indexed(&mut self) {
+        if !self.is_indexed() {
+            let count = self.triangle_count() * 3;
+            self.indices = (0..count as u32).collect();
+        }
+    }
+
+    // Duplica los vértices compartidos para que cada triángulo tenga los suyos
+    pub fn unindex(&mut self) {
+        if !self.is_indexed() {
+            return;
+        }
+        self.vertices = self.indices.iter().map(|&i| self.vertices[i as usize].clone()).collect();
+        self.indices.clear();
+        for group in &mut self.groups {
+            group.vertices = group.triangles.start * 3..group.triangles.end * 3;
+        }
+        self.invalidate_bounds();
+    }
+
+    // Anexa los vértices y grupos de `other` con `transform` aplicado a posiciones y normales
+    pub fn merge(&mut self, other: &Model3D, transform: &Mat4) {
+        // Solo si ambos carecen de índices el resultado puede seguir sin ellos
+        let indexed = self.is_indexed() || other.is_indexed();
+        if indexed {
+            self.ensure_indexed();
+        }
+        let base = self.vertices.len();
+        let first_triangle = self.triangle_count();
+
+        // Las normales usan la inversa transpuesta para soportar escalas no uniformes
+        let normal_matrix = nalgebra_glm::mat4_to_mat3(transform)
+            .try_inverse()
+            .map(|inverse| inverse.transpose())
+            .unwrap_or_else(Mat3::identity);
+        self.vertices.extend(other.vertices.iter().map(|vertex| {
+            let position = transform * nalgebra_glm::vec4(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
+            let normal = normal_matrix * vertex.normal;
+            Vertex {
+                position: Vec3::new(position.x, position.y, position.z),
+                normal: normal.try_normalize(f32::EPSILON).unwrap_or_else(Vec3::zeros),
+                ..vertex.clone()
+            }
+        }));
+
+        if indexed {
+            let offset = |index: usize| (base + index) as u32;
+            if other.is_indexed() {
+                self.indices.extend(other.indices.iter().map(|&index| offset(index as usize)));
+            } else {
+                self.indices.extend((0..other.vertices.len()).map(offset));
+            }
+        }
+
+        self.groups.extend(other.groups.iter().map(|group| Group {
+            name: group.name.clone(),
+            vertices: base + group.vertices.start..base + group.vertices.end,
+            triangles: first_triangle + group.triangles.start..first_triangle + group.triangles.end,
+        }));
+        self.invalidate_bounds();
+    }
+
+    // Carga un modelo eligiendo el formato por la extensión del archivo; `options`
+    // solo cambia algo en los .obj
+    pub fn load(filename: &str, options: &ImportOptions) -> Result<Self, LoadError> {
+        let extension = std::path::Path::new(filename)
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .unwrap_or("")
+            .to_ascii_lowercase();
+
+        match extension.as_str() {
+            "obj" => {
+                let obj = Obj::load(filename).map_err(LoadError::Obj)?;
+                let mut model = Model3D::new();
+                model.add_vertices_from_obj(&obj, options);
+                Ok(model)
+            }
+            "stl" => {
+                let mut model = Self::from_stl(filename).map_err(LoadError::Stl)?;
+                // Cada triángulo trae sus tres vértices: se unen los de una misma cara
+                // plana, que comparten posición y normal
+                let epsilon = model.bounding_box().map_or(0.0, |bounds| bounds.size().norm() * 1e-6);
+                model.weld(epsilon, WeldOptions { normals: true, tex_coords: false });
+                Ok(model)
+            }
+            "ply" => Self::from_ply(filename).map_err(LoadError::Ply),
+            "gltf" | "glb" => Self::from_gltf(filename).map_err(LoadError::Gltf),
+            _ => Err(LoadError::UnsupportedFormat(extension)),
+        }
+    }
+
+    // Construye un modelo a partir de un archivo .stl (binario o ASCII)
+    pub fn from_stl(filename: &str) -> Result<Self, StlError> {
+        let stl = Stl::load(filename)?;
+        let mut model = Model3D::new();
+        model.add_triangles_from_stl(&stl);
+        Ok(model)
+    }
+
+    pub fn add_triangles_from_stl(&mut self, stl: &Stl) {
+        self.ensure_indexed();
+        let color = Material::default().diffuse;
+        let base = self.vertices.len();
+        let first_triangle = self.triangle_count();
+
+        for triangle in &stl.triangles {
+            let [a, b, c] = triangle.vertices;
+            // Muchos exportadores escriben la normal de faceta como cero
+            let normal = if triangle.normal.norm() > f32::EPSILON {
+                triangle.normal.normalize()
+            } else {
+                triangle_normal(&a, &b, &c)
+            };
+            for position in triangle.vertices {
+                self.indices.push(self.vertices.len() as u32);
+                self.vertices.push(Vertex { color, ..Vertex::new(position, normal, Vec2::zeros()) });
+            }
+        }
+
+        self.groups.push(Group {
+            name: stl.name.clone(),
+            vertices: base..self.vertices.len(),
+            triangles: first_triangle..self.triangle_count(),
+        });
+        self.invalidate_bounds();
+    }
+
+    // Construye un modelo a partir de un archivo .ply (ASCII o binario, little o big endian)
+    pub fn from_ply(filename: &str) -> Result<Self, PlyError> {
+        let ply = Ply::load(filename)?;
+        let mut model = Model3D::new();
+        model.add_vertices_from_ply(&ply)?;
+        Ok(model)
+    }
+
+    pub fn add_vertices_from_ply(&mut self, ply: &Ply) -> Result<(), PlyError> {
+        self.ensure_indexed();
+        let default_color = Material::default().diffuse;
+        let base = self.vertices.len();
+        let first_triangle = self.triangle_count();
+
+        for (index, face) in ply.faces.iter().enumerate() {
+            if face.len() < 3 {
+                return Err(PlyError::InvalidData {
+                    element: String::from("face"),
+                    index,
+                    reason: format!("una cara necesita al menos 3 vértices y tiene {}", face.len()),
+                });
+            }
+            if let Some(&vertex) = face.iter().find(|&&vertex| vertex as usize >= ply.vertices.len()) {
+                return Err(PlyError::InvalidData {
+                    element: String::from("face"),
+                    index,
+                    reason: format!("índice de vértice fuera de rango {}", vertex),
+                });
+            }
+            // Caras con más de tres vértices se dividen en abanico
+            for pair in face[1..].windows(2) {
+                self.indices.extend([face[0], pair[0], pair[1]].map(|vertex| base as u32 + vertex));
+            }
+        }
+
+        let has_normals = ply.vertices.iter().all(|vertex| vertex.normal.is_some());
+        self.vertices.extend(ply.vertices.iter().map(|vertex| Vertex {
+            color: vertex.color.unwrap_or(default_color),
+            ..Vertex::new(vertex.position, vertex.normal.unwrap_or_else(Vec3::zeros), Vec2::zeros())
+        }));
+
+        self.groups.push(Group {
+            name: String::from("default"),
+            vertices: base..self.vertices.len(),
+            triangles: first_triangle..self.triangle_count(),
+        });
+        self.invalidate_bounds();
+
+        // Las nubes de fotogrametría rara vez traen normales
+        if !has_normals {
+            self.smooth_normals_in(first_triangle..self.triangle_count(), base..self.vertices.len());
+        }
+        Ok(())
+    }
+
+    pub fn from_gltf(filename: &str) -> Result<Self, GltfError> {
+        let gltf = Gltf::load(filename)?;
+        let mut model = Model3D::new();
+        model.add_primitives_from_gltf(&gltf);
+        Ok(model)
+    }
+
+    // Cada primitiva se agrega como un grupo con el nombre de su malla
+    pub fn add_primitives_from_gltf(&mut self, gltf: &Gltf) {
+        self.ensure_indexed();
+        let default_color = Material::default().diffuse;
+
+        for primitive in &gltf.primitives {
+            let base = self.vertices.len();
+            let first_triangle = self.triangle_count();
+            let color = primitive.color.unwrap_or(default_color);
+
+            self.vertices.extend(primitive.positions.iter().enumerate().map(|(i, &position)| {
+                let normal = primitive.normals.as_ref().and_then(|normals| normals.get(i).copied());
+                let tex_coords = primitive.tex_coords.as_ref().and_then(|tex_coords| tex_coords.get(i).copied());
+                Vertex {
+                    color,
+                    ..Vertex::new(position, normal.unwrap_or_else(Vec3::zeros), tex_coords.unwrap_or_else(Vec2::zeros))
+                }
+            }));
+            // Un resto de índices que no completa un triángulo se descarta
+            let triangles = primitive.indices.len() / 3 * 3;
+            self.indices.extend(primitive.indices[..triangles].iter().map(|&index| base as u32 + index));
+
+            // Solo la primitiva sin NORMAL: las demás conservan las que trajo el archivo
+            if primitive.normals.is_none() {
+                self.smooth_normals_in(first_triangle..self.triangle_count(), base..self.vertices.len());
+            }
+
+            self.groups.push(Group {
+                name: primitive.name.clone(),
+                vertices: base..self.vertices.len(),
+                triangles: first_triangle..self.triangle_count(),
+            });
+        }
+        self.invalidate_bounds();
+    }
+
+    // Modelo de un solo grupo con la geometría indexada ya armada
+    fn from_indexed(name: &str, vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+        let mut model = Model3D::new();
+        let color = Material::default().diffuse;
+        model.vertices = vertices.into_iter().map(|vertex| Vertex { color, ..vertex }).collect();
+        model.indices = indices;
+        model.groups.push(Group {
+            name: name.to_string(),
+            vertices: 0..model.vertices.len(),
+            triangles: 0..model.triangle_count(),
+        });
+        model
+    }
+
+    // Cubo centrado en el origen; cada cara tiene sus propios vértices para que
+    // las normales de las aristas no se mezclen
+    pub fn cube(size: f32) -> Self {
+        let half = size * 0.5;
+        // (normal, u, v) con u x v = normal, así el orden de las esquinas es antihorario visto desde fuera
+        let faces = [
+            (Vec3::x(), -Vec3::z(), Vec3::y()),
+            (-Vec3::x(), Vec3::z(), Vec3::y()),
+            (Vec3::y(), Vec3::x(), -Vec3::z()),
+            (-Vec3::y(), Vec3::x(), Vec3::z()),
+            (Vec3::z(), Vec3::x(), Vec3::y()),
+            (-Vec3::z(), -Vec3::x(), Vec3::y()),
+        ];
+
+        let mut vertices = Vec::with_capacity(24);
+        let mut indices = Vec::with_capacity(36);
+        for (normal, u, v) in faces {
+            let base = vertices.len() as u32;
+            for (s, t) in [(-1.0, -1.0), (1.0, -1.0), (1.0, 1.0), (-1.0, 1.0)] {
+                let position = (normal + u * s + v * t) * half;
+                vertices.push(Vertex::new(position, normal, Vec2::new((s + 1.0) * 0.5, (t + 1.0) * 0.5)));
+            }
+            indices.extend([0, 1, 2, 0, 2, 3].map(|i| base + i));
+        }
+        Self::from_indexed("cube", vertices, indices)
+    }
+
+    // Plano en XY mirando hacia +Z, dividido en `subdivisions` x `subdivisions` celdas
+    pub fn plane(width: f32, height: f32, subdivisions: usize) -> Self {
+        let cells = subdivisions.max(1);
+        let mut vertices = Vec::with_capacity((cells + 1) * (cells + 1));
+        for j in 0..=cells {
+            for i in 0..=cells {
+                let (u, v) = (i as f32 / cells as f32, j as f32 / cells as f32);
+                let position = Vec3::new((u - 0.5) * width, (v - 0.5) * height, 0.0);
+                vertices.push(Vertex::new(position, Vec3::z(), Vec2::new(u, v)));
+            }
+        }
+
+        let row = cells as u32 + 1;
+        let mut indices = Vec::with_capacity(cells * cells * 6);
+        for j in 0..cells as u32 {
+            for i in 0..cells as u32 {
+                let a = j * row + i;
+                let (b, c, d) = (a + 1, a + row + 1, a + row);
+                indices.extend([a, b, c, a, c, d]);
+            }
+        }
+        Self::from_indexed("plane", vertices, indices)
+    }
+
+    // Esfera UV con `rings` bandas de latitud y `segments` de longitud. La costura
+    // en u = 1 duplica vértices para que las UVs no den la vuelta hacia atrás
+    pub fn uv_sphere(radius: f32, rings: usize, segments: usize) -> Self {
+        let (rings, segments) = (rings.max(2), segments.max(3));
+        let mut vertices = Vec::with_capacity((rings + 1) * (segments + 1));
+        for ring in 0..=rings {
+            let phi = std::f32::consts::PI * ring as f32 / rings as f32;
+            for segment in 0..=segments {
+                let theta = std::f32::consts::TAU * segment as f32 / segments as f32;
+                let normal = Vec3::new(phi.sin() * theta.cos(), phi.cos(), -phi.sin() * theta.sin());
+                let tex_coords = Vec2::new(segment as f32 / segments as f32, 1.0 - ring as f32 / rings as f32);
+                vertices.push(Vertex::new(normal * radius, normal, tex_coords));
+            }
+        }
+
+        let row = segments as u32 + 1;
+        let mut indices = Vec::with_capacity(rings * segments * 6);
+        for ring in 0..rings as u32 {
+            for segment in 0..segments as u32 {
+                let a = ring * row + segment;
+                let (b, c, d) = (a + row, a + row + 1, a + 1);
+                // En los polos un lado del cuadrilátero colapsa a un punto:
+                // solo queda el triángulo que no es degenerado
+                if ring != rings as u32 - 1 {
+                    indices.extend([a, b, c]);
+                }
+                if ring != 0 {
+                    indices.extend([a, c, d]);
+                }
+            }
+        }
+        Self::from_indexed("uv_sphere", vertices, indices)
+    }
+
+    // Toro acostado en el plano XZ (eje Y), con el tubo de radio `minor_radius`
+    pub fn torus(major_radius: f32, minor_radius: f32, major_segments: usize, minor_segments: usize) -> Self {
+        let (major_segments, minor_segments) = (major_segments.max(3), minor_segments.max(3));
+        let mut vertices = Vec::with_capacity((major_segments + 1) * (minor_segments + 1));
+        for i in 0..=major_segments {
+            let theta = std::f32::consts::TAU * i as f32 / major_segments as f32;
+            let center = Vec3::new(theta.cos(), 0.0, -theta.sin()) * major_radius;
+            for j in 0..=minor_segments {
+                let phi = std::f32::consts::TAU * j as f32 / minor_segments as f32;
+                let normal = Vec3::new(phi.cos() * theta.cos(), phi.sin(), -phi.cos() * theta.sin());
+                let tex_coords = Vec2::new(i as f32 / major_segments as f32, j as f32 / minor_segments as f32);
+                vertices.push(Vertex::new(center + normal * minor_radius, normal, tex_coords));
+            }
+        }
+
+        let row = minor_segments as u32 + 1;
+        let mut indices = Vec::with_capacity(major_segments * minor_segments * 6);
+        for i in 0..major_segments as u32 {
+            for j in 0..minor_segments as u32 {
+                let a = i * row + j;
+                let (b, c, d) = (a + row, a + row + 1, a + 1);
+                indices.extend([a, b, c, a, c, d]);
+            }
+        }
+        Self::from_indexed("torus", vertices, indices)
+    }
+
+    // Cilindro centrado en el origen a lo largo de Y. Con `capped`, las tapas
+    // repiten los vértices del borde con su propia normal plana, separada de la
+    // normal curva del costado
+    pub fn cylinder(radius: f32, height: f32, segments: usize, capped: bool) -> Self {
+        let segments = segments.max(3);
+        let half = height * 0.5;
+        let mut vertices = Vec::new();
+        let mut indices = Vec::new();
+        let direction = |segment: usize| {
+            let theta = std::f32::consts::TAU * segment as f32 / segments as f32;
+            Vec3::new(theta.cos(), 0.0, -theta.sin())
+        };
+
+        // Costado: abajo y arriba por cada segmento, con la costura duplicada para las UVs
+        for segment in 0..=segments {
+            let normal = direction(segment);
+            let u = segment as f32 / segments as f32;
+            vertices.push(Vertex::new(normal * radius - Vec3::y() * half, normal, Vec2::new(u, 0.0)));
+            vertices.push(Vertex::new(normal * radius + Vec3::y() * half, normal, Vec2::new(u, 1.0)));
+        }
+        for segment in 0..segments as u32 {
+            let a = segment * 2;
+            let (b, c, d) = (a + 2, a + 3, a + 1);
+            indices.extend([a, b, c, a, c, d]);
+        }
+
+        if capped {
+            for normal in [Vec3::y(), -Vec3::y()] {
+                let center = vertices.len() as u32;
+                vertices.push(Vertex::new(normal * half, normal, Vec2::new(0.5, 0.5)));
+                for segment in 0..segments {
+                    let rim = direction(segment);
+                    let tex_coords = Vec2::new((rim.x + 1.0) * 0.5, (1.0 - rim.z) * 0.5);
+                    vertices.push(Vertex::new(rim * radius + normal * half, normal, tex_coords));
+                }
+                for segment in 0..segments as u32 {
+                    let current = center + 1 + segment;
+                    let next = center + 1 + (segment + 1) % segments as u32;
+                    // La tapa inferior se recorre al revés para que mire hacia -Y
+                    if normal.y > 0.0 {
+                        indices.extend([center, current, next]);
+                    } else {
+                        indices.extend([center, next, current]);
+                    }
+                }
+            }
+        }
+        Self::from_indexed("cylinder", vertices, indices)
+    }
+
+    // Caja envolvente de todas las posiciones (None si el modelo está vacío)
+    pub fn bounding_box(&self) -> Option<Aabb> {
+        if let Some(bounds) = self.bounds_cache.get() {
+            return bounds;
+        }
+        let bounds = Aabb::from_points(self.vertices.iter().map(|v| &v.position));
+        self.bounds_cache.set(Some(bounds));
+        bounds
+    }
+
+    // Caja envolvente del modelo después de aplicar `matrix`
+    pub fn transformed_bounding_box(&self, matrix: &Mat4) -> Option<Aabb> {
+        self.bounding_box().map(|bounds| bounds.transformed(matrix))
+    }
+
+    // Debe llamarse tras modificar `vertices` directamente
+    pub fn invalidate_bounds(&self) {
+        self.bounds_cache.set(None);
+    }
+
+    // Centra el modelo en el origen y lo escala para que su mayor dimensión mida 1
+    pub fn normalize(&mut self) -> Option<Normalization> {
+        self.normalize_to(1.0)
+    }
+
+    // Igual que `normalize`, pero con la mayor dimensión igual a `size`
+    pub fn normalize_to(&mut self, size: f32) -> Option<Normalization> {
+        let bounds = self.bounding_box()?;
+        let largest = bounds.size().max();
+        let translation = -bounds.center();
+        // Un modelo plano en un punto no tiene tamaño que escalar
+        let scale = if largest > f32::EPSILON { size / largest } else { 1.0 };
+
+        for vertex in &mut self.vertices {
+            vertex.position = (vertex.position + translation) * scale;
+            vertex.transformed_position = vertex.position;
+        }
+        self.invalidate_bounds();
+
+        Some(Normalization { original_bounds: bounds, scale })
+    }
+
+    // Une los vértices cuya posición (y lo que pida `options`) difiere menos de
+    // `epsilon` y devuelve cuántos se eliminaron. El resultado queda indexado
+    pub fn weld(&mut self, epsilon: f32, options: WeldOptions) -> usize {
+        self.ensure_indexed();
+        let cell_size = epsilon.max(1e-12);
+        let cell = |position: &Vec3| {
+            [position.x, position.y, position.z].map(|component| (component / cell_size).floor() as i64)
+        };
+        // Los colores y el brillo vienen del material, así que nunca se sueldan vértices de materiales distintos
+        let same = |a: &Vertex, b: &Vertex| {
+            (a.position - b.position).norm() <= epsilon
+                && (!options.normals || (a.normal - b.normal).norm() <= epsilon)
+                && (!options.tex_coords || (a.tex_coords - b.tex_coords).norm() <= epsilon)
+                && a.color.to_hex() == b.color.to_hex()
+                && a.ambient.to_hex() == b.ambient.to_hex()
+                && a.emissive.to_hex() == b.emissive.to_hex()
+                && a.specular.to_hex() == b.specular.to_hex()
+                && a.shininess == b.shininess
+        };
+
+        // Se suelda dentro de cada grupo para que sus rangos de vértices sigan siendo contiguos
+        let mut boundaries: Vec<usize> = self
+            .groups
+            .iter()
+            .flat_map(|group| [group.vertices.start, group.vertices.end])
+            .chain([0, self.vertices.len()])
+            .collect();
+        boundaries.sort_unstable();
+        boundaries.dedup();
+
+        let mut welded: Vec<Vertex> = Vec::with_capacity(self.vertices.len());
+        let mut remap = vec![0u32; self.vertices.len()];
+        let mut new_boundaries = HashMap::new();
+        for segment in boundaries.windows(2) {
+            new_boundaries.insert(segment[0], welded.len());
+            // Celda -> vértices ya soldados en ella; recorrer en orden da un resultado estable
+            let mut grid: HashMap<[i64; 3], Vec<u32>> = HashMap::new();
+            let range = segment[0]..segment[1];
+            for (vertex, slot) in self.vertices[range.clone()].iter().zip(&mut remap[range]) {
+                let [x, y, z] = cell(&vertex.position);
+                let existing = (-1..=1)
+                    .flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| [x + dx, y + dy, z + dz])))
+                    .filter_map(|key| grid.get(&key))
+                    .flatten()
+                    .copied()
+                    .filter(|&candidate| same(&welded[candidate as usize], vertex))
+                    .min();
+
+                *slot = existing.unwrap_or_else(|| {
+                    let new_index = welded.len() as u32;
+                    welded.push(vertex.clone());
+                    grid.entry([x, y, z]).or_default().push(new_index);
+                    new_index
+                });
+            }
+        }
+        new_boundaries.insert(self.vertices.len(), welded.len());
+
+        let removed = self.vertices.len() - welded.len();
+        for index in &mut self.indices {
+            *index = remap[*index as usize];
+        }
+        for group in &mut self.groups {
+            group.vertices = new_boundaries[&group.vertices.start]..new_boundaries[&group.vertices.end];
+        }
+        self.vertices = welded;
+        self.invalidate_bounds();
+        removed
+    }
+
+    // Normales planas: los tres vértices de cada triángulo comparten la normal
+    // geométrica de la cara (ideal para superficies duras como la nave)
+    pub fn compute_flat_normals(&mut self) {
+        // Un vértice compartido no puede tener la normal de dos caras distintas
+        self.unindex();
+        for triangle in self.vertices.chunks_exact_mut(3) {
+            let normal = triangle_normal(&triangle[0].position, &triangle[1].position, &triangle[2].position);
+            for vertex in triangle {
+                vertex.normal = normal;
+            }
+        }
+    }
+
+    // Normales suaves: cada vértice recibe la suma normalizada de las normales
+    // de todas las caras que comparten su posición, ponderadas por área
+    pub fn compute_smooth_normals(&mut self) {
+        self.smooth_normals_in(0..self.triangle_count(), 0..self.vertices.len());
+    }
+
+    // Como `compute_smooth_normals`, pero solo con los triángulos `triangles` y solo
+    // cambia los vértices `vertices`: al importar una parte sin normales no se
+    // pisan las del resto del modelo
+    fn smooth_normals_in(&mut self, triangles: Range<usize>, vertices: Range<usize>) {
+        let mut accumulated: HashMap<[i64; 3], Vec3> = HashMap::new();
+
+        for triangle in triangles.map(|triangle| self.triangle(triangle)) {
+            let [a, b, c] = triangle.map(|i| self.vertices[i].position);
+            // La magnitud del producto cruz es el doble del área: así pesa más la cara grande
+            let weighted_normal = (b - a).cross(&(c - a));
+            for position in [a, b, c] {
+                *accumulated.entry(position_key(&position)).or_insert_with(Vec3::zeros) += weighted_normal;
+            }
+        }
+
+        for vertex in &mut self.vertices[vertices] {
+            let sum = accumulated.get(&position_key(&vertex.position)).copied().unwrap_or_else(Vec3::zeros);
+            vertex.normal = if sum.norm() > f32::EPSILON { sum.normalize() } else { Vec3::zeros() };
+        }
+    }
+
+    // Cambia el material de todo el modelo, por ejemplo el de un modelo que no trae .mtl
+    pub fn set_material(&mut self, material: &Material) {
+        self.vertices.iter_mut().for_each(|vertex| apply_material(vertex, material));
+    }
+
+    pub fn groups(&self) -> &[Group] {
+        &self.groups
+    }
+
+    pub fn model_matrix(&self) -> Mat4 {
+        self.transform.matrix()
+    }
+
+    pub fn set_position(&mut self, position: Vec3) {
+        self.transform.position = position;
+    }
+
+    pub fn set_rotation(&mut self, rotation: Vec3) {
+        self.transform.rotation = rotation;
+    }
+
+    pub fn set_scale(&mut self, scale: f32) {
+        self.transform.scale = Vec3::new(scale, scale, scale);
+    }
+
+    pub fn rotate_y(&mut self, angle: f32) {
+        self.transform.rotation.y += angle;
+    }
+
+    pub fn stats(&self) -> MeshStats {
+        let extents = self.bounding_box().map_or_else(Vec3::zeros, |bounds| bounds.size());
+        // El umbral escala con el modelo: una nave de 1000 unidades y otra de 0.01
+        // deben dar el mismo conteo
+        let area_epsilon = DEGENERATE_AREA_EPSILON * extents.norm_squared();
+        let degenerate_triangles = self
+            .triangles()
+            .filter(|&triangle| {
+                let [a, b, c] = triangle.map(|i| self.vertices[i].position);
+                (b - a).cross(&(c - a)).norm() * 0.5 <= area_epsilon
+            })
+            .count();
+
+        MeshStats {
+            vertex_count: self.vertices.len(),
+            triangle_count: self.triangle_count(),
+            degenerate_triangles,
+            extents,
+            has_normals: self.vertices.iter().any(|vertex| vertex.normal != Vec3::zeros()),
+            has_tex_coords: self.vertices.iter().any(|vertex| vertex.tex_coords != Vec2::zeros()),
+        }
+    }
+}
+
+// Cuantiza una posición para que los duplicados que emite el cargador
+// (mismas coordenadas repetidas por cara) caigan en la misma celda
+fn position_key(position: &Vec3) -> [i64; 3] {
+    [
+        (position.x / POSITION_EPSILON).round() as i64,
+        (position.y / POSITION_EPSILON).round() as i64,
+        (position.z / POSITION_EPSILON).round() as i64,
+    ]
+}
+
+// Normal unitaria de un triángulo; los degenerados devuelven cero en vez de NaN
+fn triangle_normal(a: &Vec3, b: &Vec3, c: &Vec3) -> Vec3 {
+    let cross = (b - a).cross(&(c - a));
+    let longest_edge = (b - a).norm_squared().max((c - a).norm_squared()).max((c - b).norm_squared());
+
+    if cross.norm() <= DEGENERATE_EPSILON * longest_edge || longest_edge == 0.0 {
+        Vec3::zeros()
+    } else {
+        cross.normalize()
+    }
+}
+
+// Copia al vértice lo que el material dice de cómo refleja y emite la luz
+fn apply_material(vertex: &mut Vertex, material: &Material) {
+    vertex.color = material.diffuse;
+    vertex.ambient = material.ambient;
+    vertex.specular = material.specular;
+    vertex.shininess = material.shininess;
+    vertex.emissive = material.emissive;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::gltf::GltfPrimitive;
+    use crate::ply::PlyVertex;
+
+    // Triángulos del modelo como posiciones, para comparar antes y después de soldar
+    fn triangle_positions(model: &Model3D) -> Vec<[Vec3; 3]> {
+        model.triangles().map(|triangle| triangle.map(|i| model.vertices[i].position)).collect()
+    }
+
+    fn unindexed_cube() -> Model3D {
+        let mut cube = Model3D::cube(1.0);
+        cube.unindex();
+        assert_eq!(cube.vertices.len(), 36);
+        cube
+    }
+
+    // Normales de largo 1 y caras en sentido antihorario vistas desde afuera: la
+    // normal geométrica de cada triángulo va hacia el mismo lado que las de sus vértices
+    fn assert_unit_normals_and_ccw(model: &Model3D) {
+        for vertex in &model.vertices {
+            assert!((vertex.normal.magnitude() - 1.0).abs() < 1e-5, "{:?} en {:?}", vertex.normal, vertex.position);
+        }
+        for triangle in model.triangles() {
+            let [a, b, c] = triangle.map(|i| &model.vertices[i]);
+            let face = (b.position - a.position).cross(&(c.position - a.position));
+            assert!(face.norm() > 0.0, "triángulo degenerado {:?}", triangle);
+            for vertex in [a, b, c] {
+                assert!(face.dot(&vertex.normal) > 0.0, "{:?} en {:?}", triangle, vertex.position);
+            }
+        }
+    }
+
+    #[test]
+    fn cube_has_twelve_outward_triangles() {
+        let cube = Model3D::cube(2.0);
+        assert_eq!((cube.vertices.len(), cube.triangle_count()), (24, 12));
+        assert_unit_normals_and_ccw(&cube);
+    }
+
+    #[test]
+    fn plane_has_two_triangles_per_cell_facing_z() {
+        for subdivisions in [1, 3, 8] {
+            let plane = Model3D::plane(2.0, 1.0, subdivisions);
+            assert_eq!(plane.vertices.len(), (subdivisions + 1) * (subdivisions + 1));
+            assert_eq!(plane.triangle_count(), subdivisions * subdivisions * 2);
+            assert_unit_normals_and_ccw(&plane);
+            assert!(plane.vertices.iter().all(|vertex| vertex.normal == Vec3::z()));
+        }
+    }
+
+    #[test]
+    fn sphere_has_one_triangle_per_segment_at_each_pole() {
+        for (rings, segments) in [(2, 3), (6, 8), (16, 32)] {
+            let sphere = Model3D::uv_sphere(1.5, rings, segments);
+            assert_eq!(sphere.vertices.len(), (rings + 1) * (segments + 1));
+            assert_eq!(sphere.triangle_count(), 2 * segments * (rings - 1));
+            assert_unit_normals_and_ccw(&sphere);
+
+            // La primera y la última fila de vértices son los polos
+            let row = segments + 1;
+            let touching = |pole: std::ops::Range<usize>| sphere.triangles().filter(|triangle| triangle.iter().any(|i| pole.contains(i))).count();
+            assert_eq!(touching(0..row), segments);
+            assert_eq!(touching(rings * row..(rings + 1) * row), segments);
+            for vertex in &sphere.vertices[..row] {
+                assert!((vertex.normal - Vec3::y()).norm() < 1e-6);
+            }
+            for vertex in &sphere.vertices[rings * row..] {
+                assert!((vertex.normal + Vec3::y()).norm() < 1e-6);
+            }
+        }
+    }
+
+    #[test]
+    fn cylinder_rim_has_a_flat_cap_copy_and_a_radial_side_copy() {
+        let (radius, height, segments) = (0.5, 2.0, 12);
+        let cylinder = Model3D::cylinder(radius, height, segments, true);
+        // Costado con la costura repetida, más centro y borde de cada tapa
+        assert_eq!(cylinder.vertices.len(), (segments + 1) * 2 + (segments + 1) * 2);
+        assert_eq!(cylinder.triangle_count(), segments * 4);
+        assert_unit_normals_and_ccw(&cylinder);
+
+        // Lejos de la costura en U, donde el costado tiene otra copia más
+        for (position, cap) in [(Vec3::new(0.0, 1.0, -radius), Vec3::y()), (Vec3::new(0.0, -1.0, radius), -Vec3::y())] {
+            let copies: Vec<&Vertex> = cylinder.vertices.iter().filter(|vertex| (vertex.position - position).norm() < 1e-5).collect();
+            assert_eq!(copies.len(), 2, "{:?}", position);
+            let (caps, sides): (Vec<&Vertex>, Vec<&Vertex>) = copies.into_iter().partition(|vertex| vertex.normal.y != 0.0);
+            assert_eq!((caps.len(), sides.len()), (1, 1));
+            assert_eq!(caps[0].normal, cap);
+            let radial = Vec3::new(position.x, 0.0, position.z) / radius;
+            assert_eq!(sides[0].normal.y, 0.0);
+            assert!((sides[0].normal - radial).norm() < 1e-5, "{:?}", sides[0].normal);
+        }
+
+        let open = Model3D::cylinder(radius, height, segments, false);
+        assert_eq!(open.triangle_count(), segments * 2);
+        assert!(open.vertices.iter().all(|vertex| vertex.normal.y == 0.0));
+    }
+
+    #[test]
+    fn torus_normals_point_away_from_the_tube_center() {
+        let (major, minor) = (1.0, 0.25);
+        let torus = Model3D::torus(major, minor, 16, 8);
+        assert_eq!(torus.triangle_count(), 16 * 8 * 2);
+        assert_unit_normals_and_ccw(&torus);
+        for vertex in &torus.vertices {
+            // Centro del tubo en el anillo de radio `major`, a la altura del plano XZ
+            let p = vertex.position;
+            let center = Vec3::new(p.x, 0.0, p.z).normalize() * major;
+            let outward = (p - center) / minor;
+            assert!((outward.norm() - 1.0).abs() < 1e-5);
+            assert!((vertex.normal - outward).norm() < 1e-5, "{:?} en {:?}", vertex.normal, p);
+        }
+    }
+
+    #[test]
+    fn weld_leaves_the_eight_corners_of_a_cube() {
+        let mut cube = unindexed_cube();
+        let before = triangle_positions(&cube);
+
+        assert_eq!(cube.weld(1e-4, WeldOptions::default()), 28);
+        assert_eq!(cube.vertices.len(), 8);
+        assert_eq!(cube.indices.len(), 36);
+        assert_eq!(triangle_positions(&cube), before);
+        assert_eq!(cube.groups[0].vertices, 0..8);
+    }
+
+    #[test]
+    fn weld_with_normals_keeps_one_corner_per_face() {
+        let mut cube = unindexed_cube();
+        let before = triangle_positions(&cube);
+
+        assert_eq!(cube.weld(1e-4, WeldOptions { normals: true, tex_coords: false }), 12);
+        assert_eq!(cube.vertices.len(), 24);
+        assert_eq!(triangle_positions(&cube), before);
+    }
+
+    #[test]
+    fn weld_joins_vertices_within_epsilon_only() {
+        let mut cube = unindexed_cube();
+        cube.vertices[0].position += Vec3::new(1e-5, 0.0, 0.0);
+        assert_eq!(cube.weld(1e-4, WeldOptions::default()), 28);
+
+        let mut cube = unindexed_cube();
+        cube.vertices[0].position += Vec3::new(1e-2, 0.0, 0.0);
+        cube.weld(1e-4, WeldOptions::default());
+        assert_eq!(cube.vertices.len(), 9);
+    }
+
+    #[test]
+    fn weld_does_not_join_vertices_of_different_groups() {
+        let mut model = unindexed_cube();
+        model.merge(&unindexed_cube(), &Mat4::identity());
+        model.weld(1e-4, WeldOptions::default());
+        assert_eq!(model.vertices.len(), 16);
+        assert_eq!(model.groups.iter().map(|group| group.vertices.clone()).collect::<Vec<_>>(), [0..8, 8..16]);
+        assert!(model.triangles().skip(12).flatten().all(|vertex| vertex >= 8));
+    }
+
+    fn moved(model: &Model3D, offset: Vec3) -> Vec<[Vec3; 3]> {
+        triangle_positions(model).into_iter().map(|triangle| triangle.map(|p| p + offset)).collect()
+    }
+
+    #[test]
+    fn merged_cubes_span_both_bounding_boxes() {
+        let (left, right) = (Vec3::new(-1.0, 0.0, 0.0), Vec3::new(2.0, 1.0, 0.0));
+        let mut model = Model3D::new();
+        model.merge(&Model3D::cube(1.0), &Mat4::new_translation(&left));
+        model.merge(&Model3D::cube(1.0), &Mat4::new_translation(&right));
+
+        let bounds = model.bounding_box().unwrap();
+        assert_eq!((bounds.min, bounds.max), (Vec3::new(-1.5, -0.5, -0.5), Vec3::new(2.5, 1.5, 0.5)));
+        assert_eq!((model.vertices.len(), model.triangle_count()), (48, 24));
+        assert_eq!(model.groups.iter().map(|group| group.triangles.clone()).collect::<Vec<_>>(), [0..12, 12..24]);
+    }
+
+    #[test]
+    fn merging_indexed_and_unindexed_keeps_every_triangle() {
+        let offset = Vec3::new(0.0, 0.0, 3.0);
+        let (indexed, unindexed) = (Model3D::cube(1.0), unindexed_cube());
+        for (first, second) in [(&indexed, &unindexed), (&unindexed, &indexed)] {
+            let mut model = Model3D::new();
+            model.merge(first, &Mat4::identity());
+            assert_eq!(model.is_indexed(), first.is_indexed());
+            model.merge(second, &Mat4::new_translation(&offset));
+            assert!(model.is_indexed());
+            assert_eq!(model.vertices.len(), first.vertices.len() + second.vertices.len());
+            let expected: Vec<[Vec3; 3]> = triangle_positions(first).into_iter().chain(moved(second, offset)).collect();
+            assert_eq!(triangle_positions(&model), expected);
+            assert_eq!(model.groups[1].vertices, first.vertices.len()..model.vertices.len());
+        }
+    }
+
+    // Un cubo ya cargado con todas sus normales apuntando a -Z, que nadie calcularía
+    fn cube_with_marked_normals() -> Model3D {
+        let mut cube = Model3D::cube(1.0);
+        cube.vertices.iter_mut().for_each(|vertex| vertex.normal = -Vec3::z());
+        cube
+    }
+
+    fn triangle_of(name: &str, normals: Option<Vec<Vec3>>) -> GltfPrimitive {
+        GltfPrimitive {
+            name: name.to_string(),
+            positions: vec![Vec3::new(0.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0), Vec3::new(0.0, 1.0, 0.0)],
+            normals,
+            tex_coords: None,
+            indices: vec![0, 1, 2],
+            color: None,
+        }
+    }
+
+    #[test]
+    fn gltf_only_smooths_the_primitives_without_normals() {
+        let mut model = cube_with_marked_normals();
+        let imported = vec![Vec3::x(); 3];
+        let gltf = Gltf { primitives: vec![triangle_of("con", Some(imported.clone())), triangle_of("sin", None)] };
+        model.add_primitives_from_gltf(&gltf);
+
+        assert!(model.vertices[..24].iter().all(|vertex| vertex.normal == -Vec3::z()));
+        assert_eq!(model.vertices[24..27].iter().map(|vertex| vertex.normal).collect::<Vec<_>>(), imported);
+        // La del triángulo en XY, antihorario visto desde +Z
+        assert!(model.vertices[27..].iter().all(|vertex| (vertex.normal - Vec3::z()).norm() < 1e-6));
+    }
+
+    #[test]
+    fn obj_without_normals_only_smooths_its_own_faces() {
+        let mut model = cube_with_marked_normals();
+        let obj = Obj::parse("v 0 0 0\nv 1 0 0\nv 0 1 0\nf 1 2 3\n", None).unwrap();
+        model.add_vertices_from_obj(&obj, &ImportOptions { smooth_missing_normals: true, ..ImportOptions::default() });
+
+        assert!(model.vertices[..24].iter().all(|vertex| vertex.normal == -Vec3::z()));
+        assert_eq!(model.vertices.len(), 27);
+        assert!(model.vertices[24..].iter().all(|vertex| (vertex.normal - Vec3::z()).norm() < 1e-6));
+    }
+
+    #[test]
+    fn ply_without_normals_only_smooths_its_own_faces() {
+        let mut model = cube_with_marked_normals();
+        let vertex = |x, y| PlyVertex { position: Vec3::new(x, y, 0.5), normal: None, color: None };
+        // Sobre la cara +Z del cubo: comparten posiciones con sus esquinas
+        let ply = Ply { vertices: vec![vertex(-0.5, -0.5), vertex(0.5, -0.5), vertex(0.5, 0.5)], faces: vec![vec![0, 1, 2]] };
+        model.add_vertices_from_ply(&ply).unwrap();
+
+        assert!(model.vertices[..24].iter().all(|vertex| vertex.normal == -Vec3::z()));
+        assert!(model.vertices[24..].iter().all(|vertex| (vertex.normal - Vec3::z()).norm() < 1e-6));
+    }
+
+    #[test]
+    fn flat_normals_of_a_cube_are_its_face_axes() {
+        let mut cube = Model3D::cube(2.0);
+        cube.vertices.iter_mut().for_each(|vertex| vertex.normal = Vec3::zeros());
+        cube.compute_flat_normals();
+
+        assert!(!cube.is_indexed());
+        assert_eq!(cube.vertices.len(), 36);
+        for triangle in cube.vertices.chunks_exact(3) {
+            let normal = triangle[0].normal;
+            assert!(triangle.iter().all(|vertex| vertex.normal == normal));
+            // Un eje, hacia afuera: la cara está en la coordenada ±1 de ese eje
+            assert_eq!(normal.abs().sum(), 1.0);
+            assert!(triangle.iter().all(|vertex| vertex.position.dot(&normal) == 1.0));
+        }
+    }
+
+    #[test]
+    fn smooth_normals_of_a_cube_point_out_of_its_corners() {
+        let mut cube = Model3D::cube(2.0);
+        cube.compute_smooth_normals();
+        for vertex in &cube.vertices {
+            assert!((vertex.normal.norm() - 1.0).abs() < 1e-6);
+            // Las esquinas mezclan las tres caras: cada componente sigue el signo de la posición
+            for axis in 0..3 {
+                assert!(vertex.normal[axis] * vertex.position[axis] > 0.0, "{:?}", vertex.position);
+            }
+        }
+    }
+
+    #[test]
+    fn smooth_normals_of_a_sphere_are_parallel_to_the_positions() {
+        let mut sphere = Model3D::uv_sphere(2.0, 16, 32);
+        sphere.vertices.iter_mut().for_each(|vertex| vertex.normal = Vec3::zeros());
+        sphere.compute_smooth_normals();
+        for vertex in &sphere.vertices {
+            let radial = vertex.position.normalize();
+            assert!(vertex.normal.dot(&radial) > 0.999, "{:?} en {:?}", vertex.normal, vertex.position);
         }
     }
 
-    pub fn add_vertices_from_obj(&mut self, obj: &crate::obj::Obj) {
-        for vertex in obj.get_vertex_array() {
-            self.vertices.push(vertex);
+    #[test]
+    fn flat_normals_of_a_sphere_face_outwards() {
+        let mut sphere = Model3D::uv_sphere(2.0, 16, 32);
+        sphere.compute_flat_normals();
+        for triangle in sphere.vertices.chunks_exact(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| triangle[i].position);
+            let normal = triangle[0].normal;
+            assert!((normal.norm() - 1.0).abs() < 1e-6);
+            assert!(normal.dot(&(b - a)).abs() < 1e-5 && normal.dot(&(c - a)).abs() < 1e-5);
+            assert!(normal.dot(&(a + b + c)) > 0.0);
         }
     }
 }
@@ -0,0 +1,244 @@
+// noise.rs
+// Ruido coherente para los shaders procedurales: valores que cambian suave de un
+// punto al siguiente pero sin repetirse a la vista. Todo sale de un hash de los
+// enteros de la grilla y la semilla, así que el mismo punto con la misma semilla da
+// siempre lo mismo, en cualquier hilo y en cualquier cuadro
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Noise {
+    pub seed: u32,
+}
+
+impl Noise {
+    pub fn new(seed: u32) -> Self {
+        Noise { seed }
+    }
+
+    // Ruido de valor: un número al azar en cada punto entero de la grilla, mezclados
+    // con una curva suave. Da valores en [0, 1]
+    pub fn value2(&self, x: f32, y: f32) -> f32 {
+        let (ix, iy) = (x.floor() as i32, y.floor() as i32);
+        let (fx, fy) = (x - ix as f32, y - iy as f32);
+        let corner = |dx: i32, dy: i32| unit(hash(self.seed, ix + dx, iy + dy, 0));
+        let (u, v) = (fade(fx), fade(fy));
+        lerp(lerp(corner(0, 0), corner(1, 0), u), lerp(corner(0, 1), corner(1, 1), u), v)
+    }
+
+    pub fn value3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (ix, iy, iz) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+        let (fx, fy, fz) = (x - ix as f32, y - iy as f32, z - iz as f32);
+        let corner = |dx: i32, dy: i32, dz: i32| unit(hash(self.seed, ix + dx, iy + dy, iz + dz));
+        let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+        let plane = |dz: i32| {
+            lerp(lerp(corner(0, 0, dz), corner(1, 0, dz), u), lerp(corner(0, 1, dz), corner(1, 1, dz), u), v)
+        };
+        lerp(plane(0), plane(1), w)
+    }
+
+    // Ruido de gradiente (Perlin): en cada punto de la grilla hay una dirección en vez
+    // de un valor, y cada esquina aporta cuánto se alejó el punto en esa dirección.
+    // Vale 0 sobre la grilla y no tiene los bloques que a veces deja el de valor.
+    // Da valores en [-1, 1]
+    pub fn perlin2(&self, x: f32, y: f32) -> f32 {
+        let (ix, iy) = (x.floor() as i32, y.floor() as i32);
+        let (fx, fy) = (x - ix as f32, y - iy as f32);
+        let corner = |dx: i32, dy: i32| {
+            let (gx, gy) = GRADIENTS_2D[(hash(self.seed, ix + dx, iy + dy, 0) & 7) as usize];
+            gx * (fx - dx as f32) + gy * (fy - dy as f32)
+        };
+        let (u, v) = (fade(fx), fade(fy));
+        // Con gradientes de largo 1 el máximo es √½: se lleva a [-1, 1]
+        let noise = lerp(lerp(corner(0, 0), corner(1, 0), u), lerp(corner(0, 1), corner(1, 1), u), v);
+        (noise * std::f32::consts::SQRT_2).clamp(-1.0, 1.0)
+    }
+
+    pub fn perlin3(&self, x: f32, y: f32, z: f32) -> f32 {
+        let (ix, iy, iz) = (x.floor() as i32, y.floor() as i32, z.floor() as i32);
+        let (fx, fy, fz) = (x - ix as f32, y - iy as f32, z - iz as f32);
+        let corner = |dx: i32, dy: i32, dz: i32| {
+            let (gx, gy, gz) = GRADIENTS_3D[(hash(self.seed, ix + dx, iy + dy, iz + dz) % 12) as usize];
+            gx * (fx - dx as f32) + gy * (fy - dy as f32) + gz * (fz - dz as f32)
+        };
+        let (u, v, w) = (fade(fx), fade(fy), fade(fz));
+        let plane = |dz: i32| {
+            lerp(lerp(corner(0, 0, dz), corner(1, 0, dz), u), lerp(corner(0, 1, dz), corner(1, 1, dz), u), v)
+        };
+        // Los gradientes de las aristas del cubo ya dejan el resultado cerca de [-1, 1]
+        lerp(plane(0), plane(1), w).clamp(-1.0, 1.0)
+    }
+}
+
+// Movimiento browniano fraccional: suma varias capas (octavas) de ruido de Perlin,
+// cada una `lacunarity` veces más fina y con `gain` veces la amplitud de la
+// anterior. Las capas finas agregan detalle sin cambiar la forma grande
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Fbm {
+    pub octaves: u32,
+    pub lacunarity: f32,
+    pub gain: f32,
+}
+
+impl Fbm {
+    // Divide por la suma de las amplitudes, así el resultado queda en [-1, 1] como el
+    // de una sola octava. Cada octava usa otra semilla para que no coincidan en el origen
+    pub fn sample2(&self, noise: &Noise, x: f32, y: f32) -> f32 {
+        self.sum(noise, |octave, frequency| octave.perlin2(x * frequency, y * frequency))
+    }
+
+    pub fn sample3(&self, noise: &Noise, x: f32, y: f32, z: f32) -> f32 {
+        self.sum(noise, |octave, frequency| octave.perlin3(x * frequency, y * frequency, z * frequency))
+    }
+
+    fn sum(&self, noise: &Noise, sample: impl Fn(&Noise, f32) -> f32) -> f32 {
+        let (mut total, mut weight) = (0.0, 0.0);
+        let (mut frequency, mut amplitude) = (1.0, 1.0);
+        for octave in 0..self.octaves {
+            total += amplitude * sample(&Noise::new(noise.seed.wrapping_add(octave)), frequency);
+            weight += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.gain;
+        }
+        if weight > 0.0 { total / weight } else { 0.0 }
+    }
+}
+
+impl Default for Fbm {
+    fn default() -> Self {
+        Fbm { octaves: 5, lacunarity: 2.0, gain: 0.5 }
+    }
+}
+
+// Ocho direcciones de largo 1 repartidas en el círculo
+const GRADIENTS_2D: [(f32, f32); 8] = [
+    (1.0, 0.0),
+    (-1.0, 0.0),
+    (0.0, 1.0),
+    (0.0, -1.0),
+    (std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2),
+    (-std::f32::consts::FRAC_1_SQRT_2, std::f32::consts::FRAC_1_SQRT_2),
+    (std::f32::consts::FRAC_1_SQRT_2, -std::f32::consts::FRAC_1_SQRT_2),
+    (-std::f32::consts::FRAC_1_SQRT_2, -std::f32::consts::FRAC_1_SQRT_2),
+];
+
+// Los centros de las doce aristas de un cubo, como en el Perlin mejorado
+const GRADIENTS_3D: [(f32, f32, f32); 12] = [
+    (1.0, 1.0, 0.0),
+    (-1.0, 1.0, 0.0),
+    (1.0, -1.0, 0.0),
+    (-1.0, -1.0, 0.0),
+    (1.0, 0.0, 1.0),
+    (-1.0, 0.0, 1.0),
+    (1.0, 0.0, -1.0),
+    (-1.0, 0.0, -1.0),
+    (0.0, 1.0, 1.0),
+    (0.0, -1.0, 1.0),
+    (0.0, 1.0, -1.0),
+    (0.0, -1.0, -1.0),
+];
+
+// Mezcla los enteros de la grilla con la semilla hasta que cambiar un bit cambia
+// la mitad del resultado (el final de MurmurHash3)
+fn hash(seed: u32, x: i32, y: i32, z: i32) -> u32 {
+    let mut h = seed.wrapping_mul(0x9E37_79B9)
+        ^ (x as u32).wrapping_mul(0x85EB_CA6B)
+        ^ (y as u32).wrapping_mul(0xC2B2_AE35)
+        ^ (z as u32).wrapping_mul(0x27D4_EB2F);
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x7FEB_352D);
+    h ^= h >> 15;
+    h = h.wrapping_mul(0x846C_A68B);
+    h ^= h >> 16;
+    h
+}
+
+// Los 24 bits altos del hash como un número en [0, 1]
+fn unit(hash: u32) -> f32 {
+    (hash >> 8) as f32 / ((1 << 24) - 1) as f32
+}
+
+// 6t⁵ - 15t⁴ + 10t³: pasa de 0 a 1 sin cambios bruscos de pendiente ni de curvatura
+// en la grilla
+fn fade(t: f32) -> f32 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f32, b: f32, t: f32) -> f32 {
+    a + (b - a) * t
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Puntos repartidos por la grilla, también en los negativos y lejos del origen
+    fn points() -> impl Iterator<Item = (f32, f32, f32)> {
+        (0..4000).map(|i| {
+            let i = i as f32;
+            ((i * 0.137).sin() * 300.0, (i * 0.071).cos() * 300.0 - 50.0, i * 0.013 - 20.0)
+        })
+    }
+
+    #[test]
+    fn same_seed_gives_the_same_noise() {
+        let (a, b, other) = (Noise::new(7), Noise::new(7), Noise::new(8));
+        let fbm = Fbm::default();
+        let mut different = 0;
+        for (x, y, z) in points() {
+            assert_eq!(a.value2(x, y).to_bits(), b.value2(x, y).to_bits());
+            assert_eq!(a.value3(x, y, z).to_bits(), b.value3(x, y, z).to_bits());
+            assert_eq!(a.perlin2(x, y).to_bits(), b.perlin2(x, y).to_bits());
+            assert_eq!(a.perlin3(x, y, z).to_bits(), b.perlin3(x, y, z).to_bits());
+            assert_eq!(fbm.sample3(&a, x, y, z).to_bits(), fbm.sample3(&b, x, y, z).to_bits());
+            different += (a.perlin3(x, y, z) != other.perlin3(x, y, z)) as usize;
+        }
+        // Otra semilla es otro ruido
+        assert!(different > 3500, "{}", different);
+    }
+
+    #[test]
+    fn noise_stays_in_its_documented_range() {
+        let noise = Noise::new(42);
+        let fbm = Fbm { octaves: 6, lacunarity: 2.3, gain: 0.6 };
+        let (mut low, mut high) = (f32::MAX, f32::MIN);
+        for (x, y, z) in points() {
+            for value in [noise.value2(x, y), noise.value3(x, y, z)] {
+                assert!((0.0..=1.0).contains(&value), "{} en {:?}", value, (x, y, z));
+            }
+            for value in [noise.perlin2(x, y), noise.perlin3(x, y, z), fbm.sample2(&noise, x, y), fbm.sample3(&noise, x, y, z)] {
+                assert!((-1.0..=1.0).contains(&value), "{} en {:?}", value, (x, y, z));
+            }
+            let value = noise.perlin3(x, y, z);
+            (low, high) = (low.min(value), high.max(value));
+        }
+        // Y usa buena parte de él
+        assert!(low < -0.5 && high > 0.5, "[{}, {}]", low, high);
+    }
+
+    #[test]
+    fn perlin_is_zero_on_the_grid_and_continuous() {
+        let noise = Noise::new(3);
+        for x in -5..5 {
+            for y in -5..5 {
+                assert_eq!(noise.perlin2(x as f32, y as f32), 0.0);
+                assert_eq!(noise.perlin3(x as f32, y as f32, 2.0), 0.0);
+            }
+        }
+        // Un paso chico cambia poco, también al cruzar una línea de la grilla
+        for (x, y, z) in points().take(500) {
+            let step = 1e-3;
+            assert!((noise.perlin3(x, y, z) - noise.perlin3(x + step, y, z)).abs() < 0.01);
+            assert!((noise.value2(x, y) - noise.value2(x, y + step)).abs() < 0.01);
+        }
+        assert!((noise.perlin2(0.9995, 0.3) - noise.perlin2(1.0005, 0.3)).abs() < 0.01);
+    }
+
+    #[test]
+    fn fbm_with_one_octave_is_plain_perlin() {
+        let noise = Noise::new(11);
+        let single = Fbm { octaves: 1, ..Fbm::default() };
+        for (x, y, z) in points().take(200) {
+            assert_eq!(single.sample3(&noise, x, y, z), noise.perlin3(x, y, z));
+        }
+        assert_eq!(Fbm { octaves: 0, ..Fbm::default() }.sample2(&noise, 0.3, 0.7), 0.0);
+    }
+}
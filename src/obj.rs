@@ -1,68 +1,481 @@
-use tobj;
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::ops::Range;
+use std::path::Path;
 use nalgebra_glm::{Vec2, Vec3};
 use crate::vertex::Vertex;
+use crate::material::Material;
+
+// Errores al cargar un .obj (o su .mtl)
+#[derive(Debug)]
+pub enum ObjError {
+    Io(io::Error),
+    // `line` empieza en 1 y `content` es la línea tal como aparece en el archivo
+    Parse { line: usize, content: String, reason: ParseError },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParseError {
+    InvalidNumber(String),                           // Token que no es un número finito
+    MissingValues { expected: usize, found: usize }, // Faltan componentes (p. ej. `v 1 2`)
+    InvalidIndex(String),                            // Índice de cara mal formado o cero
+    IndexOutOfRange(String),                         // Índice que no apunta a ningún v/vt/vn declarado
+    TooFewVertices(usize),                           // Cara con menos de 3 vértices
+    InvalidFacet(usize),                             // Faceta de STL que no tiene exactamente 3 vértices
+}
+
+impl fmt::Display for ObjError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjError::Io(err) => write!(f, "error de lectura: {}", err),
+            ObjError::Parse { line, content, reason } => {
+                write!(f, "línea {}: {} en `{}`", line, reason, content)
+            }
+        }
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ParseError::InvalidNumber(token) => write!(f, "número inválido `{}`", token),
+            ParseError::MissingValues { expected, found } => {
+                write!(f, "se esperaban {} valores y hay {}", expected, found)
+            }
+            ParseError::InvalidIndex(token) => write!(f, "índice inválido `{}`", token),
+            ParseError::IndexOutOfRange(token) => write!(f, "índice fuera de rango `{}`", token),
+            ParseError::TooFewVertices(count) => {
+                write!(f, "una cara necesita al menos 3 vértices y tiene {}", count)
+            }
+            ParseError::InvalidFacet(count) => {
+                write!(f, "una faceta debe tener 3 vértices y tiene {}", count)
+            }
+        }
+    }
+}
+
+impl std::error::Error for ObjError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ObjError::Io(err) => Some(err),
+            ObjError::Parse { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for ObjError {
+    fn from(err: io::Error) -> Self {
+        ObjError::Io(err)
+    }
+}
+
+// Índices de un vértice de cara: posición y, opcionalmente, normal y UV
+#[derive(Debug, Clone, Copy)]
+struct FaceIndex {
+    position: u32,
+    normal: Option<u32>,
+    tex_coords: Option<u32>,
+}
+
+// Sección `o`/`g` del archivo: nombre y rango de triángulos que abarca
+#[derive(Debug, Clone)]
+pub struct ObjGroup {
+    pub name: String,
+    pub faces: Range<usize>,
+}
 
 pub struct Obj {
     vertices: Vec<Vec3>,     // Posiciones de los vértices
     normals: Vec<Vec3>,      // Normales
     texcoords: Vec<Vec2>,    // Coordenadas de textura (UV)
-    indices: Vec<u32>,       // Índices para construir las caras
+    indices: Vec<FaceIndex>, // Índices para construir las caras (de 3 en 3)
+    materials: Vec<Material>,           // Materiales del .mtl referido por `mtllib`
+    face_materials: Vec<Option<usize>>, // Material (`usemtl`) de cada triángulo
+    groups: Vec<ObjGroup>,              // Grupos / objetos en orden de aparición
 }
 
 impl Obj {
     // Cargar un archivo .obj y extraer vértices, normales, y texcoords
-    pub fn load(filename: &str) -> Result<Self, tobj::LoadError> {
-        // Llama a `load_obj` con las opciones predeterminadas directamente
-        let (models, _) = tobj::load_obj(filename, &tobj::LoadOptions {
-            triangulate: true,
-            single_index: true,
-            ..Default::default()
-        })?;
-
-        let mesh = &models[0].mesh;
-
-        let vertices: Vec<Vec3> = mesh.positions.chunks(3)
-            .map(|v| Vec3::new(v[0], v[1], v[2]))
-            .collect();
-
-        let normals: Vec<Vec3> = mesh.normals.chunks(3)
-            .map(|n| Vec3::new(n[0], n[1], n[2]))
-            .collect();
-
-        let texcoords: Vec<Vec2> = mesh.texcoords.chunks(2)
-            .map(|t| Vec2::new(t[0], t[1]))
-            .collect();
-
-        let indices = mesh.indices.clone();
-
-        Ok(Obj {
-            vertices,
-            normals,
-            texcoords,
-            indices,
+    pub fn load(filename: &str) -> Result<Self, ObjError> {
+        let source = fs::read_to_string(filename)?;
+        // `mtllib` se resuelve relativo al directorio del .obj
+        let directory = Path::new(filename).parent().unwrap_or_else(|| Path::new(""));
+        Self::parse(&source, Some(directory))
+    }
+
+    // Cargar un modelo a partir del contenido de un .obj ya en memoria. `mtllib` se
+    // busca en `directory`; sin él se ignora, porque no hay desde dónde resolverlo
+    pub fn parse(source: &str, directory: Option<&Path>) -> Result<Self, ObjError> {
+        let mut obj = Obj {
+            vertices: Vec::new(),
+            normals: Vec::new(),
+            texcoords: Vec::new(),
+            indices: Vec::new(),
+            materials: Vec::new(),
+            face_materials: Vec::new(),
+            groups: Vec::new(),
+        };
+        let mut current_material = None;
+        let mut current_group = ObjGroup { name: String::from("default"), faces: 0..0 };
+
+        for (number, content) in source.lines().enumerate() {
+            let error = |reason| ObjError::Parse { line: number + 1, content: content.to_string(), reason };
+
+            // Los comentarios pueden ir al final de cualquier línea
+            let data = content.split('#').next().unwrap_or("");
+            let mut tokens = data.split_whitespace();
+            let Some(keyword) = tokens.next() else { continue };
+
+            match keyword {
+                "v" => {
+                    // Se ignoran `w` y los colores por vértice (`v x y z r g b`)
+                    let [x, y, z] = parse_floats::<3>(tokens, 3).map_err(error)?;
+                    obj.vertices.push(Vec3::new(x, y, z));
+                }
+                "vn" => {
+                    let [x, y, z] = parse_floats::<3>(tokens, 3).map_err(error)?;
+                    obj.normals.push(Vec3::new(x, y, z));
+                }
+                "vt" => {
+                    // `v` es opcional en el formato y vale 0 si falta
+                    let [u, v] = parse_floats::<2>(tokens, 1).map_err(error)?;
+                    obj.texcoords.push(Vec2::new(u, v));
+                }
+                "f" => {
+                    let face = tokens
+                        .map(|token| obj.parse_face_index(token))
+                        .collect::<Result<Vec<_>, _>>()
+                        .map_err(error)?;
+                    if face.len() < 3 {
+                        return Err(error(ParseError::TooFewVertices(face.len())));
+                    }
+
+                    // Quads y n-gonos se dividen en abanico: (v0, v1, v2), (v0, v2, v3), ...
+                    // para que el arreglo de vértices siempre venga en grupos de 3
+                    for pair in face[1..].windows(2) {
+                        obj.indices.extend([face[0], pair[0], pair[1]]);
+                        obj.face_materials.push(current_material);
+                        current_group.faces.end += 1;
+                    }
+                }
+                "o" | "g" => {
+                    let name = tokens.collect::<Vec<_>>().join(" ");
+                    let name = if name.is_empty() { String::from("default") } else { name };
+                    let start = current_group.faces.end;
+                    let previous = std::mem::replace(&mut current_group, ObjGroup { name, faces: start..start });
+                    if !previous.faces.is_empty() {
+                        obj.groups.push(previous);
+                    }
+                }
+                "mtllib" => {
+                    let Some(directory) = directory else { continue };
+                    for library in tokens {
+                        let path = directory.join(library);
+                        match Material::load_library(&path) {
+                            Ok(materials) => obj.materials.extend(materials),
+                            Err(err) => eprintln!(
+                                "Advertencia: no se pudo cargar el material {}: {}",
+                                path.display(),
+                                err
+                            ),
+                        }
+                    }
+                }
+                "usemtl" => {
+                    let name = tokens.collect::<Vec<_>>().join(" ");
+                    current_material = obj.materials.iter().position(|material| material.name == name);
+                }
+                // Directivas que no afectan la geometría (`s`, `l`, `p`, ...) se ignoran
+                _ => {}
+            }
+        }
+
+        if !current_group.faces.is_empty() {
+            obj.groups.push(current_group);
+        }
+
+        Ok(obj)
+    }
+
+    // Resuelve un vértice de cara `v`, `v/vt`, `v//vn` o `v/vt/vn`
+    fn parse_face_index(&self, token: &str) -> Result<FaceIndex, ParseError> {
+        // Una barra al final (`1/2/`) es un archivo cortado a mitad de la cara, no
+        // un vértice sin normal: esos se escriben `1/2`
+        if token.ends_with('/') {
+            return Err(ParseError::InvalidIndex(token.to_string()));
+        }
+        let mut parts = token.split('/');
+        let position = parts.next().unwrap_or("");
+        let tex_coords = parts.next().filter(|part| !part.is_empty());
+        let normal = parts.next().filter(|part| !part.is_empty());
+        if parts.next().is_some() {
+            return Err(ParseError::InvalidIndex(token.to_string()));
+        }
+
+        let resolve = |part: &str, len: usize| resolve_index(part, len, token);
+        Ok(FaceIndex {
+            position: resolve(position, self.vertices.len())?,
+            tex_coords: tex_coords.map(|part| resolve(part, self.texcoords.len())).transpose()?,
+            normal: normal.map(|part| resolve(part, self.normals.len())).transpose()?,
         })
     }
 
-    // Crear un array de vértices (Vec<Vertex>) usando los datos cargados
-    pub fn get_vertex_array(&self) -> Vec<Vertex> {
+    // Indica si el archivo declara normales (`vn`)
+    pub fn has_normals(&self) -> bool {
+        !self.normals.is_empty()
+    }
+
+    pub fn groups(&self) -> &[ObjGroup] {
+        &self.groups
+    }
+
+    // Material de la cara `face` (en el orden de los `f` del archivo);
+    // None si la cara no tiene `usemtl` o el .mtl no se pudo cargar
+    pub fn face_material(&self, face: usize) -> Option<&Material> {
+        self.face_materials
+            .get(face)
+            .copied()
+            .flatten()
+            .and_then(|id| self.materials.get(id))
+    }
+
+    // Vértices únicos e índices (de 3 en 3) de los triángulos `faces`. Los vértices
+    // de cara con el mismo trío v/vt/vn y el mismo material se comparten
+    pub fn get_indexed_vertex_array(&self, faces: Range<usize>) -> (Vec<Vertex>, Vec<u32>) {
         let mut vertex_array = Vec::new();
+        let mut index_array = Vec::with_capacity(faces.len() * 3);
+        let mut shared: HashMap<VertexKey, u32> = HashMap::new();
+
+        for face in faces {
+            let face_indices = &self.indices[face * 3..face * 3 + 3];
+            let face_normal = self.face_normal(face_indices);
 
-        for &index in &self.indices {
-            let position = self.vertices[index as usize];
-            let normal = if !self.normals.is_empty() {
-                self.normals[index as usize]
-            } else {
-                Vec3::new(0.0, 0.0, 0.0)  // Normal predeterminada si no hay datos
-            };
-            let tex_coords = if !self.texcoords.is_empty() {
-                self.texcoords[index as usize]
-            } else {
-                Vec2::new(0.0, 0.0)  // UV predeterminado si no hay datos
-            };
-
-            vertex_array.push(Vertex::new(position, normal, tex_coords));
+            for index in face_indices {
+                let key = VertexKey {
+                    position: index.position,
+                    tex_coords: index.tex_coords,
+                    // Sin `vn` la normal es la de la cara, así que el vértice no se comparte
+                    normal: index.normal.ok_or(face),
+                    material: self.face_materials[face],
+                };
+                let shared_index = *shared.entry(key).or_insert_with(|| {
+                    vertex_array.push(self.build_vertex(index, face_normal));
+                    (vertex_array.len() - 1) as u32
+                });
+                index_array.push(shared_index);
+            }
         }
 
-        vertex_array
+        (vertex_array, index_array)
+    }
+
+    fn face_normal(&self, face: &[FaceIndex]) -> Vec3 {
+        let [a, b, c] = [face[0], face[1], face[2]].map(|index| self.vertices[index.position as usize]);
+        face_normal(&a, &b, &c)
+    }
+
+    fn build_vertex(&self, index: &FaceIndex, face_normal: Vec3) -> Vertex {
+        let position = self.vertices[index.position as usize];
+        // Si la cara no trae normales se usa la normal geométrica de la cara
+        let normal = index.normal
+            .and_then(|n| self.normals.get(n as usize).copied())
+            .unwrap_or(face_normal);
+        let tex_coords = index.tex_coords
+            .and_then(|t| self.texcoords.get(t as usize).copied())
+            .unwrap_or_else(|| Vec2::new(0.0, 0.0));  // UV predeterminado si no hay datos
+
+        Vertex::new(position, normal, tex_coords)
+    }
+}
+
+// Identidad de un vértice al deduplicar: sus índices y el material de la cara
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+struct VertexKey {
+    position: u32,
+    tex_coords: Option<u32>,
+    normal: Result<u32, usize>, // Índice de `vn`, o la cara si no tiene normal propia
+    material: Option<usize>,
+}
+
+// Lee hasta N números de la línea; exige al menos `required` y rellena el resto con 0
+pub(crate) fn parse_floats<'a, const N: usize>(
+    tokens: impl Iterator<Item = &'a str>,
+    required: usize,
+) -> Result<[f32; N], ParseError> {
+    let mut values = [0.0; N];
+    let mut found = 0;
+
+    for token in tokens.take(N) {
+        values[found] = token
+            .parse::<f32>()
+            .ok()
+            .filter(|value| value.is_finite())
+            .ok_or_else(|| ParseError::InvalidNumber(token.to_string()))?;
+        found += 1;
+    }
+
+    if found < required {
+        return Err(ParseError::MissingValues { expected: required, found });
+    }
+    Ok(values)
+}
+
+// Índices base 1; los negativos (`f -3 -2 -1`) cuentan hacia atrás desde los
+// elementos declarados hasta el momento en que aparece la cara
+fn resolve_index(part: &str, len: usize, token: &str) -> Result<u32, ParseError> {
+    let index: i64 = part.parse().map_err(|_| ParseError::InvalidIndex(token.to_string()))?;
+    let resolved = match index {
+        0 => return Err(ParseError::InvalidIndex(token.to_string())),
+        i if i > 0 => i - 1,
+        i => len as i64 + i,
+    };
+
+    if resolved < 0 || resolved >= len as i64 {
+        return Err(ParseError::IndexOutOfRange(token.to_string()));
+    }
+    Ok(resolved as u32)
+}
+
+// Normal de una cara a partir del producto cruz de sus aristas (cero si es degenerada)
+fn face_normal(a: &Vec3, b: &Vec3, c: &Vec3) -> Vec3 {
+    let normal = (b - a).cross(&(c - a));
+    if normal.norm() > f32::EPSILON {
+        normal.normalize()
+    } else {
+        Vec3::new(0.0, 0.0, 0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn positions(obj: &Obj) -> Vec<u32> {
+        obj.indices.iter().map(|index| index.position).collect()
+    }
+
+    #[test]
+    fn quad_is_split_into_a_fan() {
+        let obj = Obj::parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nf 1 2 3 4\n", None).unwrap();
+        assert_eq!(positions(&obj), [0, 1, 2, 0, 2, 3]);
+        assert_eq!(obj.face_materials.len(), 2);
+        assert_eq!(obj.groups()[0].faces, 0..2);
+
+        // Sin `vn` cada triángulo lleva la normal de su cara y no comparte vértices
+        let (vertices, indices) = obj.get_indexed_vertex_array(0..2);
+        assert_eq!((vertices.len(), indices.len()), (6, 6));
+        assert!(vertices.iter().all(|vertex| vertex.normal == Vec3::z()));
+    }
+
+    #[test]
+    fn quad_with_normals_shares_its_corners() {
+        let obj = Obj::parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\nvn 0 0 1\nf 1//1 2//1 3//1 4//1\n", None).unwrap();
+        let (vertices, indices) = obj.get_indexed_vertex_array(0..2);
+        assert_eq!(vertices.len(), 4);
+        assert_eq!(indices, [0, 1, 2, 0, 2, 3]);
+    }
+
+    #[test]
+    fn polygon_fan_starts_at_the_first_vertex() {
+        let obj = Obj::parse("v 0 0 0\nv 2 0 0\nv 3 1 0\nv 1 2 0\nv -1 1 0\nf 1 2 3 4 5\n", None).unwrap();
+        assert_eq!(positions(&obj), [0, 1, 2, 0, 2, 3, 0, 3, 4]);
+    }
+
+    const SQUARE: &str = "v 0 0 0\nv 1 0 0\nv 1 1 0\nv 0 1 0\n";
+
+    fn parse_error(source: &str) -> (usize, ParseError) {
+        match Obj::parse(source, None) {
+            Err(ObjError::Parse { line, reason, .. }) => (line, reason),
+            Err(err) => panic!("error inesperado: {}", err),
+            Ok(_) => panic!("`{}` debería fallar", source),
+        }
+    }
+
+    #[test]
+    fn negative_indices_count_back_from_the_last_vertex() {
+        let obj = Obj::parse(&format!("{}f -1 -2 -3\n", SQUARE), None).unwrap();
+        assert_eq!(positions(&obj), [3, 2, 1]);
+    }
+
+    #[test]
+    fn negative_and_positive_indices_can_be_mixed() {
+        let obj = Obj::parse(&format!("{}f 1 -3 3\n", SQUARE), None).unwrap();
+        assert_eq!(positions(&obj), [0, 1, 2]);
+    }
+
+    #[test]
+    fn minus_len_is_the_first_vertex() {
+        let obj = Obj::parse(&format!("{}f -4 -3 -2\n", SQUARE), None).unwrap();
+        assert_eq!(positions(&obj), [0, 1, 2]);
+    }
+
+    #[test]
+    fn negative_indices_only_see_what_was_declared_before_the_face() {
+        let obj = Obj::parse("v 0 0 0\nv 1 0 0\nv 1 1 0\nf -3 -2 -1\nv 0 1 0\nf -3 -2 -1\n", None).unwrap();
+        assert_eq!(positions(&obj), [0, 1, 2, 1, 2, 3]);
+    }
+
+    #[test]
+    fn negative_texcoord_and_normal_indices() {
+        let source = format!("{}vt 0 0\nvt 1 0\nvt 1 1\nvn 0 0 1\nf -4/-3/-1 -3/-2/-1 -2/-1/-1\n", SQUARE);
+        let obj = Obj::parse(&source, None).unwrap();
+        let tex_coords: Vec<_> = obj.indices.iter().map(|index| index.tex_coords).collect();
+        assert_eq!(tex_coords, [Some(0), Some(1), Some(2)]);
+        assert!(obj.indices.iter().all(|index| index.normal == Some(0)));
+    }
+
+    #[test]
+    fn negative_indices_out_of_range_are_rejected() {
+        let (line, reason) = parse_error(&format!("{}f -1 -2 -5\n", SQUARE));
+        assert_eq!((line, reason), (5, ParseError::IndexOutOfRange(String::from("-5"))));
+        // Un `vt` negativo sin ningún `vt` declarado
+        let (_, reason) = parse_error(&format!("{}f 1/-1 2/-1 3/-1\n", SQUARE));
+        assert_eq!(reason, ParseError::IndexOutOfRange(String::from("1/-1")));
+    }
+
+    #[test]
+    fn bad_vertex_lines_report_their_line_and_reason() {
+        assert_eq!(parse_error("# nave\nv 1 2\n"), (2, ParseError::MissingValues { expected: 3, found: 2 }));
+        assert_eq!(parse_error("v 0 0 0\n\nv 1 x 3\n"), (3, ParseError::InvalidNumber(String::from("x"))));
+        assert_eq!(parse_error("v 1 2 inf\n"), (1, ParseError::InvalidNumber(String::from("inf"))));
+        assert_eq!(parse_error("vn 0 1\n"), (1, ParseError::MissingValues { expected: 3, found: 2 }));
+    }
+
+    #[test]
+    fn bad_face_lines_report_their_line_and_reason() {
+        assert_eq!(parse_error(&format!("{}f 1 2\n", SQUARE)), (5, ParseError::TooFewVertices(2)));
+        assert_eq!(parse_error(&format!("{}f 1 0 2\n", SQUARE)), (5, ParseError::InvalidIndex(String::from("0"))));
+        assert_eq!(parse_error(&format!("{}f 1 2 a\n", SQUARE)), (5, ParseError::InvalidIndex(String::from("a"))));
+        assert_eq!(parse_error(&format!("{}f 1 2 9\n", SQUARE)), (5, ParseError::IndexOutOfRange(String::from("9"))));
+        assert_eq!(parse_error(&format!("{}f 1/1/1/1 2 3\n", SQUARE)), (5, ParseError::InvalidIndex(String::from("1/1/1/1"))));
+    }
+
+    #[test]
+    fn empty_files_have_no_vertices_or_faces() {
+        for source in ["", "\n\n", "# exportado sin geometría\n", "   \t\n"] {
+            let obj = Obj::parse(source, None).unwrap();
+            assert!(obj.vertices.is_empty() && positions(&obj).is_empty(), "{:?}", source);
+            assert!(!obj.has_normals());
+        }
+    }
+
+    #[test]
+    fn files_cut_mid_line_are_rejected_at_that_line() {
+        let textured = format!("{}vt 0 0\nvt 1 0\n", SQUARE);
+        assert_eq!(parse_error(&format!("{}f 1/2/", textured)), (7, ParseError::InvalidIndex(String::from("1/2/"))));
+        assert_eq!(parse_error(&format!("{}f 1/1 2/2 3/", textured)), (7, ParseError::InvalidIndex(String::from("3/"))));
+        assert_eq!(parse_error(&format!("{}f 1/1 2/2", textured)), (7, ParseError::TooFewVertices(2)));
+        assert_eq!(parse_error("v 0 0 0\nv 1 0"), (2, ParseError::MissingValues { expected: 3, found: 2 }));
+        // Sin salto de línea al final pero con la cara completa, el archivo sirve
+        assert_eq!(positions(&Obj::parse(&format!("{}f 1/1 2/2 3/1", textured), None).unwrap()), [0, 1, 2]);
+    }
+
+    #[test]
+    fn parse_errors_keep_the_original_line() {
+        let err = Obj::parse("v 0 0 0\nv 1 2 # sin z\n", None).err().unwrap();
+        assert!(matches!(&err, ObjError::Parse { content, .. } if content == "v 1 2 # sin z"));
+        assert_eq!(err.to_string(), "línea 2: se esperaban 3 valores y hay 2 en `v 1 2 # sin z`");
     }
 }
@@ -0,0 +1,474 @@
+// ply.rs
+
+use std::fmt;
+use std::fs;
+use std::io;
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+
+#[derive(Debug)]
+pub enum PlyError {
+    Io(io::Error),
+    InvalidHeader { line: usize, content: String, reason: String },
+    // Dato inválido en el elemento `element` número `index` (desde 0)
+    InvalidData { element: String, index: usize, reason: String },
+    UnexpectedEof,
+}
+
+impl fmt::Display for PlyError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlyError::Io(err) => write!(f, "error de lectura: {}", err),
+            PlyError::InvalidHeader { line, content, reason } => {
+                write!(f, "cabecera, línea {}: {} en `{}`", line, reason, content)
+            }
+            PlyError::InvalidData { element, index, reason } => {
+                write!(f, "{} {}: {}", element, index, reason)
+            }
+            PlyError::UnexpectedEof => write!(f, "el archivo termina antes de tiempo"),
+        }
+    }
+}
+
+impl std::error::Error for PlyError {}
+
+impl From<io::Error> for PlyError {
+    fn from(err: io::Error) -> Self {
+        PlyError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlyVertex {
+    pub position: Vec3,
+    pub normal: Option<Vec3>,  // Si el archivo declara `nx ny nz`
+    pub color: Option<Color>,  // Si el archivo declara `red green blue`
+}
+
+pub struct Ply {
+    pub vertices: Vec<PlyVertex>,
+    pub faces: Vec<Vec<u32>>, // Índices de cada cara tal como vienen (pueden ser n-gonos)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Format {
+    Ascii,
+    BinaryLittleEndian,
+    BinaryBigEndian,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ScalarType {
+    Int8,
+    UInt8,
+    Int16,
+    UInt16,
+    Int32,
+    UInt32,
+    Float32,
+    Float64,
+}
+
+impl ScalarType {
+    fn parse(name: &str) -> Option<Self> {
+        Some(match name {
+            "char" | "int8" => ScalarType::Int8,
+            "uchar" | "uint8" => ScalarType::UInt8,
+            "short" | "int16" => ScalarType::Int16,
+            "ushort" | "uint16" => ScalarType::UInt16,
+            "int" | "int32" => ScalarType::Int32,
+            "uint" | "uint32" => ScalarType::UInt32,
+            "float" | "float32" => ScalarType::Float32,
+            "double" | "float64" => ScalarType::Float64,
+            _ => return None,
+        })
+    }
+
+    fn size(self) -> usize {
+        match self {
+            ScalarType::Int8 | ScalarType::UInt8 => 1,
+            ScalarType::Int16 | ScalarType::UInt16 => 2,
+            ScalarType::Int32 | ScalarType::UInt32 | ScalarType::Float32 => 4,
+            ScalarType::Float64 => 8,
+        }
+    }
+
+    // Valor máximo para normalizar colores enteros a [0, 1]; en los tipos con signo
+    // es el máximo positivo
+    fn color_scale(self) -> f64 {
+        match self {
+            ScalarType::UInt8 => u8::MAX as f64,
+            ScalarType::Int8 => i8::MAX as f64,
+            ScalarType::UInt16 => u16::MAX as f64,
+            ScalarType::Int16 => i16::MAX as f64,
+            ScalarType::UInt32 => u32::MAX as f64,
+            ScalarType::Int32 => i32::MAX as f64,
+            ScalarType::Float32 | ScalarType::Float64 => 1.0,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Property {
+    Scalar { name: String, kind: ScalarType },
+    List { name: String, count: ScalarType, item: ScalarType },
+}
+
+#[derive(Debug, Clone)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+    line: usize, // Línea de la cabecera que lo declara
+}
+
+enum ReadError {
+    Eof,
+    Invalid(String),
+}
+
+// Lector de valores para el cuerpo del archivo, en texto o binario
+enum Body<'a> {
+    Ascii(std::str::SplitWhitespace<'a>),
+    Binary { bytes: &'a [u8], big_endian: bool },
+}
+
+impl Body<'_> {
+    fn read(&mut self, kind: ScalarType) -> Result<f64, ReadError> {
+        match self {
+            Body::Ascii(tokens) => {
+                let token = tokens.next().ok_or(ReadError::Eof)?;
+                token.parse::<f64>().map_err(|_| ReadError::Invalid(format!("número inválido `{}`", token)))
+            }
+            Body::Binary { bytes, big_endian } => {
+                let size = kind.size();
+                if bytes.len() < size {
+                    return Err(ReadError::Eof);
+                }
+                let (value, rest) = bytes.split_at(size);
+                *bytes = rest;
+
+                // Se pasa todo a little endian y se lee igual en los dos formatos
+                let mut value: [u8; 8] = std::array::from_fn(|i| value.get(i).copied().unwrap_or(0));
+                if *big_endian {
+                    value[..size].reverse();
+                }
+                Ok(match kind {
+                    ScalarType::Int8 => value[0] as i8 as f64,
+                    ScalarType::UInt8 => value[0] as f64,
+                    ScalarType::Int16 => i16::from_le_bytes([value[0], value[1]]) as f64,
+                    ScalarType::UInt16 => u16::from_le_bytes([value[0], value[1]]) as f64,
+                    ScalarType::Int32 => i32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f64,
+                    ScalarType::UInt32 => u32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f64,
+                    ScalarType::Float32 => f32::from_le_bytes([value[0], value[1], value[2], value[3]]) as f64,
+                    ScalarType::Float64 => f64::from_le_bytes(value),
+                })
+            }
+        }
+    }
+}
+
+impl Ply {
+    // Cargar un .ply en formato ASCII o binario (little o big endian)
+    pub fn load(filename: &str) -> Result<Self, PlyError> {
+        let bytes = fs::read(filename)?;
+        Self::parse(&bytes)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, PlyError> {
+        let (format, elements, body_start) = parse_header(bytes)?;
+        let mut body = match format {
+            Format::Ascii => {
+                let text = std::str::from_utf8(&bytes[body_start..]).map_err(|_| PlyError::InvalidData {
+                    element: String::from("cuerpo"),
+                    index: 0,
+                    reason: String::from("texto que no es UTF-8"),
+                })?;
+                Body::Ascii(text.split_whitespace())
+            }
+            Format::BinaryLittleEndian => Body::Binary { bytes: &bytes[body_start..], big_endian: false },
+            Format::BinaryBigEndian => Body::Binary { bytes: &bytes[body_start..], big_endian: true },
+        };
+
+        check_counts(&elements, format, bytes.len() - body_start)?;
+        let mut ply = Ply { vertices: Vec::new(), faces: Vec::new() };
+
+        for element in &elements {
+            for index in 0..element.count {
+                let error = |err| match err {
+                    ReadError::Eof => PlyError::UnexpectedEof,
+                    ReadError::Invalid(reason) => PlyError::InvalidData { element: element.name.clone(), index, reason },
+                };
+
+                let mut scalars: Vec<(&str, f64, ScalarType)> = Vec::new();
+                let mut lists: Vec<(&str, Vec<f64>)> = Vec::new();
+                for property in &element.properties {
+                    match property {
+                        Property::Scalar { name, kind } => {
+                            scalars.push((name, body.read(*kind).map_err(error)?, *kind));
+                        }
+                        Property::List { name, count, item } => {
+                            let len = body.read(*count).map_err(error)?;
+                            let len = whole(len).ok_or_else(|| error(ReadError::Invalid(format!("cantidad inválida `{}`", len))))? as usize;
+                            let items = (0..len).map(|_| body.read(*item)).collect::<Result<Vec<_>, _>>().map_err(error)?;
+                            lists.push((name, items));
+                        }
+                    }
+                }
+
+                match element.name.as_str() {
+                    "vertex" => ply.vertices.push(read_vertex(&scalars)),
+                    "face" => {
+                        // `vertex_indices` es lo habitual; algunos exportadores usan `vertex_index`
+                        let items = lists.iter().find(|(name, _)| *name == "vertex_indices" || *name == "vertex_index");
+                        let indices = items.map_or(&[][..], |(_, items)| items).iter().map(|&i| {
+                            whole(i).ok_or_else(|| error(ReadError::Invalid(format!("índice inválido `{}`", i))))
+                        });
+                        ply.faces.push(indices.collect::<Result<_, _>>()?);
+                    }
+                    // Otros elementos (aristas, material...) se leen pero se descartan
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(ply)
+    }
+}
+
+// Un índice o el largo de una lista tienen que ser enteros no negativos: `as u32`
+// llevaría -1, 0.5 o NaN a 0 sin avisar
+fn whole(value: f64) -> Option<u32> {
+    (value.fract() == 0.0 && (0.0..=u32::MAX as f64).contains(&value)).then_some(value as u32)
+}
+
+// Rechaza las cantidades de la cabecera que el cuerpo no puede tener, antes de
+// recorrerlas: un elemento sin propiedades no lee nada y daría `count` vueltas en vano,
+// y cada valor ocupa al menos un byte en texto (un dígito) o su tamaño en binario
+fn check_counts(elements: &[Element], format: Format, body_len: usize) -> Result<(), PlyError> {
+    let mut needed = 0_usize;
+    for element in elements {
+        let error = |reason: &str| PlyError::InvalidHeader {
+            line: element.line,
+            content: format!("element {} {}", element.name, element.count),
+            reason: reason.to_string(),
+        };
+        if element.count > 0 && element.properties.is_empty() {
+            return Err(error("elemento sin propiedades"));
+        }
+        let item_size: usize = element
+            .properties
+            .iter()
+            .map(|property| match (format, property) {
+                (Format::Ascii, _) => 1,
+                (_, Property::Scalar { kind, .. }) => kind.size(),
+                (_, Property::List { count, .. }) => count.size(),
+            })
+            .sum();
+        needed = needed.saturating_add(element.count.saturating_mul(item_size));
+        if needed > body_len {
+            return Err(error("hay más de los que caben en el resto del archivo"));
+        }
+    }
+    Ok(())
+}
+
+fn read_vertex(scalars: &[(&str, f64, ScalarType)]) -> PlyVertex {
+    let find = |name: &str| scalars.iter().find(|(n, _, _)| *n == name).map(|&(_, value, kind)| (value, kind));
+    let component = |name: &str| find(name).map_or(0.0, |(value, _)| value as f32);
+    let vec3 = |x: &str, y: &str, z: &str| {
+        find(x).and(find(y)).and(find(z)).map(|_| Vec3::new(component(x), component(y), component(z)))
+    };
+
+    let color = match (find("red"), find("green"), find("blue")) {
+        (Some((r, kind)), Some((g, _)), Some((b, _))) => {
+            let scale = kind.color_scale();
+            Some(Color { r: (r / scale) as f32, g: (g / scale) as f32, b: (b / scale) as f32 })
+        }
+        _ => None,
+    };
+
+    PlyVertex {
+        position: Vec3::new(component("x"), component("y"), component("z")),
+        normal: vec3("nx", "ny", "nz"),
+        color,
+    }
+}
+
+// Lee la cabecera y devuelve el formato, los elementos y dónde empieza el cuerpo
+fn parse_header(bytes: &[u8]) -> Result<(Format, Vec<Element>, usize), PlyError> {
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    let mut offset = 0;
+
+    for number in 1.. {
+        let rest = &bytes[offset..];
+        let end = rest.iter().position(|&b| b == b'\n').ok_or(PlyError::UnexpectedEof)?;
+        let content = String::from_utf8_lossy(&rest[..end]).trim_end_matches('\r').to_string();
+        offset += end + 1;
+
+        let error = |reason: &str| PlyError::InvalidHeader { line: number, content: content.clone(), reason: reason.to_string() };
+        let tokens: Vec<&str> = content.split_whitespace().collect();
+
+        if number == 1 {
+            if tokens.first() != Some(&"ply") {
+                return Err(error("el archivo no empieza con `ply`"));
+            }
+            continue;
+        }
+
+        match tokens.as_slice() {
+            ["format", "ascii", ..] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", ..] => format = Some(Format::BinaryLittleEndian),
+            ["format", "binary_big_endian", ..] => format = Some(Format::BinaryBigEndian),
+            ["format", ..] => return Err(error("formato no soportado")),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count.parse().map_err(|_| error("cantidad inválida"))?,
+                properties: Vec::new(),
+                line: number,
+            }),
+            ["property", "list", count, item, name] => {
+                let element = elements.last_mut().ok_or_else(|| error("propiedad fuera de un elemento"))?;
+                element.properties.push(Property::List {
+                    name: name.to_string(),
+                    count: ScalarType::parse(count).ok_or_else(|| error("tipo desconocido"))?,
+                    item: ScalarType::parse(item).ok_or_else(|| error("tipo desconocido"))?,
+                });
+            }
+            ["property", kind, name] => {
+                let element = elements.last_mut().ok_or_else(|| error("propiedad fuera de un elemento"))?;
+                element.properties.push(Property::Scalar {
+                    name: name.to_string(),
+                    kind: ScalarType::parse(kind).ok_or_else(|| error("tipo desconocido"))?,
+                });
+            }
+            ["end_header"] => break,
+            ["comment", ..] | ["obj_info", ..] | [] => {}
+            _ => return Err(error("línea de cabecera desconocida")),
+        }
+    }
+
+    let format = format.ok_or(PlyError::InvalidHeader {
+        line: 0,
+        content: String::new(),
+        reason: String::from("falta la línea `format`"),
+    })?;
+    Ok((format, elements, offset))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEADER: &str = "ply
+format {} 1.0
+comment triángulo con colores por vértice
+element vertex 3
+property float x
+property float y
+property float z
+property uchar red
+property uchar green
+property uchar blue
+element face 1
+property list uchar int vertex_indices
+end_header
+";
+
+    const POSITIONS: [[f32; 3]; 3] = [[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.5]];
+    const COLORS: [[u8; 3]; 3] = [[255, 0, 0], [0, 255, 0], [0, 0, 51]];
+
+    // Cuerpo binario del triángulo de `HEADER`; `bytes` convierte cada número
+    fn binary(format: &str, f32_bytes: fn(f32) -> [u8; 4], i32_bytes: fn(i32) -> [u8; 4]) -> Vec<u8> {
+        let mut bytes = HEADER.replace("{}", format).into_bytes();
+        for (position, color) in POSITIONS.iter().zip(COLORS) {
+            for &value in position {
+                bytes.extend(f32_bytes(value));
+            }
+            bytes.extend(color);
+        }
+        bytes.push(3);
+        for index in 0..3 {
+            bytes.extend(i32_bytes(index));
+        }
+        bytes
+    }
+
+    fn assert_triangle(ply: &Ply) {
+        assert_eq!(ply.vertices.len(), 3);
+        for (vertex, (position, color)) in ply.vertices.iter().zip(POSITIONS.iter().zip(COLORS)) {
+            assert_eq!(vertex.position, Vec3::from(*position));
+            assert!(vertex.normal.is_none());
+            let actual = vertex.color.unwrap();
+            assert_eq!([actual.r, actual.g, actual.b], color.map(|c| c as f32 / 255.0));
+        }
+        assert_eq!(ply.faces, vec![vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn parses_ascii_with_vertex_colors() {
+        let source = HEADER.replace("{}", "ascii") + "0 0 0 255 0 0\n1 0 0 0 255 0\n0 1 0.5 0 0 51\n3 0 1 2\n";
+        assert_triangle(&Ply::parse(source.as_bytes()).unwrap());
+    }
+
+    #[test]
+    fn parses_binary_little_endian_with_vertex_colors() {
+        assert_triangle(&Ply::parse(&binary("binary_little_endian", f32::to_le_bytes, i32::to_le_bytes)).unwrap());
+    }
+
+    #[test]
+    fn parses_binary_big_endian_with_vertex_colors() {
+        assert_triangle(&Ply::parse(&binary("binary_big_endian", f32::to_be_bytes, i32::to_be_bytes)).unwrap());
+    }
+
+    #[test]
+    fn signed_colors_are_scaled_by_their_positive_maximum() {
+        assert_eq!(ScalarType::Int8.color_scale(), 127.0);
+        assert_eq!(ScalarType::Int16.color_scale(), 32767.0);
+        assert_eq!(ScalarType::UInt16.color_scale(), 65535.0);
+        assert_eq!(ScalarType::Int32.color_scale(), i32::MAX as f64);
+
+        let source = "ply\nformat ascii 1.0\nelement vertex 1\nproperty float x\nproperty float y\nproperty float z\n\
+            property short red\nproperty short green\nproperty short blue\nend_header\n0 0 0 32767 0 16384\n";
+        let color = Ply::parse(source.as_bytes()).unwrap().vertices[0].color.unwrap();
+        assert_eq!(color.r, 1.0);
+        assert_eq!(color.g, 0.0);
+        assert!((color.b - 0.5).abs() < 1e-4);
+    }
+
+    #[test]
+    fn truncated_binary_body_is_an_error() {
+        let mut bytes = binary("binary_big_endian", f32::to_be_bytes, i32::to_be_bytes);
+        bytes.truncate(bytes.len() - 2);
+        assert!(matches!(Ply::parse(&bytes), Err(PlyError::UnexpectedEof)));
+    }
+
+    #[test]
+    fn negative_fractional_or_nan_indices_are_errors() {
+        let header = HEADER.replace("{}", "ascii");
+        for face in ["3 0 1 -1", "3 0 1.5 2", "3 0 1 nan", "2.5 0 1 2", "-3 0 1 2"] {
+            let source = format!("{}0 0 0 255 0 0\n1 0 0 0 255 0\n0 1 0.5 0 0 51\n{}\n", header, face);
+            assert!(matches!(Ply::parse(source.as_bytes()), Err(PlyError::InvalidData { index: 0, .. })), "{}", face);
+        }
+
+        // Los índices en float son válidos si son enteros
+        let floats = header.replace("list uchar int", "list uchar float");
+        let source = format!("{}0 0 0 255 0 0\n1 0 0 0 255 0\n0 1 0.5 0 0 51\n3 0 1.0 2\n", floats);
+        assert_eq!(Ply::parse(source.as_bytes()).unwrap().faces, vec![vec![0, 1, 2]]);
+    }
+
+    #[test]
+    fn counts_the_body_cannot_hold_are_rejected_up_front() {
+        // Sin propiedades no se leería nada en cuatro mil millones de vueltas
+        let empty = "ply\nformat ascii 1.0\nelement nada 4000000000\nend_header\n";
+        assert!(matches!(Ply::parse(empty.as_bytes()), Err(PlyError::InvalidHeader { line: 3, .. })));
+        // Sin propiedades pero sin ninguno está bien
+        assert!(Ply::parse(b"ply\nformat ascii 1.0\nelement nada 0\nend_header\n").is_ok());
+
+        let huge = HEADER.replace("{}", "binary_little_endian").replace("element face 1", "element face 4000000000");
+        let mut bytes = binary("binary_little_endian", f32::to_le_bytes, i32::to_le_bytes);
+        bytes.splice(..HEADER.replace("{}", "binary_little_endian").len(), huge.into_bytes());
+        assert!(matches!(Ply::parse(&bytes), Err(PlyError::InvalidHeader { line: 11, .. })));
+    }
+}
@@ -0,0 +1,128 @@
+// scene.rs
+// Conjunto de modelos que se dibujan juntos contra el mismo z-buffer
+
+use crate::model::Model3D;
+
+// Identificador estable de un modelo dentro de la escena. Al quitar un modelo su lugar
+// se reutiliza, pero con otra generación: el id viejo no ve al modelo que lo ocupa
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ModelId {
+    index: usize,
+    generation: u32,
+}
+
+struct Entry {
+    model: Model3D,
+    visible: bool,
+}
+
+struct Slot {
+    generation: u32, // Sube cada vez que se quita el modelo del lugar
+    entry: Option<Entry>,
+}
+
+#[derive(Default)]
+pub struct Scene {
+    slots: Vec<Slot>,
+    free: Vec<usize>, // Lugares vacíos, para el próximo `add_model`
+}
+
+impl Scene {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_model(&mut self, model: Model3D) -> ModelId {
+        let entry = Some(Entry { model, visible: true });
+        match self.free.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index];
+                slot.entry = entry;
+                ModelId { index, generation: slot.generation }
+            }
+            None => {
+                self.slots.push(Slot { generation: 0, entry });
+                ModelId { index: self.slots.len() - 1, generation: 0 }
+            }
+        }
+    }
+
+    pub fn remove(&mut self, id: ModelId) -> Option<Model3D> {
+        let slot = self.slots.get_mut(id.index).filter(|slot| slot.generation == id.generation)?;
+        let entry = slot.entry.take()?;
+        slot.generation += 1;
+        self.free.push(id.index);
+        Some(entry.model)
+    }
+
+    // Cambia la geometría de `id` conservando su transformación y visibilidad;
+    // devuelve el modelo anterior
+    pub fn replace(&mut self, id: ModelId, mut model: Model3D) -> Option<Model3D> {
+        let current = self.get_mut(id)?;
+        model.transform = current.transform;
+        Some(std::mem::replace(current, model))
+    }
+
+    pub fn get(&self, id: ModelId) -> Option<&Model3D> {
+        self.entry(id).map(|entry| &entry.model)
+    }
+
+    pub fn get_mut(&mut self, id: ModelId) -> Option<&mut Model3D> {
+        self.entry_mut(id).map(|entry| &mut entry.model)
+    }
+
+    // Devuelve false si el id ya no existe
+    pub fn set_visible(&mut self, id: ModelId, visible: bool) -> bool {
+        match self.entry_mut(id) {
+            Some(entry) => {
+                entry.visible = visible;
+                true
+            }
+            None => false,
+        }
+    }
+
+    pub fn is_visible(&self, id: ModelId) -> bool {
+        self.entry(id).is_some_and(|entry| entry.visible)
+    }
+
+    // Solo los modelos que se deben dibujar
+    pub fn visible_models(&self) -> impl Iterator<Item = &Model3D> + '_ {
+        self.slots.iter().filter_map(|slot| slot.entry.as_ref()).filter(|entry| entry.visible).map(|entry| &entry.model)
+    }
+
+    fn entry(&self, id: ModelId) -> Option<&Entry> {
+        self.slots.get(id.index).filter(|slot| slot.generation == id.generation)?.entry.as_ref()
+    }
+
+    fn entry_mut(&mut self, id: ModelId) -> Option<&mut Entry> {
+        self.slots.get_mut(id.index).filter(|slot| slot.generation == id.generation)?.entry.as_mut()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn removed_slots_are_reused_without_reviving_old_ids() {
+        let mut scene = Scene::new();
+        let first = scene.add_model(Model3D::cube(1.0));
+        let second = scene.add_model(Model3D::cube(2.0));
+        assert!(scene.remove(first).is_some());
+        assert!(scene.remove(first).is_none());
+
+        // El lugar de `first` se reutiliza y la escena no crece con cada arrastre
+        let third = scene.add_model(Model3D::uv_sphere(1.0, 4, 8));
+        assert_eq!(scene.slots.len(), 2);
+        assert_ne!(third, first);
+        assert!(scene.get(first).is_none() && scene.get_mut(first).is_none());
+        assert!(!scene.set_visible(first, false) && scene.is_visible(third));
+        assert!(scene.remove(first).is_none() && scene.get(third).is_some());
+        assert!(scene.replace(first, Model3D::cube(3.0)).is_none());
+
+        assert!(scene.set_visible(second, false));
+        assert_eq!(scene.visible_models().count(), 1);
+        assert_eq!(scene.get(third).unwrap().triangle_count(), Model3D::uv_sphere(1.0, 4, 8).triangle_count());
+    }
+}
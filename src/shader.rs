@@ -0,0 +1,713 @@
+// shader.rs
+// Etapas programables del pipeline. Un shader de vértices recibe cada vértice del
+// modelo y decide dónde queda: así se puede deformar la malla (ondas, ruido, hacerla
+// estallar a lo largo de las normales) sin tocar el recorte ni el rasterizador. Uno
+// de fragmentos da el color de cada píxel que pasa la prueba de profundidad
+//
+// Los dos reciben los `Uniforms` del modelo que se dibuja, donde `model_matrix` y
+// `normal_matrix` ya incluyen la transformación del modelo, y los `ShadingParams`
+// con las luces en espacio de cámara, como las normales transformadas, y el tiempo
+
+use std::sync::Arc;
+use nalgebra_glm::Vec3;
+use crate::color::Color;
+use crate::fragment::Fragment;
+use crate::light::{Lights, Shading, Surface};
+use crate::noise::{Fbm, Noise};
+use crate::texture::Texture;
+use crate::vertex::Vertex;
+use crate::{camera_position, ShadingParams, Uniforms};
+
+// Se llama una vez por vértice del modelo, desde varios hilos a la vez. Debe dejar en
+// el vértice que devuelve:
+// - `transformed_position` y `transformed_w`: la posición en coordenadas de recorte
+//   (x, y, z y w); la división por w y el viewport vienen después del recorte
+// - `position`: la posición en espacio de mundo, donde se evalúa la luz
+// - `transformed_normal`: la normal en espacio de cámara, de largo 1 (o cero si no tiene)
+// - `color` y el resto de los atributos, que se interpolan tal cual
+// `model_position` no: el pipeline la llena después con la posición original
+pub trait VertexShader: Send + Sync {
+    fn shade(&self, vertex: &Vertex, uniforms: &Uniforms, params: &ShadingParams) -> Vertex;
+
+    // Si los vértices quedan adentro de la caja envolvente del modelo transformada con
+    // las matrices: solo así `render` puede descartar el modelo entero mirando la caja.
+    // Un shader que desplaza vértices la dejaría chica
+    fn keeps_bounds(&self) -> bool {
+        false
+    }
+}
+
+// Lo que hace el pipeline sin shader propio: modelo -> mundo -> cámara -> recorte
+pub struct DefaultVertexShader;
+
+impl VertexShader for DefaultVertexShader {
+    fn shade(&self, vertex: &Vertex, uniforms: &Uniforms, _params: &ShadingParams) -> Vertex {
+        let position = nalgebra_glm::vec4(vertex.position.x, vertex.position.y, vertex.position.z, 1.0);
+        let clip = uniforms.projection_matrix * uniforms.view_matrix * uniforms.model_matrix * position;
+        let world = (uniforms.model_matrix * position).xyz();
+        let normal = (uniforms.normal_matrix * vertex.normal).try_normalize(f32::EPSILON).unwrap_or_else(Vec3::zeros);
+        Vertex {
+            position: world,
+            transformed_position: clip.xyz(),
+            transformed_w: clip.w,
+            transformed_normal: normal,
+            ..vertex.clone()
+        }
+    }
+
+    fn keeps_bounds(&self) -> bool {
+        true
+    }
+}
+
+// Se llama con cada fragmento de un triángulo que pasa la prueba de profundidad,
+// desde varios hilos a la vez. `None` lo descarta: no pinta ni guarda su profundidad,
+// así lo de atrás se ve por el agujero (recortes con prueba alfa)
+pub trait FragmentShader: Send + Sync {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, params: &ShadingParams) -> Option<Color>;
+
+    // Si espera el color de los vértices ya iluminado (Gouraud): así el pipeline
+    // ilumina cada vértice antes de interpolar
+    fn lit_vertices(&self) -> bool {
+        false
+    }
+}
+
+// La normal con que se ilumina un fragmento: si el modelo no trae normales mira
+// hacia el ojo, igual que la cara en flat shading
+fn lit_normal(fragment: &Fragment) -> Vec3 {
+    if fragment.normal == Vec3::zeros() { Vec3::z() } else { fragment.normal }
+}
+
+// El contorno de `params.rim` en un punto en espacio de cámara, o negro sin contorno.
+// La vista va del punto al ojo, que en ese espacio está en el origen
+fn rim_light(normal: &Vec3, position: &Vec3, params: &ShadingParams) -> Color {
+    match params.rim {
+        Some(rim) => rim.term(normal, &(-position).try_normalize(f32::EPSILON).unwrap_or_else(Vec3::z)),
+        None => Color::black(),
+    }
+}
+
+// Toda la cara con la misma luz sobre el color de sus vértices, más lo que no
+// depende de la luz
+pub struct FlatShader;
+
+impl FragmentShader for FlatShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, params: &ShadingParams) -> Option<Color> {
+        let rim = rim_light(&lit_normal(fragment), &camera_position(&uniforms.view_matrix, &fragment.world_position), params);
+        Some((fragment.color * fragment.face_light + params.lights.glow(&fragment.surface()) + rim).clamped())
+    }
+}
+
+// El color ya viene iluminado desde los vértices (`Shading::Gouraud`) y solo se interpola
+pub struct GouraudShader;
+
+impl FragmentShader for GouraudShader {
+    fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms, _params: &ShadingParams) -> Option<Color> {
+        Some(fragment.color)
+    }
+
+    fn lit_vertices(&self) -> bool {
+        true
+    }
+}
+
+// Para las pasadas que solo llenan el z-buffer, como el mapa de sombras: el color
+// no se mira
+pub struct DepthOnlyShader;
+
+impl FragmentShader for DepthOnlyShader {
+    fn shade(&self, _fragment: &Fragment, _uniforms: &Uniforms, _params: &ShadingParams) -> Option<Color> {
+        Some(Color::black())
+    }
+}
+
+// La luz de cada píxel, con la normal interpolada y el brillo de Phong o de Blinn-Phong
+pub struct PhongShader {
+    pub blinn: bool,
+}
+
+impl FragmentShader for PhongShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, params: &ShadingParams) -> Option<Color> {
+        let position = camera_position(&uniforms.view_matrix, &fragment.world_position);
+        let lighting = if self.blinn { Lights::blinn_phong } else { Lights::phong };
+        let normal = lit_normal(fragment);
+        Some((lighting(&params.lights, &fragment.surface(), &normal, &position) + rim_light(&normal, &position, params)).clamped())
+    }
+}
+
+// Dibujo animado: la luz difusa se lleva al nivel de `bands` que queda justo debajo
+// y recién ahí se multiplica por el color, así todos los materiales cambian de tono
+// en los mismos lugares. El brillo, si hay, es una mancha blanca de borde duro donde
+// el de Blinn-Phong pasa de `highlight`
+pub struct ToonShader {
+    pub bands: Vec<f32>,        // Niveles de luz, de menor a mayor
+    pub highlight: Option<f32>, // Umbral del brillo, entre 0 y 1; `None` lo apaga
+}
+
+impl ToonShader {
+    // `count` niveles parejos de 0 a 1 (al menos 2)
+    pub fn with_bands(count: usize) -> Self {
+        let count = count.max(2);
+        ToonShader {
+            bands: (0..count).map(|i| i as f32 / (count - 1) as f32).collect(),
+            ..ToonShader::default()
+        }
+    }
+}
+
+impl Default for ToonShader {
+    fn default() -> Self {
+        ToonShader { bands: vec![0.0, 0.4, 0.7, 1.0], highlight: Some(0.5) }
+    }
+}
+
+impl FragmentShader for ToonShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, params: &ShadingParams) -> Option<Color> {
+        let position = camera_position(&uniforms.view_matrix, &fragment.world_position);
+        let surface = fragment.surface();
+        // La intensidad es el canal más fuerte: el nivel cambia cuánta luz llega pero
+        // no su color
+        let normal = lit_normal(fragment);
+        let light = params.lights.diffuse(&normal, &position);
+        let intensity = light.r.max(light.g).max(light.b);
+        let level = self.bands.iter().rev().find(|&&band| band <= intensity).copied().unwrap_or(0.0);
+        let banded = if intensity > 0.0 { light * (level / intensity) } else { Color::black() };
+        let mut color = surface.diffuse * banded + params.lights.glow(&surface);
+
+        if let Some(threshold) = self.highlight {
+            // Solo el brillo: una superficie sin difuso ni ambiente y con especular blanco
+            let white = Color::from_rgb([1.0; 3]);
+            let shine_only = Surface { diffuse: Color::black(), ambient: Color::black(), emissive: Color::black(), specular: white, ..surface };
+            let shine = params.lights.blinn_phong(&shine_only, &normal, &position);
+            if shine.r.max(shine.g).max(shine.b) > threshold {
+                color = color + white;
+            }
+        }
+        Some((color + rim_light(&normal, &position, params)).clamped())
+    }
+}
+
+// En qué espacio se muestran las normales con `NormalShader`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalSpace {
+    World, // No cambia al mover la cámara: sirve para ver si están bien orientadas
+    View,  // Las que usa la luz, ya pasadas por la matriz de normales
+}
+
+// Depuración: cada componente de la normal, de -1 a 1, como un canal de 0 a 1, sin
+// luces. Los fragmentos sin normal salen magenta, que ninguna normal da
+pub struct NormalShader {
+    pub space: NormalSpace,
+}
+
+impl FragmentShader for NormalShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, _params: &ShadingParams) -> Option<Color> {
+        if fragment.normal == Vec3::zeros() {
+            return Some(Color::from_hex(0xFF00FF));
+        }
+        let normal = match self.space {
+            // La vista es una rotación más una traslación: su inversa es la transpuesta
+            NormalSpace::World => nalgebra_glm::mat4_to_mat3(&uniforms.view_matrix).transpose() * fragment.normal,
+            NormalSpace::View => fragment.normal,
+        };
+        let channel = |value: f32| value * 0.5 + 0.5;
+        Some(Color::from_rgb([channel(normal.x), channel(normal.y), channel(normal.z)]).clamped())
+    }
+}
+
+// Depuración de la profundidad: la distancia del fragmento a la cámara en grises,
+// blanco en el plano cercano y negro en el lejano, sin luces. Es la misma escala que
+// Shift+Z entre los planos, pero dentro del relleno: se puede ver junto a las aristas
+pub struct DepthShader;
+
+impl FragmentShader for DepthShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, _params: &ShadingParams) -> Option<Color> {
+        // Los planos salen de llevar la profundidad del recorte (-1 y 1) de vuelta a la cámara
+        let inverse = uniforms.projection_matrix.try_inverse()?;
+        let plane = |z: f32| {
+            let eye = inverse * nalgebra_glm::vec4(0.0, 0.0, z, 1.0);
+            -eye.z / eye.w
+        };
+        let (near, far) = (plane(-1.0), plane(1.0));
+        let distance = -camera_position(&uniforms.view_matrix, &fragment.world_position).z;
+        let gray = (1.0 - (distance - near) / (far - near).max(f32::EPSILON)).clamp(0.0, 1.0);
+        Some(Color::from_rgb([gray; 3]))
+    }
+}
+
+// Depuración de coordenadas de textura: un tablero de `squares` x `squares` cuadros
+// por cada unidad de UV, sin luces. Un cuadro estirado o un tablero al revés delatan
+// UV mal asignadas. Con `tint` el rojo sigue a U y el verde a V, y lo que queda fuera
+// de [0, 1] se pone azul. Los fragmentos con UV (0, 0) exacto salen naranja: dentro
+// de un triángulo solo pasa si ninguno de sus vértices trae UV
+pub struct UvCheckerShader {
+    pub squares: f32,
+    pub tint: bool,
+}
+
+impl Default for UvCheckerShader {
+    fn default() -> Self {
+        UvCheckerShader { squares: 8.0, tint: false }
+    }
+}
+
+impl FragmentShader for UvCheckerShader {
+    fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms, _params: &ShadingParams) -> Option<Color> {
+        let uv = fragment.tex_coords;
+        if uv == nalgebra_glm::Vec2::zeros() {
+            return Some(Color::from_hex(0xFF8000));
+        }
+        let parity = ((uv.x * self.squares).floor() + (uv.y * self.squares).floor()).rem_euclid(2.0);
+        let square = if parity < 1.0 { 0.9 } else { 0.3 };
+        let color = if self.tint {
+            let outside = !(0.0..=1.0).contains(&uv.x) || !(0.0..=1.0).contains(&uv.y);
+            Color::from_rgb([uv.x.clamp(0.0, 1.0), uv.y.clamp(0.0, 1.0), if outside { 1.0 } else { 0.0 }]) * square
+        } else {
+            Color::from_rgb([square; 3])
+        };
+        Some(color)
+    }
+}
+
+// De dónde toman su posición los patrones procedurales
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PatternSpace {
+    #[default]
+    Model, // Antes de mover el modelo: el patrón se pega a la superficie
+    World, // El modelo se mueve a través de un patrón quieto
+    Uv,    // Las coordenadas de textura, con Z en 0: sigue a la malla como una textura
+}
+
+impl PatternSpace {
+    fn position(self, fragment: &Fragment) -> Vec3 {
+        match self {
+            PatternSpace::Model => fragment.model_position,
+            PatternSpace::World => fragment.world_position,
+            PatternSpace::Uv => Vec3::new(fragment.tex_coords.x, fragment.tex_coords.y, 0.0),
+        }
+    }
+}
+
+// Colores repartidos entre 0 y 1; entre dos paradas se mezclan en línea recta y
+// fuera de las puntas queda el de la punta
+#[derive(Debug, Clone)]
+pub struct ColorRamp {
+    pub stops: Vec<(f32, Color)>, // De menor a mayor
+}
+
+impl ColorRamp {
+    pub fn sample(&self, t: f32) -> Color {
+        let (first, last) = match (self.stops.first(), self.stops.last()) {
+            (Some(first), Some(last)) => (first, last),
+            _ => return Color::black(),
+        };
+        if t <= first.0 {
+            return first.1;
+        }
+        self.stops
+            .windows(2)
+            .find(|pair| t <= pair[1].0)
+            .map(|pair| {
+                let ((start, from), (end, to)) = (pair[0], pair[1]);
+                from.lerp(to, if end > start { (t - start) / (end - start) } else { 1.0 })
+            })
+            .unwrap_or(last.1)
+    }
+}
+
+// El color de un patrón como difuso de la superficie, con la luz de Blinn-Phong
+fn lit_pattern(pattern: Color, fragment: &Fragment, uniforms: &Uniforms, params: &ShadingParams) -> Color {
+    let position = camera_position(&uniforms.view_matrix, &fragment.world_position);
+    let surface = Surface { diffuse: pattern, ..fragment.material };
+    let normal = lit_normal(fragment);
+    (params.lights.blinn_phong(&surface, &normal, &position) + rim_light(&normal, &position, params)).clamped()
+}
+
+const CELL_OFFSET: f32 = 1e-3;
+
+// Tablero en 3D: cubos de lado `size` que alternan los dos colores. No necesita UV
+pub struct CheckerShader {
+    pub size: f32,
+    pub colors: [Color; 2],
+    pub space: PatternSpace,
+}
+
+impl Default for CheckerShader {
+    fn default() -> Self {
+        CheckerShader { size: 0.1, colors: [Color::from_hex(0xE0E0E0), Color::from_hex(0x303030)], space: PatternSpace::Model }
+    }
+}
+
+impl FragmentShader for CheckerShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, params: &ShadingParams) -> Option<Color> {
+        // Corrida un poco: una cara justo sobre el borde entre dos cubos (el plano X = 0
+        // de un modelo centrado) tomaría uno u otro color según el redondeo de cada píxel
+        let cell = self.space.position(fragment) / self.size + Vec3::repeat(CELL_OFFSET);
+        let parity = (cell.x.floor() + cell.y.floor() + cell.z.floor()).rem_euclid(2.0);
+        Some(lit_pattern(self.colors[(parity >= 1.0) as usize], fragment, uniforms, params))
+    }
+}
+
+// Franjas de ancho `width` perpendiculares a `direction` (de largo 1), alternando
+// los dos colores
+pub struct StripesShader {
+    pub direction: Vec3,
+    pub width: f32,
+    pub colors: [Color; 2],
+    pub space: PatternSpace,
+}
+
+impl Default for StripesShader {
+    fn default() -> Self {
+        StripesShader {
+            direction: Vec3::y(),
+            width: 0.05,
+            colors: [Color::from_hex(0xE0B040), Color::from_hex(0x204080)],
+            space: PatternSpace::Model,
+        }
+    }
+}
+
+impl FragmentShader for StripesShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, params: &ShadingParams) -> Option<Color> {
+        let stripe = (self.direction.dot(&self.space.position(fragment)) / self.width + CELL_OFFSET).floor().rem_euclid(2.0);
+        Some(lit_pattern(self.colors[(stripe >= 1.0) as usize], fragment, uniforms, params))
+    }
+}
+
+// Qué figura arma `NoiseShader` con el ruido
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NoisePattern {
+    Marble, // Vetas: ondas a lo largo de X que el ruido tuerce
+    Wood,   // Anillos alrededor del eje Y, deformados por el ruido
+    Granite, // Granos del ruido de valor, con bordes que el ruido fractal mezcla
+}
+
+// Patrones de ruido fractal. `frequency` es cuántas vetas o anillos entran en una
+// unidad, `scale` lo mismo para el ruido y `turbulence` cuánto de una veta lo mueve
+// el ruido; el resultado, de 0 a 1, elige el color en `ramp`
+pub struct NoiseShader {
+    pub pattern: NoisePattern,
+    pub frequency: f32,
+    pub scale: f32,
+    pub turbulence: f32,
+    pub ramp: ColorRamp,
+    pub noise: Noise,
+    pub fbm: Fbm,
+    pub space: PatternSpace,
+}
+
+impl NoiseShader {
+    pub fn marble() -> Self {
+        NoiseShader {
+            pattern: NoisePattern::Marble,
+            frequency: 2.0,
+            scale: 3.0,
+            turbulence: 1.0,
+            ramp: ColorRamp {
+                stops: vec![(0.0, Color::from_hex(0x404050)), (0.3, Color::from_hex(0xB0B0B8)), (1.0, Color::from_hex(0xF4F4F0))],
+            },
+            noise: Noise::new(1),
+            fbm: Fbm::default(),
+            space: PatternSpace::Model,
+        }
+    }
+
+    pub fn wood() -> Self {
+        NoiseShader {
+            pattern: NoisePattern::Wood,
+            frequency: 10.0,
+            scale: 2.0,
+            turbulence: 0.5,
+            ramp: ColorRamp { stops: vec![(0.0, Color::from_hex(0x6B3A1A)), (0.7, Color::from_hex(0xB07838)), (1.0, Color::from_hex(0x8A5228))] },
+            noise: Noise::new(2),
+            fbm: Fbm { octaves: 3, ..Fbm::default() },
+            space: PatternSpace::Model,
+        }
+    }
+
+    pub fn granite() -> Self {
+        NoiseShader {
+            pattern: NoisePattern::Granite,
+            frequency: 40.0,
+            scale: 8.0,
+            turbulence: 0.6,
+            ramp: ColorRamp {
+                stops: vec![(0.0, Color::from_hex(0x302C2C)), (0.5, Color::from_hex(0x8C8080)), (0.8, Color::from_hex(0xC8B8B0)), (1.0, Color::from_hex(0xF0E8E0))],
+            },
+            noise: Noise::new(3),
+            fbm: Fbm { octaves: 2, ..Fbm::default() },
+            space: PatternSpace::Model,
+        }
+    }
+}
+
+impl FragmentShader for NoiseShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, params: &ShadingParams) -> Option<Color> {
+        let position = self.space.position(fragment);
+        let p = position * self.scale;
+        // Las UV no tienen Z: alcanza con el ruido 2D, que cuesta la mitad de esquinas
+        let flat = self.space == PatternSpace::Uv;
+        let noise = if flat { self.fbm.sample2(&self.noise, p.x, p.y) } else { self.fbm.sample3(&self.noise, p.x, p.y, p.z) };
+        let t = match self.pattern {
+            NoisePattern::Marble => {
+                let veins = self.frequency * position.x + self.turbulence * noise;
+                0.5 + 0.5 * (veins * std::f32::consts::TAU).sin()
+            }
+            NoisePattern::Wood => {
+                let rings = self.frequency * (position.x * position.x + position.z * position.z).sqrt() + self.turbulence * noise;
+                rings - rings.floor()
+            }
+            NoisePattern::Granite => {
+                // El de valor deja bloques de un solo tono: aquí hacen de granos
+                let q = position * self.frequency;
+                let grain = if flat { self.noise.value2(q.x, q.y) } else { self.noise.value3(q.x, q.y, q.z) };
+                (grain + 0.5 * self.turbulence * noise).clamp(0.0, 1.0)
+            }
+        };
+        Some(lit_pattern(self.ramp.sample(t), fragment, uniforms, params))
+    }
+}
+
+// Ondas que corren a lo largo de X: cada vértice se mueve sobre su normal
+// `amplitude * sen(frequency * x + speed * time)`, medido en el modelo. Sobre un
+// plano acostado es `position.y += ...`. Usa el tiempo de `Uniforms`, no los cuadros,
+// así la onda va igual de rápido a cualquier FPS. Las normales se inclinan con la
+// pendiente; en un plano quedan exactas
+pub struct WaveVertexShader {
+    pub amplitude: f32,
+    pub frequency: f32,
+    pub speed: f32, // Radianes por segundo
+}
+
+impl Default for WaveVertexShader {
+    fn default() -> Self {
+        WaveVertexShader { amplitude: 0.04, frequency: 12.0, speed: 3.0 }
+    }
+}
+
+impl VertexShader for WaveVertexShader {
+    fn shade(&self, vertex: &Vertex, uniforms: &Uniforms, params: &ShadingParams) -> Vertex {
+        let phase = self.frequency * vertex.position.x + self.speed * params.time;
+        let normal = vertex.normal.try_normalize(f32::EPSILON).unwrap_or_else(Vec3::zeros);
+        // Pendiente de la onda: la normal se inclina hacia atrás en la dirección de X
+        // que queda sobre la superficie
+        let slope = self.amplitude * self.frequency * phase.cos();
+        let along = Vec3::x() - normal * normal.x;
+        let displaced = Vertex {
+            position: vertex.position + normal * (self.amplitude * phase.sin()),
+            normal: normal - along * slope,
+            ..vertex.clone()
+        };
+        DefaultVertexShader.shade(&displaced, uniforms, params)
+    }
+}
+
+// Corre las coordenadas de textura `velocity` unidades de UV por segundo y deja el
+// color a `inner`
+pub struct ScrollShader {
+    pub inner: Arc<dyn FragmentShader>,
+    pub velocity: nalgebra_glm::Vec2,
+}
+
+impl FragmentShader for ScrollShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, params: &ShadingParams) -> Option<Color> {
+        let scrolled = Fragment { tex_coords: fragment.tex_coords + self.velocity * params.time, ..*fragment };
+        self.inner.shade(&scrolled, uniforms, params)
+    }
+
+    fn lit_vertices(&self) -> bool {
+        self.inner.lit_vertices()
+    }
+}
+
+// Agrega a la luz propia del material `color` con un brillo que sube y baja
+// `speed` veces por segundo, y deja el resto a `inner`. Solo se nota con los shaders
+// que iluminan por píxel o por cara
+pub struct PulseShader {
+    pub inner: Arc<dyn FragmentShader>,
+    pub color: Color,
+    pub speed: f32,
+}
+
+impl FragmentShader for PulseShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, params: &ShadingParams) -> Option<Color> {
+        let pulse = 0.5 + 0.5 * (params.time * self.speed * std::f32::consts::TAU).sin();
+        let material = Surface { emissive: fragment.material.emissive + self.color * pulse, ..fragment.material };
+        self.inner.shade(&Fragment { material, ..*fragment }, uniforms, params)
+    }
+
+    fn lit_vertices(&self) -> bool {
+        self.inner.lit_vertices()
+    }
+}
+
+// Agua para la demo de las ondas: ruido azul sobre las UV que se va corriendo
+pub fn water_shader() -> ScrollShader {
+    let ramp = ColorRamp {
+        stops: vec![(0.0, Color::from_hex(0x0A2A5A)), (0.6, Color::from_hex(0x1E64A0)), (1.0, Color::from_hex(0xA0D8F0))],
+    };
+    let noise = NoiseShader { frequency: 3.0, scale: 6.0, turbulence: 0.8, ramp, space: PatternSpace::Uv, ..NoiseShader::marble() };
+    ScrollShader { inner: Arc::new(noise), velocity: nalgebra_glm::Vec2::new(0.05, 0.02) }
+}
+
+// Esferas pintadas de antemano para `MatcapShader`: cada punto (u, v) del cuadrado
+// es el color que muestra la esfera donde su normal en cámara apunta hacia
+// (2u - 1, 2v - 1). Sin imágenes todavía, se calculan al vuelo
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Matcap {
+    Chrome, // Refleja un cielo claro sobre un suelo oscuro, con el horizonte marcado
+    Clay,   // Arcilla mate iluminada desde arriba a la izquierda
+}
+
+impl Matcap {
+    pub fn sample(self, u: f32, v: f32) -> Color {
+        let (x, y) = (u * 2.0 - 1.0, v * 2.0 - 1.0);
+        let normal = Vec3::new(x, y, (1.0 - x * x - y * y).max(0.0).sqrt());
+        match self {
+            Matcap::Chrome => {
+                // Lo que ve el ojo reflejado en la esfera: hacia arriba, cielo; hacia abajo, suelo
+                let reflected = normal * (2.0 * normal.z) - Vec3::z();
+                let (sky, horizon, ground) = (Color::from_hex(0xC8DCF0), Color::from_hex(0xFFFFFF), Color::from_hex(0x302820));
+                if reflected.y >= 0.0 {
+                    horizon.lerp(sky, reflected.y.powf(0.5))
+                } else {
+                    Color::from_hex(0x605040).lerp(ground, (-reflected.y).powf(0.3))
+                }
+            }
+            Matcap::Clay => {
+                let light = Vec3::new(-0.5, 0.6, 0.6).normalize();
+                let diffuse = normal.dot(&light).max(0.0);
+                let shine = (normal.dot(&(light + Vec3::z()).normalize()).max(0.0)).powf(20.0) * 0.15;
+                Color::from_hex(0xC08870) * (0.25 + 0.75 * diffuse) + Color::from_rgb([shine; 3])
+            }
+        }
+        .clamped()
+    }
+}
+
+// Material sin luces: el color sale de la matcap según la normal en espacio de cámara
+// (la de `normal_matrix`, que ya incluye la vista). Las normales de espaldas al ojo
+// se llevan al borde del círculo, que es lo más cerca que tiene la esfera, y las UV
+// se recortan a [0, 1]: nunca dan la vuelta al otro lado
+pub struct MatcapShader {
+    pub matcap: Matcap,
+}
+
+impl FragmentShader for MatcapShader {
+    fn shade(&self, fragment: &Fragment, _uniforms: &Uniforms, _params: &ShadingParams) -> Option<Color> {
+        let normal = lit_normal(fragment);
+        let mut xy = normal.xy();
+        if normal.z < 0.0 {
+            xy = xy.try_normalize(f32::EPSILON).unwrap_or_else(|| nalgebra_glm::Vec2::new(0.0, -1.0));
+        }
+        let (u, v) = ((xy.x * 0.5 + 0.5).clamp(0.0, 1.0), (xy.y * 0.5 + 0.5).clamp(0.0, 1.0));
+        Some(self.matcap.sample(u, v))
+    }
+}
+
+// La textura en las UV interpoladas como color difuso, con la luz de Blinn-Phong: el
+// texel queda multiplicado por lo que ilumina cada luz. La textura se comparte entre
+// los hilos y con el registro sin copiarla
+pub struct TextureShader {
+    pub texture: Arc<Texture>,
+}
+
+impl FragmentShader for TextureShader {
+    fn shade(&self, fragment: &Fragment, uniforms: &Uniforms, params: &ShadingParams) -> Option<Color> {
+        let uv = fragment.tex_coords;
+        Some(lit_pattern(self.texture.sample(uv.x, uv.y), fragment, uniforms, params))
+    }
+}
+
+// El shader de fragmentos de cada modo de luz, cuando `Uniforms` no trae uno propio
+pub fn builtin(shading: Shading) -> &'static dyn FragmentShader {
+    match shading {
+        Shading::Flat => &FlatShader,
+        Shading::Gouraud => &GouraudShader,
+        Shading::BlinnPhong => &PhongShader { blinn: true },
+        Shading::Phong => &PhongShader { blinn: false },
+    }
+}
+
+// Shaders de fragmentos con nombre, para elegirlos mientras el programa corre (las
+// teclas 1 a 9 eligen los nueve primeros). Se comparten con `Uniforms` sin copiarlos
+#[derive(Clone)]
+pub struct ShaderRegistry {
+    shaders: Vec<(String, Arc<dyn FragmentShader>)>,
+}
+
+impl ShaderRegistry {
+    pub fn new() -> Self {
+        ShaderRegistry { shaders: Vec::new() }
+    }
+
+    // Agrega `shader` al final y devuelve su índice
+    pub fn register_shader(&mut self, name: &str, shader: Box<dyn FragmentShader>) -> usize {
+        self.shaders.push((name.to_string(), Arc::from(shader)));
+        self.shaders.len() - 1
+    }
+
+    pub fn len(&self) -> usize {
+        self.shaders.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shaders.is_empty()
+    }
+
+    pub fn get(&self, index: usize) -> Option<(&str, &Arc<dyn FragmentShader>)> {
+        self.shaders.get(index).map(|(name, shader)| (name.as_str(), shader))
+    }
+
+    // Cambia el shader `index` por otro con el mismo nombre, por ejemplo para ajustar
+    // sus parámetros; los cuadros ya dibujados no cambian
+    pub fn replace(&mut self, index: usize, shader: Box<dyn FragmentShader>) {
+        if let Some(entry) = self.shaders.get_mut(index) {
+            entry.1 = Arc::from(shader);
+        }
+    }
+
+    // Nombre del shader `index`, o "" si no hay tantos
+    pub fn name(&self, index: usize) -> &str {
+        self.get(index).map_or("", |(name, _)| name)
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.shaders.iter().position(|(registered, _)| registered == name)
+    }
+}
+
+// Los modos de luz de `Shading`, en el mismo orden y con su nombre, y después los
+// que no tienen un modo propio
+impl Default for ShaderRegistry {
+    fn default() -> Self {
+        let mut registry = ShaderRegistry::new();
+        registry.register_shader(Shading::Flat.name(), Box::new(FlatShader));
+        registry.register_shader(Shading::Gouraud.name(), Box::new(GouraudShader));
+        registry.register_shader(Shading::BlinnPhong.name(), Box::new(PhongShader { blinn: true }));
+        registry.register_shader(Shading::Phong.name(), Box::new(PhongShader { blinn: false }));
+        registry.register_shader("Toon", Box::new(ToonShader::default()));
+        registry.register_shader("Normales (mundo)", Box::new(NormalShader { space: NormalSpace::World }));
+        registry.register_shader("Normales (cámara)", Box::new(NormalShader { space: NormalSpace::View }));
+        registry.register_shader("Profundidad", Box::new(DepthShader));
+        registry.register_shader("UV", Box::new(UvCheckerShader::default()));
+        registry.register_shader("UV (teñido)", Box::new(UvCheckerShader { tint: true, ..UvCheckerShader::default() }));
+        registry.register_shader("Tablero 3D", Box::new(CheckerShader::default()));
+        registry.register_shader("Franjas", Box::new(StripesShader::default()));
+        // Diagonales y quietas en el mundo: al girar el modelo las franjas lo recorren
+        let diagonal = Vec3::new(1.0, 1.0, 0.0).normalize();
+        let world = StripesShader { direction: diagonal, width: 0.1, space: PatternSpace::World, ..StripesShader::default() };
+        registry.register_shader("Franjas (mundo)", Box::new(world));
+        registry.register_shader("Mármol", Box::new(NoiseShader::marble()));
+        registry.register_shader("Madera", Box::new(NoiseShader::wood()));
+        registry.register_shader("Granito", Box::new(NoiseShader::granite()));
+        registry.register_shader("Agua", Box::new(water_shader()));
+        let warm = Color::from_rgb([0.35, 0.12, 0.0]);
+        registry.register_shader("Pulso", Box::new(PulseShader { inner: Arc::new(PhongShader { blinn: true }), color: warm, speed: 0.5 }));
+        registry.register_shader("Matcap (cromo)", Box::new(MatcapShader { matcap: Matcap::Chrome }));
+        registry.register_shader("Matcap (arcilla)", Box::new(MatcapShader { matcap: Matcap::Clay }));
+        registry
+    }
+}
@@ -0,0 +1,34 @@
+// shadow.rs
+// Mapa de sombras de una luz direccional: la profundidad de la escena vista desde la
+// luz con una proyección ortográfica. Un punto está en sombra si, visto desde la
+// luz, hay algo más cerca en su píxel del mapa
+//
+// `bias` es cuánto más lejos que lo guardado debe estar el punto para contar como
+// tapado, en profundidad del mapa (de 0 a 1). Muy chico y las caras se sombrean a sí
+// mismas en franjas (acné); muy grande y la sombra se despega del objeto que la
+// hace (peter-panning)
+
+use nalgebra_glm::{Mat4, Vec3};
+
+#[derive(Debug, Clone)]
+pub struct ShadowMap {
+    pub size: usize,        // Lado del mapa en píxeles
+    pub depths: Vec<f32>,   // De 0 (junto a la luz) a 1, o infinito donde no hay nada
+    pub light_matrix: Mat4, // Mundo -> píxel del mapa en X e Y, y profundidad en Z
+    pub bias: f32,
+}
+
+impl ShadowMap {
+    // Cuánto de la luz llega a `point`, dado en el espacio del que parte `to_map`
+    // (la `light_matrix` o la misma pasada a otro espacio): 0 en sombra y 1 si no.
+    // Lo que cae fuera del mapa queda iluminado
+    pub fn visibility(&self, to_map: &Mat4, point: &Vec3) -> f32 {
+        let mapped = to_map * nalgebra_glm::vec4(point.x, point.y, point.z, 1.0);
+        let (x, y) = (mapped.x.floor(), mapped.y.floor());
+        if x < 0.0 || y < 0.0 || x >= self.size as f32 || y >= self.size as f32 {
+            return 1.0;
+        }
+        let stored = self.depths[y as usize * self.size + x as usize];
+        if mapped.z - self.bias > stored { 0.0 } else { 1.0 }
+    }
+}
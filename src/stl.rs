@@ -0,0 +1,239 @@
+// stl.rs
+
+use std::fmt;
+use std::fs;
+use std::io;
+use nalgebra_glm::Vec3;
+use crate::obj::{parse_floats, ParseError};
+
+#[derive(Debug)]
+pub enum StlError {
+    Io(io::Error),
+    // Error en una línea de un STL ASCII (`line` empieza en 1)
+    Parse { line: usize, content: String, reason: ParseError },
+    // Un STL binario con menos bytes de los que anuncia su cantidad de triángulos
+    Truncated { expected: usize, found: usize },
+}
+
+impl fmt::Display for StlError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            StlError::Io(err) => write!(f, "error de lectura: {}", err),
+            StlError::Parse { line, content, reason } => {
+                write!(f, "línea {}: {} en `{}`", line, reason, content)
+            }
+            StlError::Truncated { expected, found } => {
+                write!(f, "archivo truncado: se esperaban {} bytes y hay {}", expected, found)
+            }
+        }
+    }
+}
+
+impl std::error::Error for StlError {}
+
+impl From<io::Error> for StlError {
+    fn from(err: io::Error) -> Self {
+        StlError::Io(err)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct StlTriangle {
+    pub normal: Vec3, // Normal de la faceta tal como viene en el archivo (puede ser cero)
+    pub vertices: [Vec3; 3],
+}
+
+pub struct Stl {
+    pub name: String,
+    pub triangles: Vec<StlTriangle>,
+}
+
+// Tamaño de la cabecera binaria y de cada triángulo (normal + 3 vértices + atributo)
+const HEADER_SIZE: usize = 84;
+const TRIANGLE_SIZE: usize = 50;
+
+impl Stl {
+    // Cargar un .stl en formato binario o ASCII
+    pub fn load(filename: &str) -> Result<Self, StlError> {
+        let bytes = fs::read(filename)?;
+        Self::parse(&bytes)
+    }
+
+    pub fn parse(bytes: &[u8]) -> Result<Self, StlError> {
+        // Muchos STL binarios también empiezan con "solid" en su cabecera de 80 bytes,
+        // así que primero se comprueba si el tamaño coincide con el conteo binario
+        if binary_size(bytes) == Some(bytes.len()) {
+            return Self::parse_binary(bytes);
+        }
+
+        // Un STL ASCII nunca tiene bytes nulos; uno binario cortado casi siempre (el
+        // relleno de la cabecera, los ceros de los floats)
+        match std::str::from_utf8(bytes) {
+            Ok(source) if source.trim_start().starts_with("solid") && !source.contains('\0') => Self::parse_ascii(source),
+            _ => Self::parse_binary(bytes),
+        }
+    }
+
+    fn parse_binary(bytes: &[u8]) -> Result<Self, StlError> {
+        let expected = binary_size(bytes).ok_or(StlError::Truncated { expected: HEADER_SIZE, found: bytes.len() })?;
+        if bytes.len() < expected {
+            return Err(StlError::Truncated { expected, found: bytes.len() });
+        }
+
+        let read_vec3 = |offset: usize| {
+            let component = |i: usize| {
+                let start = offset + i * 4;
+                f32::from_le_bytes([bytes[start], bytes[start + 1], bytes[start + 2], bytes[start + 3]])
+            };
+            Vec3::new(component(0), component(1), component(2))
+        };
+
+        let count = (expected - HEADER_SIZE) / TRIANGLE_SIZE;
+        let triangles = (0..count)
+            .map(|i| {
+                let offset = HEADER_SIZE + i * TRIANGLE_SIZE;
+                StlTriangle {
+                    normal: read_vec3(offset),
+                    vertices: [read_vec3(offset + 12), read_vec3(offset + 24), read_vec3(offset + 36)],
+                }
+            })
+            .collect();
+
+        Ok(Stl { name: String::from("default"), triangles })
+    }
+
+    fn parse_ascii(source: &str) -> Result<Self, StlError> {
+        let mut name = String::from("default");
+        let mut triangles = Vec::new();
+        let mut normal = Vec3::zeros();
+        let mut vertices = Vec::with_capacity(3);
+
+        for (number, content) in source.lines().enumerate() {
+            let error = |reason| StlError::Parse { line: number + 1, content: content.to_string(), reason };
+            let mut tokens = content.split_whitespace();
+            let Some(keyword) = tokens.next() else { continue };
+
+            match keyword {
+                "solid" => {
+                    let solid = tokens.collect::<Vec<_>>().join(" ");
+                    if !solid.is_empty() {
+                        name = solid;
+                    }
+                }
+                "facet" => {
+                    // `facet normal nx ny nz`
+                    let [x, y, z] = parse_floats::<3>(tokens.skip(1), 3).map_err(error)?;
+                    normal = Vec3::new(x, y, z);
+                    vertices.clear();
+                }
+                "vertex" => {
+                    let [x, y, z] = parse_floats::<3>(tokens, 3).map_err(error)?;
+                    vertices.push(Vec3::new(x, y, z));
+                }
+                "endfacet" => {
+                    if vertices.len() != 3 {
+                        return Err(error(ParseError::InvalidFacet(vertices.len())));
+                    }
+                    triangles.push(StlTriangle { normal, vertices: [vertices[0], vertices[1], vertices[2]] });
+                    vertices.clear();
+                }
+                // `outer loop`, `endloop` y `endsolid` no aportan datos
+                _ => {}
+            }
+        }
+
+        Ok(Stl { name, triangles })
+    }
+}
+
+// Tamaño total que debería tener un STL binario según el conteo de su cabecera
+fn binary_size(bytes: &[u8]) -> Option<usize> {
+    let count = bytes.get(80..HEADER_SIZE)?;
+    let count = u32::from_le_bytes([count[0], count[1], count[2], count[3]]) as usize;
+    count.checked_mul(TRIANGLE_SIZE)?.checked_add(HEADER_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII: &str = "solid pieza de prueba
+  facet normal 0 0 1
+    outer loop
+      vertex 0 0 0
+      vertex 1 0 0
+      vertex 0 1 0
+    endloop
+  endfacet
+  facet normal 0 0 -1
+    outer loop
+      vertex 0 0 0
+      vertex 0 1 0
+      vertex 1 0 0
+    endloop
+  endfacet
+endsolid pieza de prueba
+";
+
+    // STL binario con `triangles` (normal y vértices); la cabecera empieza con
+    // "solid" a propósito, como la de muchos exportadores
+    fn binary(triangles: &[[[f32; 3]; 4]]) -> Vec<u8> {
+        let mut bytes = b"solid binario".to_vec();
+        bytes.resize(80, 0);
+        bytes.extend((triangles.len() as u32).to_le_bytes());
+        for triangle in triangles {
+            for value in triangle.iter().flatten() {
+                bytes.extend(value.to_le_bytes());
+            }
+            bytes.extend([0, 0]);
+        }
+        bytes
+    }
+
+    #[test]
+    fn parses_ascii() {
+        let stl = Stl::parse(ASCII.as_bytes()).unwrap();
+        assert_eq!(stl.name, "pieza de prueba");
+        assert_eq!(stl.triangles.len(), 2);
+        assert_eq!(stl.triangles[0].normal, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(stl.triangles[1].normal, Vec3::new(0.0, 0.0, -1.0));
+        assert_eq!(stl.triangles[1].vertices, [Vec3::zeros(), Vec3::new(0.0, 1.0, 0.0), Vec3::new(1.0, 0.0, 0.0)]);
+    }
+
+    #[test]
+    fn parses_binary_even_with_a_solid_header() {
+        let bytes = binary(&[
+            [[0.0, 0.0, 1.0], [0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [0.0, 1.0, 0.0]],
+            [[0.0, 0.0, 0.0], [1.0, 2.0, 3.0], [4.0, 5.0, 6.0], [7.0, 8.0, 9.0]],
+        ]);
+        assert_eq!(bytes.len(), HEADER_SIZE + 2 * TRIANGLE_SIZE);
+
+        let stl = Stl::parse(&bytes).unwrap();
+        assert_eq!(stl.name, "default");
+        assert_eq!(stl.triangles.len(), 2);
+        assert_eq!(stl.triangles[0].normal, Vec3::new(0.0, 0.0, 1.0));
+        assert_eq!(stl.triangles[0].vertices[1], Vec3::new(1.0, 0.0, 0.0));
+        assert_eq!(stl.triangles[1].normal, Vec3::zeros());
+        assert_eq!(stl.triangles[1].vertices, [Vec3::new(1.0, 2.0, 3.0), Vec3::new(4.0, 5.0, 6.0), Vec3::new(7.0, 8.0, 9.0)]);
+    }
+
+    #[test]
+    fn truncated_binary_is_an_error() {
+        let mut bytes = binary(&[[[0.0; 3]; 4]; 3]);
+        bytes.truncate(bytes.len() - 10);
+        let expected = HEADER_SIZE + 3 * TRIANGLE_SIZE;
+        assert!(matches!(Stl::parse(&bytes), Err(StlError::Truncated { expected: e, found }) if e == expected && found == expected - 10));
+
+        // Sin cabecera completa no hay ni conteo
+        assert!(matches!(Stl::parse(&[0; 40]), Err(StlError::Truncated { expected: HEADER_SIZE, found: 40 })));
+    }
+
+    #[test]
+    fn ascii_facet_without_three_vertices_is_an_error() {
+        let source = ASCII.replacen("      vertex 0 1 0\n", "", 1);
+        assert!(matches!(
+            Stl::parse(source.as_bytes()),
+            Err(StlError::Parse { line: 7, reason: ParseError::InvalidFacet(2), .. })
+        ));
+    }
+}
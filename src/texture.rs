@@ -0,0 +1,361 @@
+// texture.rs
+// Imágenes que se pegan a los modelos según sus UV. Se guardan como RGBA de 8 bits
+// por canal con la primera fila arriba, como vienen en el archivo; la V de los
+// modelos crece hacia arriba (V = 1 es la fila 0), como en los .obj
+
+use std::fmt;
+use std::io;
+use std::path::Path;
+use crate::color::Color;
+
+#[derive(Debug)]
+pub enum TextureError {
+    Io(io::Error),
+    // El archivo se leyó pero no es un PNG o JPEG válido
+    Decode(image::ImageError),
+}
+
+impl fmt::Display for TextureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TextureError::Io(err) => write!(f, "error de lectura: {}", err),
+            TextureError::Decode(err) => write!(f, "imagen inválida: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for TextureError {}
+
+impl From<io::Error> for TextureError {
+    fn from(err: io::Error) -> Self {
+        TextureError::Io(err)
+    }
+}
+
+impl From<image::ImageError> for TextureError {
+    fn from(err: image::ImageError) -> Self {
+        match err {
+            image::ImageError::IoError(err) => TextureError::Io(err),
+            err => TextureError::Decode(err),
+        }
+    }
+}
+
+// Qué pasa con las UV fuera de [0, 1]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WrapMode {
+    #[default]
+    Repeat, // La imagen se repite: U = 1.25 es lo mismo que U = 0.25
+    Clamp,  // Se estira el borde
+}
+
+// Cómo sale el color de una muestra que cae entre texels
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FilterMode {
+    Nearest, // El texel más cercano: bloques al acercarse, como debe verse el pixel art
+    #[default]
+    Bilinear, // Los cuatro texels alrededor, mezclados según la distancia: suave
+}
+
+impl FilterMode {
+    // El otro modo, para alternarlos con una tecla
+    pub fn toggled(self) -> Self {
+        match self {
+            FilterMode::Nearest => FilterMode::Bilinear,
+            FilterMode::Bilinear => FilterMode::Nearest,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Texture {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>, // `width` * `height` * 4 bytes: R, G, B y A de cada texel
+    pub wrap: WrapMode,
+    filter: FilterMode,
+}
+
+impl Texture {
+    // `pixels` debe tener `width` * `height` * 4 bytes
+    pub fn from_rgba(width: usize, height: usize, pixels: Vec<u8>) -> Self {
+        assert_eq!(pixels.len(), width * height * 4, "textura de {}x{} con {} bytes", width, height, pixels.len());
+        Texture { width, height, pixels, wrap: WrapMode::default(), filter: FilterMode::default() }
+    }
+
+    // Carga un PNG o JPEG; el formato sale del contenido, no de la extensión
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, TextureError> {
+        let image = image::io::Reader::open(path)?.with_guessed_format()?.decode()?.into_rgba8();
+        let (width, height) = (image.width() as usize, image.height() as usize);
+        Ok(Texture::from_rgba(width, height, image.into_raw()))
+    }
+
+    // Como `load`, pero si no se puede avisa y devuelve `fallback`: el modelo sale
+    // a cuadros magenta y negro en vez de cortar el programa
+    pub fn load_or_fallback<P: AsRef<Path>>(path: P) -> Self {
+        let path = path.as_ref();
+        Texture::load(path).unwrap_or_else(|err| {
+            eprintln!("Advertencia: no se pudo cargar la textura {}: {}", path.display(), err);
+            Texture::fallback()
+        })
+    }
+
+    // 2x2 magenta y negro, que no se confunde con ninguna textura de verdad. Sin
+    // filtro, para que los cuadros no se borroneen
+    pub fn fallback() -> Self {
+        let (magenta, black) = ([255, 0, 255, 255], [0, 0, 0, 255]);
+        let mut texture = Texture::from_rgba(2, 2, [magenta, black, black, magenta].concat());
+        texture.set_filter(FilterMode::Nearest);
+        texture
+    }
+
+    pub fn filter(&self) -> FilterMode {
+        self.filter
+    }
+
+    pub fn set_filter(&mut self, filter: FilterMode) {
+        self.filter = filter;
+    }
+
+    // Color de la textura en (`u`, `v`) según `filter`. Se ignora el alfa
+    pub fn sample(&self, u: f32, v: f32) -> Color {
+        // En texels, con la primera fila arriba
+        let (u, v) = (self.wrap_coord(u), self.wrap_coord(v));
+        let (x, y) = (u * self.width as f32, (1.0 - v) * self.height as f32);
+        match self.filter {
+            FilterMode::Nearest => self.texel(x.floor() as i64, y.floor() as i64),
+            FilterMode::Bilinear => self.bilinear(x, y),
+        }
+    }
+
+    // Cada texel vale lo suyo en su centro: se mezclan los cuatro centros que rodean
+    // (`x`, `y`). Los vecinos del borde pasan por `wrap`, así con Repeat el borde se
+    // mezcla con el del otro lado y no queda una costura en U = 0
+    fn bilinear(&self, x: f32, y: f32) -> Color {
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (fx, fy) = (x - x0, y - y0);
+        let (left, top) = (x0 as i64, y0 as i64);
+        let upper = self.texel(left, top).lerp(self.texel(left + 1, top), fx);
+        let lower = self.texel(left, top + 1).lerp(self.texel(left + 1, top + 1), fx);
+        upper.lerp(lower, fy)
+    }
+
+    // Acota una U o V antes de pasarla a texels: con valores enormes `as i64` se
+    // satura y el vecino `+ 1` de `bilinear` se desborda. NaN e infinito van a 0
+    fn wrap_coord(&self, t: f32) -> f32 {
+        if !t.is_finite() {
+            return 0.0;
+        }
+        match self.wrap {
+            WrapMode::Repeat => t.rem_euclid(1.0),
+            // Fuera de [0, 1] ya todo es el borde, margen de sobra para el filtro
+            WrapMode::Clamp => t.clamp(-1.0, 2.0),
+        }
+    }
+
+    // El texel de la columna `x` y la fila `y`, que pueden caer fuera de la imagen
+    fn texel(&self, x: i64, y: i64) -> Color {
+        let i = (self.wrap_index(y, self.height) * self.width + self.wrap_index(x, self.width)) * 4;
+        Color::from_rgb([self.pixels[i] as f32 / 255.0, self.pixels[i + 1] as f32 / 255.0, self.pixels[i + 2] as f32 / 255.0])
+    }
+
+    // Lleva una columna o fila fuera de la imagen a una de adentro, según `wrap`
+    fn wrap_index(&self, index: i64, size: usize) -> usize {
+        match self.wrap {
+            WrapMode::Repeat => index.rem_euclid(size as i64) as usize,
+            WrapMode::Clamp => index.clamp(0, size as i64 - 1) as usize,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn asset(name: &str) -> std::path::PathBuf {
+        Path::new(env!("CARGO_MANIFEST_DIR")).join("assets").join(name)
+    }
+
+    fn rgb(color: Color) -> [u32; 3] {
+        let hex = color.to_hex();
+        [hex >> 16, (hex >> 8) & 0xFF, hex & 0xFF]
+    }
+
+    // Una fila de `colors` en escala de grises
+    fn gray_row(colors: &[u8], wrap: WrapMode) -> Texture {
+        let pixels = colors.iter().flat_map(|&c| [c, c, c, 255]).collect();
+        let mut texture = Texture::from_rgba(colors.len(), 1, pixels);
+        texture.wrap = wrap;
+        texture
+    }
+
+    fn assert_gray(color: Color, expected: f32) {
+        for channel in [color.r, color.g, color.b] {
+            assert!((channel - expected).abs() < 1e-5, "{} en vez de {}", channel, expected);
+        }
+    }
+
+    #[test]
+    fn halfway_between_black_and_white_is_gray() {
+        for wrap in [WrapMode::Repeat, WrapMode::Clamp] {
+            let texture = gray_row(&[0, 255], wrap);
+            assert_gray(texture.sample(0.5, 0.5), 0.5);
+            // En los centros de los texels sale cada uno tal cual
+            assert_gray(texture.sample(0.25, 0.5), 0.0);
+            assert_gray(texture.sample(0.75, 0.5), 1.0);
+        }
+    }
+
+    #[test]
+    fn repeat_blends_the_edges_without_a_seam() {
+        let repeat = gray_row(&[0, 255], WrapMode::Repeat);
+        // U = 0 y U = 1 son el mismo punto, a medio camino entre el último texel y el primero
+        assert_gray(repeat.sample(0.0, 0.5), 0.5);
+        assert_gray(repeat.sample(1.0, 0.5), 0.5);
+        assert_gray(repeat.sample(2.0, 0.5), 0.5);
+        assert_gray(repeat.sample(-1.0, 0.5), 0.5);
+
+        let clamp = gray_row(&[0, 255], WrapMode::Clamp);
+        assert_gray(clamp.sample(0.0, 0.5), 0.0);
+        assert_gray(clamp.sample(1.0, 0.5), 1.0);
+        assert_gray(clamp.sample(-5.0, 0.5), 0.0);
+        assert_gray(clamp.sample(5.0, 0.5), 1.0);
+    }
+
+    #[test]
+    fn huge_or_non_finite_coordinates_do_not_panic() {
+        let odd = [f32::NAN, f32::INFINITY, f32::NEG_INFINITY, 1e30, -1e30, f32::MAX, f32::MIN, 1e7 + 0.3];
+        for wrap in [WrapMode::Repeat, WrapMode::Clamp] {
+            for filter in [FilterMode::Nearest, FilterMode::Bilinear] {
+                let mut texture = gray_row(&[0, 64, 128, 255], wrap);
+                texture.set_filter(filter);
+                for &u in &odd {
+                    for v in [u, 0.5] {
+                        let color = texture.sample(u, v);
+                        assert!([color.r, color.g, color.b].iter().all(|c| (0.0..=1.0).contains(c)), "{:?} en ({}, {})", color, u, v);
+                    }
+                }
+            }
+        }
+    }
+
+    // 2x2 con un color distinto en cada texel: rojo, verde / azul, gris
+    const QUAD: [[f32; 3]; 4] = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0], [0.2, 0.6, 0.4]];
+
+    fn quad(wrap: WrapMode, filter: FilterMode) -> Texture {
+        let pixels = QUAD.iter().flat_map(|c| c.map(|x| (x * 255.0).round() as u8).into_iter().chain([255])).collect();
+        let mut texture = Texture::from_rgba(2, 2, pixels);
+        texture.wrap = wrap;
+        texture.set_filter(filter);
+        texture
+    }
+
+    fn average(texels: &[usize]) -> [f32; 3] {
+        let mut sum = [0.0; 3];
+        for &i in texels {
+            for (s, c) in sum.iter_mut().zip(QUAD[i]) {
+                *s += c / texels.len() as f32;
+            }
+        }
+        sum
+    }
+
+    fn assert_color(color: Color, expected: [f32; 3], at: (f32, f32)) {
+        let got = [color.r, color.g, color.b];
+        assert!(got.iter().zip(expected).all(|(g, e)| (g - e).abs() < 1e-5), "{:?} en vez de {:?} en {:?}", got, expected, at);
+    }
+
+    // Centro de cada texel en UV; la fila 0 está arriba (V alta)
+    const CENTERS: [(f32, f32); 4] = [(0.25, 0.75), (0.75, 0.75), (0.25, 0.25), (0.75, 0.25)];
+
+    #[test]
+    fn nearest_picks_the_texel_under_the_sample() {
+        for wrap in [WrapMode::Repeat, WrapMode::Clamp] {
+            let texture = quad(wrap, FilterMode::Nearest);
+            for (i, &(u, v)) in CENTERS.iter().enumerate() {
+                assert_color(texture.sample(u, v), QUAD[i], (u, v));
+            }
+            // Esquina de adentro: el texel que empieza ahí, abajo a la derecha
+            assert_color(texture.sample(0.5, 0.5), QUAD[3], (0.5, 0.5));
+            // Esquina (0, 1) de la imagen: el primer texel
+            assert_color(texture.sample(0.0, 1.0), QUAD[0], (0.0, 1.0));
+        }
+        // Esquina (1, 0): con Repeat ya es la copia siguiente, con Clamp el último texel
+        assert_color(quad(WrapMode::Repeat, FilterMode::Nearest).sample(1.0, 0.0), QUAD[0], (1.0, 0.0));
+        assert_color(quad(WrapMode::Clamp, FilterMode::Nearest).sample(1.0, 0.0), QUAD[3], (1.0, 0.0));
+    }
+
+    #[test]
+    fn bilinear_is_exact_at_centers_and_averages_at_corners() {
+        for wrap in [WrapMode::Repeat, WrapMode::Clamp] {
+            let texture = quad(wrap, FilterMode::Bilinear);
+            for (i, &(u, v)) in CENTERS.iter().enumerate() {
+                assert_color(texture.sample(u, v), QUAD[i], (u, v));
+            }
+            assert_color(texture.sample(0.5, 0.5), average(&[0, 1, 2, 3]), (0.5, 0.5));
+        }
+        // En las esquinas de la imagen, Repeat mezcla con los texels del otro lado
+        // (en 2x2 son los cuatro) y Clamp se queda con el del borde
+        let corners = [((0.0, 1.0), 0), ((1.0, 1.0), 1), ((0.0, 0.0), 2), ((1.0, 0.0), 3)];
+        for ((u, v), i) in corners {
+            assert_color(quad(WrapMode::Repeat, FilterMode::Bilinear).sample(u, v), average(&[0, 1, 2, 3]), (u, v));
+            assert_color(quad(WrapMode::Clamp, FilterMode::Bilinear).sample(u, v), QUAD[i], (u, v));
+        }
+        // A mitad de un borde de adentro, solo los dos texels que lo comparten
+        let texture = quad(WrapMode::Clamp, FilterMode::Bilinear);
+        assert_color(texture.sample(0.5, 0.75), average(&[0, 1]), (0.5, 0.75));
+        assert_color(texture.sample(0.25, 0.5), average(&[0, 2]), (0.25, 0.5));
+    }
+
+    #[test]
+    fn loads_the_uv_test_texture() {
+        let texture = Texture::load(asset("uv.png")).unwrap();
+        assert!(texture.width > 0 && texture.height > 0);
+        assert_eq!(texture.pixels.len(), texture.width * texture.height * 4);
+        assert_eq!((texture.wrap, texture.filter()), (WrapMode::Repeat, FilterMode::Bilinear));
+    }
+
+    #[test]
+    fn format_comes_from_the_contents() {
+        // El mismo PNG con otra extensión
+        let path = std::env::temp_dir().join(format!("textura-{}.dat", std::process::id()));
+        std::fs::copy(asset("uv.png"), &path).unwrap();
+        let loaded = Texture::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let (original, renamed) = (Texture::load(asset("uv.png")).unwrap(), loaded.unwrap());
+        assert_eq!((renamed.width, renamed.height), (original.width, original.height));
+        assert_eq!(renamed.pixels, original.pixels);
+    }
+
+    #[test]
+    fn missing_file_is_an_io_error() {
+        match Texture::load(asset("no-existe.png")) {
+            Err(TextureError::Io(err)) => assert_eq!(err.kind(), io::ErrorKind::NotFound),
+            other => panic!("{:?}", other.map(|texture| (texture.width, texture.height))),
+        }
+    }
+
+    #[test]
+    fn non_image_file_is_a_decode_error() {
+        let err = Texture::load(asset("naveT.obj")).unwrap_err();
+        assert!(matches!(err, TextureError::Decode(_)), "{:?}", err);
+        assert!(err.to_string().starts_with("imagen inválida"));
+    }
+
+    #[test]
+    fn fallback_is_a_two_by_two_magenta_and_black_checker() {
+        let fallback = Texture::fallback();
+        assert_eq!((fallback.width, fallback.height, fallback.filter()), (2, 2, FilterMode::Nearest));
+        let (magenta, black) = ([255, 0, 255], [0, 0, 0]);
+        // V crece hacia arriba: V = 0.75 es la fila 0
+        assert_eq!(rgb(fallback.sample(0.25, 0.75)), magenta);
+        assert_eq!(rgb(fallback.sample(0.75, 0.75)), black);
+        assert_eq!(rgb(fallback.sample(0.25, 0.25)), black);
+        assert_eq!(rgb(fallback.sample(0.75, 0.25)), magenta);
+
+        let missing = Texture::load_or_fallback(asset("no-existe.png"));
+        assert_eq!((missing.width, missing.height), (2, 2));
+        assert_eq!(missing.pixels, fallback.pixels);
+    }
+}
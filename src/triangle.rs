@@ -1,47 +1,494 @@
 use crate::fragment::Fragment;
 use crate::vertex::Vertex;
-use crate::color::Color;
+use crate::light::Surface;
 use nalgebra_glm::Vec3;
-use crate::utils::{calculate_bounding_box, barycentric_coordinates};
+use std::ops::Range;
+use crate::utils::{calculate_bounding_box, edge_function, is_top_left, to_fixed, SUBPIXEL_STEPS};
 
-pub fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
-    let mut fragments = Vec::new();
+// Cómo se recorren los píxeles del rectángulo que envuelve al triángulo. Los dos
+// dan exactamente los mismos fragmentos; existen juntos para poder compararlos
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Rasterizer {
+    BoundingBox, // Evalúa las tres funciones de arista desde cero en cada píxel
+    #[default]
+    Incremental, // Las avanza sumando un paso fijo y corta cada fila al salir del triángulo
+}
+
+// Qué caras se descartan antes de rasterizar. Las de atrás de una malla cerrada
+// siempre las tapa otra cara; las superficies de una sola capa necesitan `None`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CullMode {
+    None,
+    #[default]
+    Back,
+    Front,
+}
+
+// Orden de los vértices de una cara que mira hacia afuera, visto desde afuera.
+// OBJ y glTF usan antihorario, pero hay exportadores que los dejan al revés
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Winding {
+    #[default]
+    CounterClockwise,
+    Clockwise,
+}
+
+impl CullMode {
+    // `area` es el de `signed_area` del triángulo ya en pantalla
+    pub fn culls(self, winding: Winding, area: i64) -> bool {
+        // La pantalla tiene Y hacia abajo: un triángulo antihorario en el mundo
+        // queda horario en pantalla, con área negativa
+        let front_facing = match winding {
+            Winding::CounterClockwise => area < 0,
+            Winding::Clockwise => area > 0,
+        };
+        match self {
+            CullMode::None => false,
+            CullMode::Back => !front_facing,
+            CullMode::Front => front_facing,
+        }
+    }
+}
+
+// Doble del área con signo del triángulo en pantalla (punto fijo, ver `edge_function`)
+pub fn signed_area(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> i64 {
+    let fixed = |vertex: &Vertex| to_fixed(vertex.transformed_position.x, vertex.transformed_position.y);
+    edge_function(fixed(v1), fixed(v2), fixed(v3))
+}
+
+// Normal de la cara para flat shading: promedio de las normales de sus vértices, en
+// espacio de cámara. Si el modelo no trae normales mira hacia el ojo
+pub fn face_normal(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec3 {
+    (v1.transformed_normal + v2.transformed_normal + v3.transformed_normal)
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(Vec3::z)
+}
+
+// Lo más que cambia la profundidad al moverse un píxel en X o en Y dentro del
+// triángulo, a partir del plano que pasa por sus tres vértices en pantalla
+pub fn depth_slope(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> f32 {
+    let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+    let normal = (b - a).cross(&(c - a));
+    if normal.z.abs() <= f32::EPSILON {
+        return 0.0;
+    }
+    (normal.x / normal.z).abs().max((normal.y / normal.z).abs())
+}
+
+// Triángulo que no cubre ningún píxel: sus vértices caen en la misma recta al
+// redondearlos a dieciseisavos de píxel (área 0 exacta en punto fijo), o alguno no es
+// un número finito. Con área distinta de 0, por delgado que sea, los pesos de los
+// píxeles cubiertos suman el área y quedan en [0, 1]: no hace falta otro umbral
+pub fn is_degenerate(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> bool {
+    let finite = |vertex: &Vertex| {
+        vertex.transformed_position.iter().all(|c| c.is_finite()) && vertex.transformed_w.is_finite()
+    };
+    if !(finite(v1) && finite(v2) && finite(v3)) {
+        return true;
+    }
+    signed_area(v1, v2, v3) == 0
+}
+
+// Llama a `emit` con cada fragmento que cubre el triángulo, en orden de filas. Solo
+// recorre las columnas de `columns` y las filas de `rows`: cada hilo de `render`
+// dibuja su franja o su tesela de la pantalla
+pub fn triangle_with(
+    v1: &Vertex,
+    v2: &Vertex,
+    v3: &Vertex,
+    rasterizer: Rasterizer,
+    columns: Range<i32>,
+    rows: Range<i32>,
+    mut emit: impl FnMut(Fragment),
+) {
+    if is_degenerate(v1, v2, v3) {
+        return;
+    }
+
+    let fixed = |vertex: &Vertex| to_fixed(vertex.transformed_position.x, vertex.transformed_position.y);
+
+    // Con el orden inverso las aristas se recorren al revés: se intercambian dos
+    // vértices para que el área quede positiva y la regla de relleno sea la misma
+    let area = edge_function(fixed(v1), fixed(v2), fixed(v3));
+    let (v2, v3, area) = if area < 0 { (v3, v2, -area) } else { (v2, v3, area) };
     let (a, b, c) = (v1.transformed_position, v2.transformed_position, v3.transformed_position);
+    let (fa, fb, fc) = (fixed(v1), fixed(v2), fixed(v3));
+    let (top_left_bc, top_left_ca, top_left_ab) = (is_top_left(fb, fc), is_top_left(fc, fa), is_top_left(fa, fb));
 
     let (min_x, min_y, max_x, max_y) = calculate_bounding_box(&a, &b, &c);
+    let (min_x, max_x) = (min_x.max(columns.start), max_x.min(columns.end.saturating_sub(1)));
+    let (min_y, max_y) = (min_y.max(rows.start), max_y.min(rows.end.saturating_sub(1)));
+    if min_x > max_x || min_y > max_y {
+        return;
+    }
+
+    // La iluminación se calcula en `render`; aquí la normal de la cara solo
+    // reemplaza a la interpolada cuando esa se anula. Si tampoco hay normal de cara
+    // el fragmento sale con normal cero, y cada shader decide qué hacer
+    let face_normal = (v1.transformed_normal + v2.transformed_normal + v3.transformed_normal)
+        .try_normalize(f32::EPSILON)
+        .unwrap_or_else(Vec3::zeros);
+    // Los tres vértices de una cara tienen el mismo material
+    let material = Surface::of(v1);
+
+    // Adentro si está del lado positivo de las tres aristas; sobre una arista
+    // solo cuenta si es superior o izquierda, así dos triángulos vecinos no
+    // dibujan el mismo píxel ni dejan una grieta entre ellos
+    let inside = |e: i64, top_left: bool| e > 0 || (e == 0 && top_left);
+    let covers = |(e1, e2, e3): (i64, i64, i64)| inside(e1, top_left_bc) && inside(e2, top_left_ca) && inside(e3, top_left_ab);
 
-    // Dirección de la fuente de luz
-    let light_dir = Vec3::new(0.0, 0.0, -1.0);
+    let mut shade = |x: i32, y: i32, (e1, e2, e3): (i64, i64, i64)| {
+        // Coordenadas baricéntricas: cada arista pesa el vértice opuesto
+        let (w1, w2, w3) = (e1 as f32 / area as f32, e2 as f32 / area as f32, e3 as f32 / area as f32);
 
-    // Normal del triángulo para flat shading
-    let normal = (b - a).cross(&(c - a)).normalize();
+        // La profundidad ya pasó por la división por w: z/w de la proyección es
+        // a + b/w, y 1/w varía linealmente en pantalla, así que z/w también y se
+        // interpola con los pesos tal cual. Corregirla por perspectiva como los
+        // atributos de abajo la movería y las intersecciones quedarían corridas
+        let depth = w1 * a.z + w2 * b.z + w3 * c.z;
+        debug_assert!(depth.is_finite(), "profundidad no finita en ({}, {})", x, y);
 
-    // Calcula la intensidad de la luz usando el producto punto
-    let intensity = normal.dot(&light_dir).max(0.0);
+        // Los atributos varían linealmente en el espacio 3D, no en pantalla:
+        // se interpolan atributo / w y 1 / w, y se dividen al final
+        let (p1, p2, p3) = (w1 / v1.transformed_w, w2 / v2.transformed_w, w3 / v3.transformed_w);
+        let inverse_w = p1 + p2 + p3;
+        let (p1, p2, p3) = (p1 / inverse_w, p2 / inverse_w, p3 / inverse_w);
 
-    // Color base con iluminación aplicada
-    let lit_color = Color {
-        r: 0.8 * intensity,
-        g: 0.8 * intensity,
-        b: 0.8 * intensity,
+        let tex_coords = v1.tex_coords * p1 + v2.tex_coords * p2 + v3.tex_coords * p3;
+        // Interpolar acorta la normal; si se anula (normales opuestas o
+        // ausentes) se usa la de la cara
+        let normal = (v1.transformed_normal * p1 + v2.transformed_normal * p2 + v3.transformed_normal * p3)
+            .try_normalize(f32::EPSILON)
+            .unwrap_or(face_normal);
+        // Color interpolado entre los vértices (degradado tipo Gouraud)
+        let color = v1.color * p1 + v2.color * p2 + v3.color * p3;
+        let world_position = v1.position * p1 + v2.position * p2 + v3.position * p3;
+        let model_position = v1.model_position * p1 + v2.model_position * p2 + v3.model_position * p3;
+        emit(Fragment {
+            tex_coords,
+            normal,
+            world_position,
+            model_position,
+            material,
+            ..Fragment::new(x as f32, y as f32, color, depth)
+        });
     };
 
-    // Itera sobre cada píxel en el bounding box
-    for y in min_y..=max_y {
-        for x in min_x..=max_x {
-            let point = Vec3::new(x as f32, y as f32, 0.0);
+    // Se muestrea el centro de cada píxel
+    let edges_at = |x: i32, y: i32| {
+        let point = to_fixed(x as f32 + 0.5, y as f32 + 0.5);
+        (edge_function(fb, fc, point), edge_function(fc, fa, point), edge_function(fa, fb, point))
+    };
+
+    match rasterizer {
+        Rasterizer::BoundingBox => {
+            for y in min_y..=max_y {
+                for x in min_x..=max_x {
+                    let edges = edges_at(x, y);
+                    if covers(edges) {
+                        shade(x, y, edges);
+                    }
+                }
+            }
+        }
+        Rasterizer::Incremental => {
+            // Las funciones de arista son lineales: un píxel a la derecha o abajo
+            // les suma siempre lo mismo (en punto fijo, sin error acumulado)
+            let step = SUBPIXEL_STEPS as i64;
+            let step_x = (-(fc.1 - fb.1) * step, -(fa.1 - fc.1) * step, -(fb.1 - fa.1) * step);
+            let step_y = ((fc.0 - fb.0) * step, (fa.0 - fc.0) * step, (fb.0 - fa.0) * step);
+            let add = |(a1, a2, a3): (i64, i64, i64), (b1, b2, b3): (i64, i64, i64)| (a1 + b1, a2 + b2, a3 + b3);
+
+            let mut row = edges_at(min_x, min_y);
+            for y in min_y..=max_y {
+                let mut edges = row;
+                let mut entered = false;
+                for x in min_x..=max_x {
+                    if covers(edges) {
+                        shade(x, y, edges);
+                        entered = true;
+                    } else if entered {
+                        // El triángulo es convexo: después de salir no se vuelve a entrar
+                        break;
+                    }
+                    edges = add(edges, step_x);
+                }
+                row = add(row, step_y);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::color::Color;
+    use nalgebra_glm::Vec2;
+
+    // Todos los fragmentos en un vector, sin recortar a una ventana
+    fn triangle(v1: &Vertex, v2: &Vertex, v3: &Vertex) -> Vec<Fragment> {
+        let mut fragments = Vec::new();
+        triangle_with(v1, v2, v3, Rasterizer::default(), i32::MIN..i32::MAX, i32::MIN..i32::MAX, |fragment| fragments.push(fragment));
+        fragments
+    }
+
+    // Vértice ya en pantalla, con w = 1
+    fn vertex(x: f32, y: f32) -> Vertex {
+        Vertex { transformed_position: Vec3::new(x, y, 0.5), ..Vertex::default() }
+    }
+
+    fn colored(x: f32, y: f32, color: Color) -> Vertex {
+        Vertex { color, ..vertex(x, y) }
+    }
+
+    #[test]
+    fn vertex_colors_blend_to_a_third_each_at_the_centroid() {
+        let (red, green, blue) = (Color::from_hex(0xFF0000), Color::from_hex(0x00FF00), Color::from_hex(0x0000FF));
+        let (a, b, c) = (colored(2.5, 2.5, red), colored(92.5, 2.5, green), colored(2.5, 92.5, blue));
+        let fragments = triangle(&a, &b, &c);
+
+        // El centroide (32.5, 32.5) es el centro del píxel (32, 32)
+        let centroid = fragments.iter().find(|fragment| fragment.position == Vec2::new(32.0, 32.0)).unwrap();
+        for channel in [centroid.color.r, centroid.color.g, centroid.color.b] {
+            assert!((channel - 1.0 / 3.0).abs() < 1e-4, "{:?}", centroid.color);
+        }
+        // En cada esquina domina su color, y ningún canal se sale de [0, 1]
+        let corner = fragments.iter().find(|fragment| fragment.position == Vec2::new(2.0, 2.0)).unwrap();
+        assert!(corner.color.r > 0.999);
+        for fragment in &fragments {
+            let sum = fragment.color.r + fragment.color.g + fragment.color.b;
+            assert!((sum - 1.0).abs() < 1e-4);
+            assert!([fragment.color.r, fragment.color.g, fragment.color.b].iter().all(|c| (0.0..=1.0 + 1e-6).contains(c)));
+        }
+    }
+
+    // Cuántas veces se pinta cada píxel de una cuadrícula de `size` x `size` al
+    // rasterizar `triangles`
+    fn coverage(triangles: &[[Vertex; 3]], size: usize) -> Vec<u32> {
+        let mut counts = vec![0; size * size];
+        for [a, b, c] in triangles {
+            for fragment in triangle(a, b, c) {
+                counts[fragment.position.y as usize * size + fragment.position.x as usize] += 1;
+            }
+        }
+        counts
+    }
+
+    // `counts` tiene un 1 justo en los píxeles de `columns` x `rows` y 0 en el resto
+    fn assert_covered_once(counts: &[u32], size: usize, columns: Range<usize>, rows: Range<usize>) {
+        for (index, &count) in counts.iter().enumerate() {
+            let (x, y) = (index % size, index / size);
+            let expected = (columns.contains(&x) && rows.contains(&y)) as u32;
+            assert_eq!(count, expected, "píxel ({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn quad_split_in_two_covers_each_pixel_once() {
+        // Con esquinas en bordes de píxel y en centros de píxel: en el segundo caso los
+        // centros caen justo sobre las aristas de afuera y la regla decide
+        for offset in [0.0, 0.5] {
+            let (x0, y0, x1, y1) = (2.0 + offset, 3.0 + offset, 10.0 + offset, 11.0 + offset);
+            let corners = [vertex(x0, y0), vertex(x1, y0), vertex(x1, y1), vertex(x0, y1)];
+            // Las dos diagonales; en la de pendiente 1 también caen centros encima
+            for split in [[[0, 1, 2], [0, 2, 3]], [[0, 1, 3], [1, 2, 3]]] {
+                for reversed in [false, true] {
+                    let split: Vec<[Vertex; 3]> = split
+                        .iter()
+                        .map(|&[a, b, c]| if reversed { [a, c, b] } else { [a, b, c] })
+                        .map(|corner| corner.map(|i| corners[i].clone()))
+                        .collect();
+                    let counts = coverage(&split, 16);
+                    assert_eq!(counts.iter().sum::<u32>(), 64);
+                    let start = |value: f32| (value - 0.5).ceil() as usize;
+                    assert_covered_once(&counts, 16, start(x0)..start(x1), start(y0)..start(y1));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn mesh_of_irregular_triangles_has_no_gaps_or_overlaps() {
+        // Cuadrícula de 6 x 6 celdas de 5 píxeles con los vértices de adentro movidos
+        // en fracciones de píxel; cada celda en dos triángulos con la diagonal alternada
+        let cells = 6;
+        let point = |i: usize, j: usize| {
+            let inner = i > 0 && i < cells && j > 0 && j < cells;
+            let jitter = if inner { ((i * 7 + j * 13) % 11) as f32 / 11.0 - 0.5 } else { 0.0 };
+            vertex(i as f32 * 5.0 + jitter * 1.7, j as f32 * 5.0 - jitter)
+        };
+        let mut triangles = Vec::new();
+        for j in 0..cells {
+            for i in 0..cells {
+                let (a, b, c, d) = (point(i, j), point(i + 1, j), point(i + 1, j + 1), point(i, j + 1));
+                if (i + j) % 2 == 0 {
+                    triangles.extend([[a.clone(), b, c.clone()], [a, c, d]]);
+                } else {
+                    triangles.extend([[a, b.clone(), d.clone()], [b, c, d]]);
+                }
+            }
+        }
+        let counts = coverage(&triangles, 32);
+        assert_covered_once(&counts, 32, 0..30, 0..30);
+    }
+
+    type Pixels = Vec<(i32, i32)>;
+
+    const SLIVER: [(f32, f32); 3] = [(2.2, 4.3), (42.7, 6.1), (41.9, 7.4)];
 
-            // Calcula las coordenadas baricéntricas
-            let (w1, w2, w3) = barycentric_coordinates(&point, &a, &b, &c);
+    // Píxeles que cubre la astilla corrida `dx` píxeles a la derecha
+    fn sliver(dx: f32) -> Pixels {
+        let [a, b, c] = SLIVER.map(|(x, y)| vertex(x + dx, y));
+        let mut pixels: Pixels = triangle(&a, &b, &c).iter().map(|f| (f.position.x as i32, f.position.y as i32)).collect();
+        pixels.sort_unstable();
+        pixels
+    }
+
+    // Lo mismo calculado a mano en f64: un píxel está si su centro queda del lado de
+    // adentro de las tres aristas. Los centros a menos de un dieciseisavo de una
+    // arista dependen del redondeo a punto fijo y la regla top-left, así que se
+    // devuelven aparte para no exigir nada sobre ellos
+    fn sliver_reference(dx: f32) -> (Pixels, Pixels) {
+        let corners = SLIVER.map(|(x, y)| ((x + dx) as f64, y as f64));
+        let (mut inside, mut doubtful) = (Vec::new(), Vec::new());
+        for x in 0..64 {
+            for y in 0..16 {
+                let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+                let distances = [(0, 1), (1, 2), (2, 0)].map(|(i, j)| {
+                    let ((ax, ay), (bx, by)) = (corners[i], corners[j]);
+                    ((bx - ax) * (py - ay) - (by - ay) * (px - ax)) / (bx - ax).hypot(by - ay)
+                });
+                if distances.iter().any(|d| d.abs() < 1.0 / 16.0) {
+                    doubtful.push((x, y));
+                } else if distances.iter().all(|&d| d > 0.0) || distances.iter().all(|&d| d < 0.0) {
+                    inside.push((x, y));
+                }
+            }
+        }
+        (inside, doubtful)
+    }
+
+    #[test]
+    fn sliver_coverage_follows_sub_pixel_offsets() {
+        let masks: Vec<Pixels> = (0..=8).map(|step| sliver(step as f32 * 0.25)).collect();
+        for (step, mask) in masks.iter().enumerate() {
+            let (inside, doubtful) = sliver_reference(step as f32 * 0.25);
+            assert!(!mask.is_empty(), "la astilla corrida {} cuartos desapareció", step);
+            for pixel in &inside {
+                assert!(mask.contains(pixel), "corrida {} cuartos le falta {:?}", step, pixel);
+            }
+            for pixel in mask {
+                assert!(inside.contains(pixel) || doubtful.contains(pixel), "corrida {} cuartos sobra {:?}", step, pixel);
+            }
+        }
+        // Entre 0 y 1 píxel la máscara pasa por un estado intermedio, no salta de golpe
+        assert!((1..4).any(|step| masks[step] != masks[0] && masks[step] != masks[4]));
+        // Un píxel entero de corrimiento es exactamente la misma máscara un píxel más allá
+        for step in 0..=4 {
+            let shifted: Pixels = masks[step].iter().map(|&(x, y)| (x + 1, y)).collect();
+            assert_eq!(masks[step + 4], shifted, "corrida {} cuartos contra {} cuartos", step + 4, step);
+        }
+    }
 
-            // Verifica si el punto está dentro del triángulo
-            if w1 >= 0.0 && w2 >= 0.0 && w3 >= 0.0 {
-                // Calcula la profundidad interpolada
-                let depth = w1 * a.z + w2 * b.z + w3 * c.z;
-                fragments.push(Fragment::new(x as f32, y as f32, lit_color, depth));
+    // Generador congruencial lineal: siempre los mismos triángulos, sin dependencias
+    struct Lcg(u64);
+
+    impl Lcg {
+        // Un número en [0, 1)
+        fn next(&mut self) -> f32 {
+            self.0 = self.0.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            (self.0 >> 40) as f32 / (1u64 << 24) as f32
+        }
+
+        fn range(&mut self, start: f32, end: f32) -> f32 {
+            start + (end - start) * self.next()
+        }
+    }
+
+    #[test]
+    fn incremental_matches_bounding_box_on_random_triangles() {
+        let mut random = Lcg(52);
+        let mut covered = 0;
+        for i in 0..400 {
+            // Vértices con fracciones de píxel, algunos fuera de la zona a dibujar, y
+            // de vez en cuando astillas casi planas
+            let mut point = || vertex(random.range(-8.0, 72.0), random.range(-8.0, 56.0));
+            let (a, b) = (point(), point());
+            let c = if i % 5 == 0 {
+                let t = random.range(-0.5, 1.5);
+                vertex(
+                    a.transformed_position.x + (b.transformed_position.x - a.transformed_position.x) * t + random.range(-0.3, 0.3),
+                    a.transformed_position.y + (b.transformed_position.y - a.transformed_position.y) * t + random.range(-0.3, 0.3),
+                )
+            } else {
+                point()
+            };
+            // La mitad se recorta a una franja o tesela, como en `render`
+            let (columns, rows) = if i % 2 == 0 {
+                (0..64, 0..48)
+            } else {
+                let (x, y) = (random.range(0.0, 48.0) as i32, random.range(0.0, 32.0) as i32);
+                (x..x + random.range(1.0, 16.0) as i32, y..y + random.range(1.0, 16.0) as i32)
+            };
+            let mask = |rasterizer| {
+                let mut pixels = Vec::new();
+                triangle_with(&a, &b, &c, rasterizer, columns.clone(), rows.clone(), |f| {
+                    pixels.push((f.position.x as i32, f.position.y as i32, f.depth))
+                });
+                pixels
+            };
+            let expected = mask(Rasterizer::BoundingBox);
+            assert_eq!(mask(Rasterizer::Incremental), expected, "triángulo {}: {:?} {:?} {:?}", i, a.transformed_position, b.transformed_position, c.transformed_position);
+            covered += expected.len();
+        }
+        assert!(covered > 10_000, "los triángulos casi no cubren píxeles: {}", covered);
+    }
+
+    #[test]
+    fn degenerate_triangles_emit_nothing() {
+        let nan = vertex(f32::NAN, 3.0);
+        let cases = [
+            [vertex(4.0, 4.0), vertex(4.0, 4.0), vertex(20.0, 9.0)], // Vértice repetido
+            [vertex(1.0, 1.0), vertex(11.0, 6.0), vertex(31.0, 16.0)], // Alineados
+            [vertex(-1e6, -1e6), vertex(0.0, 0.0), vertex(1e6, 1e6)], // Alineados y enormes
+            [vertex(2.0, 2.0), vertex(2.01, 9.0), vertex(2.02, 16.0)], // Alineados al redondear
+            [nan, vertex(10.0, 3.0), vertex(5.0, 12.0)],
+            [vertex(f32::INFINITY, 3.0), vertex(10.0, 3.0), vertex(5.0, 12.0)],
+        ];
+        for [a, b, c] in &cases {
+            assert!(is_degenerate(a, b, c), "{:?} {:?} {:?}", a.transformed_position, b.transformed_position, c.transformed_position);
+            for rasterizer in [Rasterizer::BoundingBox, Rasterizer::Incremental] {
+                let mut count = 0;
+                triangle_with(a, b, c, rasterizer, 0..64, 0..48, |_| count += 1);
+                assert_eq!(count, 0, "{:?} {:?} {:?}", a.transformed_position, b.transformed_position, c.transformed_position);
             }
         }
     }
 
-    fragments
+    // Triángulos finísimos, con profundidades distintas en cada vértice: lo que sale
+    // siempre queda entre la menor y la mayor de las tres
+    #[test]
+    fn thin_triangles_never_emit_non_finite_depth() {
+        let mut random = Lcg(54);
+        let mut emitted = 0;
+        for _ in 0..400 {
+            let corner = |random: &mut Lcg| {
+                let (x, y, z) = (random.range(0.0, 64.0), random.range(0.0, 48.0), random.range(0.0, 1.0));
+                Vertex { transformed_position: Vec3::new(x, y, z), ..Vertex::default() }
+            };
+            let (a, b, mut c) = (corner(&mut random), corner(&mut random), corner(&mut random));
+            // A un dieciseisavo o menos de la recta de `a` a `b`
+            let (t, (ox, oy)) = (random.range(0.0, 1.0), (random.range(-0.06, 0.06), random.range(-0.06, 0.06)));
+            c.transformed_position.x = a.transformed_position.x + (b.transformed_position.x - a.transformed_position.x) * t + ox;
+            c.transformed_position.y = a.transformed_position.y + (b.transformed_position.y - a.transformed_position.y) * t + oy;
+            let depths = [a.transformed_position.z, b.transformed_position.z, c.transformed_position.z];
+            let (low, high) = (depths.iter().copied().fold(f32::INFINITY, f32::min), depths.iter().copied().fold(0.0, f32::max));
+            triangle_with(&a, &b, &c, Rasterizer::default(), 0..64, 0..48, |fragment| {
+                assert!(fragment.depth.is_finite(), "profundidad {} en {:?}", fragment.depth, fragment.position);
+                assert!(fragment.depth >= low - 1e-5 && fragment.depth <= high + 1e-5, "profundidad {} fuera de [{}, {}]", fragment.depth, low, high);
+                emitted += 1;
+            });
+        }
+        assert!(emitted > 0);
+    }
 }
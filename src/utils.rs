@@ -1,6 +1,6 @@
 // src/utils.rs
 
-use nalgebra_glm::Vec3;
+use nalgebra_glm::{Mat3, Mat4, Vec3};
 
 pub fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32, i32) {
     let min_x = v1.x.min(v2.x).min(v3.x).floor() as i32;
@@ -11,25 +11,88 @@ pub fn calculate_bounding_box(v1: &Vec3, v2: &Vec3, v3: &Vec3) -> (i32, i32, i32
     (min_x, min_y, max_x, max_y)
 }
 
-pub fn barycentric_coordinates(p: &Vec3, a: &Vec3, b: &Vec3, c: &Vec3) -> (f32, f32, f32) {
-    let v0 = b - a;
-    let v1 = c - a;
-    let v2 = p - a;
+// Posición de pantalla en punto fijo 28.4 (dieciseisavos de píxel). Las funciones de
+// arista con enteros son exactas: un vértice que se mueve un cuarto de píxel mueve
+// la cobertura un cuarto de píxel, y los empates sobre una arista son ceros de verdad
+pub type FixedPoint = (i64, i64);
 
-    let d00 = v0.dot(&v0);
-    let d01 = v0.dot(&v1);
-    let d11 = v1.dot(&v1);
-    let d20 = v2.dot(&v0);
-    let d21 = v2.dot(&v1);
+pub const SUBPIXEL_STEPS: f32 = 16.0;
 
-    let denom = d00 * d11 - d01 * d01;
-    if denom.abs() < 1e-5 {
-        return (-1.0, -1.0, -1.0); // Regresa valores fuera del rango si el área es muy pequeña
+pub fn to_fixed(x: f32, y: f32) -> FixedPoint {
+    ((x * SUBPIXEL_STEPS).round() as i64, (y * SUBPIXEL_STEPS).round() as i64)
+}
+
+// Función de arista: el doble del área con signo del triángulo (a, b, p) en pantalla.
+// Es positiva de un lado de la recta a -> b, negativa del otro y cero sobre ella
+pub fn edge_function(a: FixedPoint, b: FixedPoint, p: FixedPoint) -> i64 {
+    (b.0 - a.0) * (p.1 - a.1) - (b.1 - a.1) * (p.0 - a.0)
+}
+
+// Regla superior-izquierda para triángulos con área positiva según `edge_function`
+// (horarios en pantalla, con Y hacia abajo): una arista es superior si es horizontal
+// y va hacia la derecha, e izquierda si sube. Los píxeles justo sobre una arista
+// compartida se quedan solo en el triángulo para el que es superior o izquierda
+pub fn is_top_left(a: FixedPoint, b: FixedPoint) -> bool {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    (dy == 0 && dx > 0) || dy < 0
+}
+
+// Transformación de viewport: coordenadas normalizadas [-1, 1] -> píxeles del
+// rectángulo `width` x `height` que empieza en (`x`, `y`). Invierte Y porque en
+// pantalla crece hacia abajo; Z pasa a profundidad en [0, 1] (0 = plano cercano)
+pub fn viewport_matrix(x: f32, y: f32, width: f32, height: f32) -> Mat4 {
+    Mat4::new_translation(&Vec3::new(x + width / 2.0, y + height / 2.0, 0.5))
+        * Mat4::new_nonuniform_scaling(&Vec3::new(width / 2.0, -height / 2.0, 0.5))
+}
+
+// Matriz para transformar normales: inversa transpuesta de la parte 3x3 de `matrix`.
+// Con escala no uniforme la matriz original inclinaría las normales
+pub fn normal_matrix(matrix: &Mat4) -> Mat3 {
+    nalgebra_glm::mat4_to_mat3(matrix)
+        .try_inverse()
+        .map(|inverse| inverse.transpose())
+        .unwrap_or_else(Mat3::identity)
+}
+
+// 12450 -> "12,450"
+pub fn thousands(value: usize) -> String {
+    let digits = value.to_string();
+    let mut text = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            text.push(',');
+        }
+        text.push(digit);
     }
+    text
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    let v = (d11 * d20 - d01 * d21) / denom;
-    let w = (d00 * d21 - d01 * d20) / denom;
-    let u = 1.0 - v - w;
+    #[test]
+    fn normals_stay_perpendicular_under_non_uniform_scale() {
+        let model = Mat4::new_nonuniform_scaling(&Vec3::new(1.0, 2.0, 1.0));
+        let linear = nalgebra_glm::mat4_to_mat3(&model);
+        // El plano x + y = 0: su normal y dos direcciones sobre él
+        let normal = Vec3::new(1.0, 1.0, 0.0).normalize();
+        let tangents = [Vec3::new(1.0, -1.0, 0.0), Vec3::z()];
 
-    (u, v, w)
+        let transformed = normal_matrix(&model) * normal;
+        for tangent in tangents {
+            assert!(transformed.dot(&(linear * tangent)).abs() < 1e-6);
+        }
+        // Con la matriz del modelo la normal queda inclinada
+        assert!((linear * normal).dot(&(linear * tangents[0])).abs() > 1.0);
+    }
+
+    #[test]
+    fn normal_matrix_keeps_rotations() {
+        let model = Mat4::new_rotation(Vec3::new(0.3, -1.2, 0.7)) * Mat4::new_translation(&Vec3::new(5.0, 0.0, 2.0));
+        let rotation = nalgebra_glm::mat4_to_mat3(&model);
+        assert!((normal_matrix(&model) - rotation).abs().max() < 1e-5);
+        // Una matriz sin inversa no deja NaN
+        assert_eq!(normal_matrix(&Mat4::zeros()), Mat3::identity());
+    }
 }
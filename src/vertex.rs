@@ -1,14 +1,26 @@
 use nalgebra_glm::{Vec2, Vec3};
 use crate::color::Color;
 
+// Brillo de los vértices que no vienen de un material: gris y de tamaño medio. La
+// luz ambiente se refleja con el mismo gris que el difuso del material por defecto
+pub const DEFAULT_SPECULAR: Color = Color { r: 0.5, g: 0.5, b: 0.5 };
+pub const DEFAULT_SHININESS: f32 = 32.0;
+pub const DEFAULT_AMBIENT: Color = Color { r: 0.8, g: 0.8, b: 0.8 };
+
 #[derive(Clone, Debug)]
 pub struct Vertex {
     pub position: Vec3,               // Posición del vértice en 3D
+    pub model_position: Vec3,         // Posición en el modelo, antes de moverlo; la pone el pipeline
     pub normal: Vec3,                 // Vector normal en 3D
     pub tex_coords: Vec2,             // Coordenadas de textura en 2D
     pub color: Color,                 // Color difuso
+    pub ambient: Color,               // Cuánto refleja de la luz ambiente (Ka del material)
+    pub emissive: Color,              // Luz propia, que se ve aun a oscuras (Ke del material)
+    pub specular: Color,              // Color del brillo (Ks del material)
+    pub shininess: f32,               // Exponente del brillo (Ns del material)
     pub transformed_position: Vec3,   // Posición transformada
     pub transformed_normal: Vec3,     // Normal transformada
+    pub transformed_w: f32,           // w en coordenadas de recorte, para interpolar con perspectiva
 }
 
 impl Vertex {
@@ -16,11 +28,17 @@ impl Vertex {
     pub fn new(position: Vec3, normal: Vec3, tex_coords: Vec2) -> Self {
         Vertex {
             position,
+            model_position: position,
             normal,
             tex_coords,
             color: Color::black(),
+            ambient: DEFAULT_AMBIENT,
+            emissive: Color::black(),
+            specular: DEFAULT_SPECULAR,
+            shininess: DEFAULT_SHININESS,
             transformed_position: position,
             transformed_normal: normal,
+            transformed_w: 1.0,
         }
     }
 
@@ -28,11 +46,17 @@ impl Vertex {
     pub fn new_with_color(position: Vec3, color: Color) -> Self {
         Vertex {
             position,
+            model_position: position,
             normal: Vec3::new(0.0, 0.0, 0.0),
             tex_coords: Vec2::new(0.0, 0.0),
             color,
+            ambient: DEFAULT_AMBIENT,
+            emissive: Color::black(),
+            specular: DEFAULT_SPECULAR,
+            shininess: DEFAULT_SHININESS,
             transformed_position: Vec3::new(0.0, 0.0, 0.0),
             transformed_normal: Vec3::new(0.0, 0.0, 0.0),
+            transformed_w: 1.0,
         }
     }
 
@@ -48,11 +72,17 @@ impl Default for Vertex {
     fn default() -> Self {
         Vertex {
             position: Vec3::new(0.0, 0.0, 0.0),
+            model_position: Vec3::new(0.0, 0.0, 0.0),
             normal: Vec3::new(0.0, 1.0, 0.0),
             tex_coords: Vec2::new(0.0, 0.0),
             color: Color::black(),
+            ambient: DEFAULT_AMBIENT,
+            emissive: Color::black(),
+            specular: DEFAULT_SPECULAR,
+            shininess: DEFAULT_SHININESS,
             transformed_position: Vec3::new(0.0, 0.0, 0.0),
             transformed_normal: Vec3::new(0.0, 1.0, 0.0),
+            transformed_w: 1.0,
         }
     }
 }
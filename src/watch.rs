@@ -0,0 +1,52 @@
+// watch.rs
+// Vigila un archivo consultando su fecha de modificación cada cierto tiempo
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant, SystemTime};
+
+pub struct FileWatcher {
+    path: PathBuf,
+    interval: Duration,
+    modified: Option<SystemTime>,
+    last_check: Instant,
+}
+
+impl FileWatcher {
+    pub fn new(path: impl Into<PathBuf>, interval: Duration) -> Self {
+        let path = path.into();
+        let modified = modified_time(&path);
+        Self { path, interval, modified, last_check: Instant::now() }
+    }
+
+    // True si el archivo cambió desde la última consulta. Entre consultas
+    // devuelve false sin tocar el disco
+    pub fn poll(&mut self) -> bool {
+        if self.last_check.elapsed() < self.interval {
+            return false;
+        }
+        self.last_check = Instant::now();
+
+        let modified = modified_time(&self.path);
+        // Mientras el exportador escribe, el archivo puede desaparecer un instante
+        if modified.is_none() || modified == self.modified {
+            return false;
+        }
+        self.modified = modified;
+        true
+    }
+
+    // Registra la fecha actual como ya vista (p. ej. tras una recarga manual)
+    pub fn mark_seen(&mut self) {
+        self.modified = modified_time(&self.path);
+    }
+
+    pub fn set_path(&mut self, path: impl Into<PathBuf>) {
+        self.path = path.into();
+        self.mark_seen();
+    }
+}
+
+fn modified_time(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|metadata| metadata.modified()).ok()
+}